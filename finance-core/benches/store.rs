@@ -0,0 +1,213 @@
+// Работа одного кадра таблицы операций: страница строк в порядке дат с именем
+// счёта и остатком после операции. Индексированный Store сравнивается с прямым
+// проходом по Database; время кадра Store не должно расти с размером базы.
+//
+// cargo bench -p finance-core
+
+use chrono::{Duration, NaiveDate};
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use finance_core::account::{Account, AccountType};
+use finance_core::database::Database;
+use finance_core::dedup;
+use finance_core::exchange::ImportResult;
+use finance_core::operation::{FinanseDirection, Operation, OperationType};
+use finance_core::receipt::Currency;
+use finance_core::store::Store;
+use finance_core::view::{SavedView, TableView};
+
+const ACCOUNTS: usize = 10;
+const PAGE: usize = 50;
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+// Операций в одном импорте
+const IMPORT: usize = 100;
+
+fn database(size: usize) -> Database {
+    let mut db = Database::new();
+    for index in 0..ACCOUNTS {
+        db.accounts.push(Account {
+            id: Uuid::new_v4(),
+            name: format!("Счёт {}", index),
+            account_type: AccountType::Account,
+            number: String::new(),
+            bik: 0,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Currency::Rub,
+        });
+    }
+    let start = NaiveDate::from_ymd_opt(2020, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    // Детерминированный разброс дат, чтобы операции лежали не по порядку
+    let mut seed: u64 = 1;
+    for index in 0..size {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        db.operations.push(Operation {
+            id: Uuid::new_v4(),
+            date_time: start + Duration::minutes((seed >> 40) as i64 % 3_000_000),
+            account_id: db.accounts[index % ACCOUNTS].id,
+            operation_type: OperationType::Buy,
+            summary: Decimal::new((seed >> 20) as i64 % 100_000, 2),
+            direction: match index % 3 {
+                0 => FinanseDirection::Debet,
+                _ => FinanseDirection::Credit,
+            },
+            receipt_id: None,
+            reference: None,
+            counterparty: None,
+            note: None,
+            category: None,
+            transfer_id: None,
+        });
+    }
+    db
+}
+
+fn signed(operation: &Operation) -> Decimal {
+    match operation.direction {
+        FinanseDirection::Debet => operation.summary,
+        FinanseDirection::Credit => -operation.summary,
+    }
+}
+
+// Последняя страница без индексов: сортировка, поиск счёта и остаток проходом
+fn linear_frame(db: &Database) -> Decimal {
+    let mut operations: Vec<&Operation> = db.operations.iter().collect();
+    operations.sort_by_key(|operation| (operation.date_time, operation.id));
+    let mut total = Decimal::ZERO;
+    for operation in operations.iter().rev().take(PAGE) {
+        let account = db
+            .accounts
+            .iter()
+            .find(|account| account.id == operation.account_id);
+        black_box(account);
+        let balance: Decimal = operations
+            .iter()
+            .filter(|other| other.account_id == operation.account_id)
+            .take_while(|other| (other.date_time, other.id) <= (operation.date_time, operation.id))
+            .map(|other| signed(other))
+            .sum();
+        total += balance;
+    }
+    total
+}
+
+fn store_frame(store: &Store, first: usize) -> Decimal {
+    let mut total = Decimal::ZERO;
+    for position in first..(first + PAGE).min(store.operation_count()) {
+        let operation = store.operation_at(position).unwrap();
+        black_box(store.account(operation.account_id));
+        total += store.running_balance(operation.id).unwrap();
+    }
+    total
+}
+
+fn frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    group.sample_size(10);
+    for size in SIZES {
+        let db = database(size);
+        group.bench_with_input(BenchmarkId::new("linear", size), &db, |b, db| {
+            b.iter(|| linear_frame(db))
+        });
+        let store = Store::new(db);
+        let first = store.operation_count().saturating_sub(PAGE);
+        store_frame(&store, first);
+        group.bench_with_input(BenchmarkId::new("store", size), &store, |b, store| {
+            b.iter(|| store_frame(store, first))
+        });
+    }
+    group.finish();
+}
+
+// Правка одной операции в середине базы и следующий кадр: кэш остатков
+// пересчитывается только от места правки
+fn edit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("edit");
+    group.sample_size(10);
+    for size in SIZES {
+        let mut store = Store::new(database(size));
+        let first = store.operation_count().saturating_sub(PAGE);
+        let mut operation = store
+            .operation_at(store.operation_count() / 2)
+            .unwrap()
+            .clone();
+        group.bench_function(BenchmarkId::new("store", size), |b| {
+            b.iter(|| {
+                operation.summary += Decimal::ONE;
+                store.upsert_operation(operation.clone());
+                store_frame(&store, first)
+            })
+        });
+    }
+    group.finish();
+}
+
+// Запись правок в историю и журнал. Импорт и сохранение вида идут через
+// upsert_* и копируют только изменённые записи; update копирует базу целиком
+// и остаётся только для исправления целостности.
+fn commit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("commit");
+    group.sample_size(10);
+    for size in SIZES {
+        let mut store = Store::new(database(size));
+        let account_id = store.accounts[0].id;
+        let mut operation = store
+            .operation_at(store.operation_count() / 2)
+            .unwrap()
+            .clone();
+        group.bench_function(BenchmarkId::new("import", size), |b| {
+            b.iter(|| {
+                let mut operations = database(IMPORT).operations;
+                for operation in operations.iter_mut() {
+                    operation.account_id = account_id;
+                }
+                let import = ImportResult {
+                    operations,
+                    ..Default::default()
+                };
+                dedup::commit(&mut store, import, &[], &[]);
+                black_box(store.commit().is_some());
+                // Отмена возвращает базу к исходному размеру
+                store.undo()
+            })
+        });
+        let mut view = SavedView {
+            id: Uuid::new_v4(),
+            name: "Вид".to_string(),
+            query: String::new(),
+            table: TableView::default(),
+        };
+        group.bench_function(BenchmarkId::new("view", size), |b| {
+            b.iter(|| {
+                view.query = match view.query.as_str() {
+                    "" => "category:еда".to_string(),
+                    _ => String::new(),
+                };
+                store.upsert_view(view.clone());
+                black_box(store.commit().is_some())
+            })
+        });
+        group.bench_function(BenchmarkId::new("update", size), |b| {
+            b.iter(|| {
+                operation.summary += Decimal::ONE;
+                let edited = operation.clone();
+                store.update(|db| {
+                    let index = db.operations.iter().position(|o| o.id == edited.id);
+                    db.operations[index.unwrap()] = edited;
+                });
+                black_box(store.commit().is_some())
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, frame, edit, commit);
+criterion_main!(benches);
@@ -2,6 +2,9 @@ use serde::{Deserialize, Serialize};
 use strum::EnumIter;
 use uuid::Uuid;
 
+use crate::card::Card;
+use crate::receipt::Currency;
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, EnumIter)]
 pub enum AccountType {
     Account,
@@ -13,7 +16,7 @@ pub enum AccountType {
     Deposit,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Account {
     pub id: Uuid,
     pub name: String,
@@ -21,6 +24,16 @@ pub struct Account {
     pub number: String,
     pub bik: u32,
     pub sum: usize,
+    #[serde(default)]
+    pub cards: Vec<Card>,
+    #[serde(default)]
+    pub currency: Currency,
+}
+
+impl AccountType {
+    pub fn has_cards(&self) -> bool {
+        *self == AccountType::DebetCard || *self == AccountType::CreditCard
+    }
 }
 
 // impl Account {
@@ -0,0 +1,171 @@
+// Журнал изменений: кто, когда и что поменял в счетах, операциях и чеках. Store
+// дописывает журнал при каждой команде истории, а также при её отмене и повторе;
+// записи только добавляются, изменить или удалить их нельзя.
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::history::Change;
+use crate::report::{Cell, Sheet};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub date_time: NaiveDateTime,
+    // Имя пользователя или устройства
+    pub user: String,
+    // Команда, в составе которой сделано изменение
+    pub command: String,
+    pub change: Change,
+}
+
+impl AuditEntry {
+    // Состояние записи до и после в виде JSON; пусто, если записи не было
+    pub fn before(&self) -> Result<String, serde_json::Error> {
+        Ok(self.change.before_json()?.unwrap_or_default())
+    }
+
+    pub fn after(&self) -> Result<String, serde_json::Error> {
+        Ok(self.change.after_json()?.unwrap_or_default())
+    }
+}
+
+// Пользователь системы и имя компьютера: user@host
+pub fn default_user() -> String {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default();
+    let host = std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .map(|host| host.trim().to_string())
+        .unwrap_or_default();
+    match (user.is_empty(), host.is_empty()) {
+        (false, false) => format!("{}@{}", user, host),
+        (false, true) => user,
+        (true, _) => host,
+    }
+}
+
+// Записи журнала по изменениям команды; в журнал попадают только счета,
+// операции и чеки
+pub(crate) fn entries(
+    user: &str,
+    command: &str,
+    changes: impl Iterator<Item = Change>,
+) -> Vec<AuditEntry> {
+    let date_time = Local::now().naive_local();
+    changes
+        .flat_map(|change| change.records().to_vec())
+        .filter(|change| {
+            matches!(
+                change,
+                Change::Account { .. } | Change::Operation { .. } | Change::Receipt { .. }
+            )
+        })
+        .map(|change| AuditEntry {
+            id: Uuid::new_v4(),
+            date_time,
+            user: user.to_string(),
+            command: command.to_string(),
+            change,
+        })
+        .collect()
+}
+
+// Отбор записей журнала; пустое поле не отбирает
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditFilter {
+    // "account", "operation" или "receipt"
+    pub entity: String,
+    pub user: String,
+    // Подстрока команды, идентификатора записи или даты в виде ГГГГ-ММ-ДД
+    pub text: String,
+}
+
+impl AuditFilter {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        let contains = |value: &str, filter: &str| {
+            value.to_lowercase().contains(&filter.trim().to_lowercase())
+        };
+        (self.entity.is_empty() || entry.change.entity() == self.entity)
+            && contains(&entry.user, &self.user)
+            && (contains(&entry.command, &self.text)
+                || entry
+                    .change
+                    .id()
+                    .is_some_and(|id| contains(&id.to_string(), &self.text))
+                || contains(
+                    &entry.date_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    &self.text,
+                ))
+    }
+}
+
+pub fn sheet(entries: &[&AuditEntry]) -> Result<Sheet, serde_json::Error> {
+    let mut sheet = Sheet::new(
+        "Audit",
+        &[
+            "Date", "User", "Command", "Action", "Entity", "Id", "Before", "After",
+        ],
+    );
+    for entry in entries.iter() {
+        sheet.rows.push(vec![
+            Cell::DateTime(entry.date_time),
+            Cell::Text(entry.user.clone()),
+            Cell::Text(entry.command.clone()),
+            Cell::Text(entry.change.action().to_string()),
+            Cell::Text(entry.change.entity().to_string()),
+            entry
+                .change
+                .id()
+                .map(|id| Cell::Text(id.to_string()))
+                .unwrap_or(Cell::Empty),
+            Cell::Text(entry.before()?),
+            Cell::Text(entry.after()?),
+        ]);
+    }
+    Ok(sheet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::view::{SavedView, TableView};
+
+    #[test]
+    fn entries_and_states() {
+        let view = SavedView {
+            id: Uuid::new_v4(),
+            name: "Еда".to_string(),
+            query: String::new(),
+            table: TableView::default(),
+        };
+        let changes = vec![
+            Change::View {
+                before: None,
+                after: Some(view.clone()),
+            },
+            Change::Profiles {
+                before: Vec::new(),
+                after: Vec::new(),
+            },
+        ];
+        // Виды и профили CSV в журнал не попадают
+        assert!(entries("user", "Create view", changes.clone().into_iter()).is_empty());
+
+        let entry = AuditEntry {
+            id: Uuid::new_v4(),
+            date_time: NaiveDateTime::default(),
+            user: "user@host".to_string(),
+            command: "Create view".to_string(),
+            change: changes[0].clone(),
+        };
+        assert_eq!(entry.before().unwrap(), "");
+        let after: SavedView = serde_json::from_str(&entry.after().unwrap()).unwrap();
+        assert_eq!(after, view);
+        let sheet = sheet(&[&entry]).unwrap();
+        assert_eq!(sheet.rows.len(), 1);
+    }
+}
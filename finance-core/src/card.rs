@@ -0,0 +1,344 @@
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use strum::EnumIter;
+use uuid::Uuid;
+
+use crate::account::Account;
+
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum PaymentSystem {
+    #[default]
+    Mir,
+    Visa,
+    MasterCard,
+    UnionPay,
+    Jcb,
+    AmericanExpress,
+    Other,
+}
+
+impl PaymentSystem {
+    // Платёжная система по первым цифрам номера (BIN)
+    pub fn detect(pan: &str) -> Self {
+        let digits = digits(pan);
+        let prefix = |len: usize| -> u32 {
+            digits
+                .get(..len)
+                .and_then(|p| p.parse().ok())
+                .unwrap_or_default()
+        };
+        match (prefix(1), prefix(2), prefix(4)) {
+            (_, _, 2200..=2204) => PaymentSystem::Mir,
+            (4, _, _) => PaymentSystem::Visa,
+            (_, 51..=55, _) | (_, _, 2221..=2720) => PaymentSystem::MasterCard,
+            (_, 62, _) | (_, 81, _) => PaymentSystem::UnionPay,
+            (_, _, 3528..=3589) => PaymentSystem::Jcb,
+            (_, 34, _) | (_, 37, _) => PaymentSystem::AmericanExpress,
+            _ => PaymentSystem::Other,
+        }
+    }
+}
+
+impl fmt::Display for PaymentSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PaymentSystem::Mir => "МИР",
+            PaymentSystem::Visa => "Visa",
+            PaymentSystem::MasterCard => "MasterCard",
+            PaymentSystem::UnionPay => "UnionPay",
+            PaymentSystem::Jcb => "JCB",
+            PaymentSystem::AmericanExpress => "American Express",
+            PaymentSystem::Other => "Other",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct CardExpiry {
+    pub month: u32,
+    pub year: u32,
+}
+
+impl CardExpiry {
+    // Срок действия в виде "MM/YY" или "MM/YYYY"
+    pub fn parse(text: &str) -> Option<Self> {
+        let (month, year) = text.trim().split_once('/')?;
+        let month: u32 = month.trim().parse().ok()?;
+        let year: u32 = year.trim().parse().ok()?;
+        if !(1..=12).contains(&month) {
+            return None;
+        }
+        let year = if year < 100 { 2000 + year } else { year };
+        Some(Self { month, year })
+    }
+}
+
+impl fmt::Display for CardExpiry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}/{:02}", self.month, self.year % 100)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CardError {
+    Length,
+    Luhn,
+}
+
+impl fmt::Display for CardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CardError::Length => write!(f, "Номер карты должен содержать от 13 до 19 цифр"),
+            CardError::Luhn => write!(f, "Номер карты не проходит проверку по алгоритму Луна"),
+        }
+    }
+}
+
+// Карта, привязанная к карточному счёту. Полный номер не хранится.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Card {
+    pub id: Uuid,
+    pub masked_pan: String,
+    pub payment_system: PaymentSystem,
+    pub expiry: Option<CardExpiry>,
+    pub holder: Option<String>,
+}
+
+impl Card {
+    pub fn new(
+        pan: &str,
+        expiry: Option<CardExpiry>,
+        holder: Option<String>,
+    ) -> Result<Self, CardError> {
+        let digits = digits(pan);
+        if digits.len() < 13 || digits.len() > 19 {
+            return Err(CardError::Length);
+        }
+        if !luhn_valid(&digits) {
+            return Err(CardError::Luhn);
+        }
+        Ok(Self {
+            id: Uuid::new_v4(),
+            masked_pan: mask_pan(&digits),
+            payment_system: PaymentSystem::detect(&digits),
+            expiry,
+            holder,
+        })
+    }
+
+    // Номер в файле могли исправить руками, поэтому он может оказаться короче
+    pub fn first_six(&self) -> Option<&str> {
+        self.masked_pan.get(..6)
+    }
+
+    pub fn last_four(&self) -> Option<&str> {
+        let start = self.masked_pan.len().checked_sub(4)?;
+        self.masked_pan.get(start..)
+    }
+}
+
+fn digits(text: &str) -> String {
+    text.chars().filter(|c| c.is_ascii_digit()).collect()
+}
+
+pub fn luhn_valid(pan: &str) -> bool {
+    let digits = digits(pan);
+    if digits.is_empty() {
+        return false;
+    }
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, d)| {
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                d
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+// Маскирование номера: остаются первые шесть и последние четыре знака (как в слипах).
+// Звёздочки уже замаскированного номера считаются знаками номера, цифры в
+// середине маскируются всегда. Неполные номера возвращаются без изменений.
+pub fn mask_pan(pan: &str) -> String {
+    let symbols: Vec<char> = pan
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '*')
+        .collect();
+    if symbols.len() < 13 {
+        return pan.to_string();
+    }
+    let end = symbols.len() - 4;
+    symbols
+        .iter()
+        .enumerate()
+        .map(|(i, c)| if (6..end).contains(&i) { '*' } else { *c })
+        .collect()
+}
+
+pub fn serialize_masked<S: Serializer>(pan: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&mask_pan(pan))
+}
+
+// Счёт, которому принадлежит карта с указанным (полным, замаскированным или
+// сокращённым до последних цифр) номером. Если совпадают первые шесть цифр,
+// выбирается эта карта; карты с другими первыми шестью цифрами не подходят.
+// По одним последним четырём цифрам счёт определяется, только если такая карта
+// единственная.
+pub fn card_account(accounts: &[Account], card: &str) -> Option<Uuid> {
+    let digits = digits(card);
+    if digits.len() < 4 {
+        return None;
+    }
+    let last_four = &digits[digits.len() - 4..];
+    let first_six = (digits.len() >= 10).then(|| &digits[..6]);
+    let candidates: Vec<(&Account, &Card)> = accounts
+        .iter()
+        .flat_map(|account| account.cards.iter().map(move |c| (account, c)))
+        .filter(|(_, c)| c.last_four() == Some(last_four))
+        .filter(|(_, c)| match (first_six, c.first_six()) {
+            (Some(first_six), Some(known)) if known.chars().all(|c| c.is_ascii_digit()) => {
+                known == first_six
+            }
+            _ => true,
+        })
+        .collect();
+    if let Some(first_six) = first_six
+        && let Some((account, _)) = candidates
+            .iter()
+            .find(|(_, c)| c.first_six() == Some(first_six))
+    {
+        return Some(account.id);
+    }
+    match candidates.as_slice() {
+        [(account, _)] => Some(account.id),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::AccountType;
+    use crate::receipt::Currency;
+
+    fn account(cards: Vec<Card>) -> Account {
+        Account {
+            id: Uuid::new_v4(),
+            name: "Card".to_string(),
+            account_type: AccountType::DebetCard,
+            number: String::new(),
+            bik: 0,
+            sum: 0,
+            cards,
+            currency: Currency::default(),
+        }
+    }
+
+    #[test]
+    fn luhn() {
+        assert!(luhn_valid("4111 1111 1111 1111"));
+        assert!(luhn_valid("5555555555554444"));
+        assert!(luhn_valid("2200000000000004"));
+        assert!(luhn_valid("378282246310005"));
+        assert!(!luhn_valid("4111111111111112"));
+        assert!(!luhn_valid("2200000000000005"));
+        assert!(!luhn_valid(""));
+    }
+
+    #[test]
+    fn new_card() {
+        let card = Card::new("2200 0000 0000 0004", None, None).unwrap();
+        assert_eq!(card.masked_pan, "220000******0004");
+        assert_eq!(card.payment_system, PaymentSystem::Mir);
+        assert_eq!(card.first_six(), Some("220000"));
+        assert_eq!(card.last_four(), Some("0004"));
+        assert_eq!(
+            Card::new("4111111111111112", None, None),
+            Err(CardError::Luhn)
+        );
+        assert_eq!(Card::new("411111", None, None), Err(CardError::Length));
+    }
+
+    #[test]
+    fn masking() {
+        assert_eq!(mask_pan("4111-1111-1111-1111"), "411111******1111");
+        assert_eq!(mask_pan("378282246310005"), "378282*****0005");
+        assert_eq!(mask_pan("411111******1111"), "411111******1111");
+        assert_eq!(mask_pan("1111"), "1111");
+        // Звёздочка не защищает полный номер от маскирования
+        assert_eq!(mask_pan("4111111111111111*"), "411111*******111*");
+        assert_eq!(mask_pan("*4111 1111 1111 1111"), "*41111*******1111");
+        assert_eq!(mask_pan("4111 11** 1111 1111"), "411111******1111");
+    }
+
+    #[test]
+    fn payment_system() {
+        assert_eq!(
+            PaymentSystem::detect("4111111111111111"),
+            PaymentSystem::Visa
+        );
+        assert_eq!(
+            PaymentSystem::detect("5555555555554444"),
+            PaymentSystem::MasterCard
+        );
+        assert_eq!(
+            PaymentSystem::detect("2221000000000009"),
+            PaymentSystem::MasterCard
+        );
+        assert_eq!(
+            PaymentSystem::detect("378282246310005"),
+            PaymentSystem::AmericanExpress
+        );
+        assert_eq!(PaymentSystem::detect("9"), PaymentSystem::Other);
+    }
+
+    #[test]
+    fn expiry() {
+        assert_eq!(
+            CardExpiry::parse("07/29"),
+            Some(CardExpiry {
+                month: 7,
+                year: 2029
+            })
+        );
+        assert_eq!(CardExpiry::parse("13/29"), None);
+        assert_eq!(CardExpiry::parse("0729"), None);
+        assert_eq!(CardExpiry::parse("7/2031").unwrap().to_string(), "07/31");
+    }
+
+    #[test]
+    fn short_masked_pan() {
+        let mut card = Card::new("4111111111111111", None, None).unwrap();
+        card.masked_pan = "111".to_string();
+        assert_eq!(card.first_six(), None);
+        assert_eq!(card.last_four(), None);
+        card.masked_pan = "•111".to_string();
+        assert_eq!(card.last_four(), None);
+
+        let accounts = vec![account(vec![card])];
+        assert_eq!(card_account(&accounts, "4111111111111111"), None);
+    }
+
+    #[test]
+    fn account_by_card() {
+        let visa = account(vec![Card::new("4111111111111111", None, None).unwrap()]);
+        let mir = account(vec![Card::new("2200000000401111", None, None).unwrap()]);
+        let accounts = vec![visa.clone(), mir.clone()];
+        // По последним цифрам карты неразличимы, выбор по первым шести
+        assert_eq!(card_account(&accounts, "411111******1111"), Some(visa.id));
+        assert_eq!(card_account(&accounts, "2200001234561111"), Some(mir.id));
+        assert_eq!(card_account(&accounts, "*1111"), None);
+        assert_eq!(card_account(&accounts[..1], "*1111"), Some(visa.id));
+        assert_eq!(card_account(&accounts, "11"), None);
+        // Первые шесть цифр другой карты: последних четырёх мало
+        assert_eq!(card_account(&accounts[..1], "550000******1111"), None);
+    }
+}
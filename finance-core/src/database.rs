@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+use crate::account::*;
+use crate::audit::AuditEntry;
+use crate::exchange::csv::CsvProfile;
+use crate::history::History;
+use crate::operation::*;
+use crate::receipt::*;
+use crate::trash::Deleted;
+use crate::view::SavedView;
+
+const VERSION: &str = "0.0.1";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Database {
+    db_version: String,
+    pub accounts: Vec<Account>,
+    pub operations: Vec<Operation>,
+    pub receipts: Vec<Receipt>,
+    #[serde(default)]
+    pub csv_profiles: Vec<CsvProfile>,
+    #[serde(default)]
+    pub views: Vec<SavedView>,
+    #[serde(default)]
+    pub trash: Vec<Deleted>,
+    #[serde(default, skip_serializing_if = "History::is_empty")]
+    pub history: History,
+    // Хранилище JSON держит журнал в отдельном файле; в старых файлах он здесь
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audit: Vec<AuditEntry>,
+}
+
+// /home/user/rust_projects/file.json
+
+impl Default for Database {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Чтение и запись файлов - в storage
+impl Database {
+    pub fn new() -> Self {
+        Self {
+            db_version: VERSION.to_string(),
+            accounts: Vec::new(),
+            operations: Vec::new(),
+            receipts: Vec::new(),
+            csv_profiles: Vec::new(),
+            views: Vec::new(),
+            trash: Vec::new(),
+            history: History::default(),
+            audit: Vec::new(),
+        }
+    }
+}
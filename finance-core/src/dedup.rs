@@ -0,0 +1,330 @@
+use chrono::{NaiveDate, TimeDelta};
+use rust_decimal::Decimal;
+use std::collections::HashSet;
+use strum::EnumIter;
+use uuid::Uuid;
+
+use crate::exchange::ImportResult;
+use crate::operation::{FinanseDirection, Operation};
+use crate::store::Store;
+
+// Одна и та же операция в выписках за разные периоды может иметь дату
+// совершения или дату проведения
+const DATE_WINDOW_DAYS: i64 = 3;
+
+// Признаки, по которым операции из разных источников считаются одной
+#[derive(Debug, PartialEq, Clone)]
+pub struct Fingerprint {
+    pub account_id: Uuid,
+    pub date: NaiveDate,
+    pub summary: Decimal,
+    pub direction: FinanseDirection,
+    pub counterparty: Option<String>,
+    pub reference: Option<String>,
+}
+
+fn normalize(text: &Option<String>) -> Option<String> {
+    let text: String = text
+        .as_deref()?
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    (!text.is_empty()).then_some(text)
+}
+
+impl Fingerprint {
+    pub fn new(operation: &Operation) -> Self {
+        Self {
+            account_id: operation.account_id,
+            date: operation.date_time.date(),
+            summary: operation.summary,
+            direction: operation.direction.clone(),
+            counterparty: normalize(&operation.counterparty),
+            reference: normalize(&operation.reference),
+        }
+    }
+
+    // Насколько вероятно, что это одна и та же операция
+    pub fn compare(&self, other: &Fingerprint) -> Option<Confidence> {
+        if self.account_id != other.account_id
+            || self.summary != other.summary
+            || self.direction != other.direction
+            || (self.date - other.date).abs() > TimeDelta::days(DATE_WINDOW_DAYS)
+        {
+            return None;
+        }
+        match (&self.reference, &other.reference) {
+            (Some(a), Some(b)) if a == b => return Some(Confidence::Certain),
+            (Some(_), Some(_)) => return None,
+            _ => {}
+        }
+        let same_counterparty = match (&self.counterparty, &other.counterparty) {
+            (Some(a), Some(b)) => a.contains(b.as_str()) || b.contains(a.as_str()),
+            _ => false,
+        };
+        if same_counterparty || self.date == other.date {
+            Some(Confidence::Likely)
+        } else {
+            Some(Confidence::Possible)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Confidence {
+    Possible,
+    Likely,
+    Certain,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum Resolution {
+    Keep,  // Добавить как новую операцию
+    Skip,  // Не добавлять
+    Merge, // Дополнить имеющуюся операцию недостающими полями
+}
+
+// Импортируемая операция, похожая на уже имеющуюся (в базе или ранее в том же импорте)
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub incoming: usize,
+    pub existing: Uuid,
+    pub confidence: Confidence,
+}
+
+impl Candidate {
+    pub fn default_resolution(&self) -> Resolution {
+        match self.confidence {
+            Confidence::Possible => Resolution::Keep,
+            Confidence::Likely | Confidence::Certain => Resolution::Skip,
+        }
+    }
+}
+
+pub fn find_duplicates(existing: &[Operation], incoming: &[Operation]) -> Vec<Candidate> {
+    let known: Vec<(Uuid, Fingerprint)> = existing
+        .iter()
+        .map(|operation| (operation.id, Fingerprint::new(operation)))
+        .collect();
+    let fresh: Vec<Fingerprint> = incoming.iter().map(Fingerprint::new).collect();
+    let mut candidates = Vec::new();
+    for (i, fingerprint) in fresh.iter().enumerate() {
+        let earlier = incoming[..i]
+            .iter()
+            .zip(fresh[..i].iter())
+            .map(|(operation, other)| (operation.id, other));
+        let best = known
+            .iter()
+            .map(|(id, other)| (*id, other))
+            .chain(earlier)
+            .filter_map(|(id, other)| Some((id, fingerprint.compare(other)?)))
+            .max_by_key(|(_, confidence)| *confidence);
+        if let Some((existing, confidence)) = best {
+            candidates.push(Candidate {
+                incoming: i,
+                existing,
+                confidence,
+            });
+        }
+    }
+    candidates
+}
+
+fn merge(target: &mut Operation, source: &Operation) {
+    for (field, value) in [
+        (&mut target.reference, &source.reference),
+        (&mut target.counterparty, &source.counterparty),
+        (&mut target.note, &source.note),
+        (&mut target.category, &source.category),
+    ] {
+        if field.is_none() {
+            *field = value.clone();
+        }
+    }
+}
+
+// Запись результата импорта в базу с учётом решения по каждому кандидату.
+// Изменения идут через Store и попадают в историю по одной записи.
+pub fn commit(
+    store: &mut Store,
+    import: ImportResult,
+    candidates: &[Candidate],
+    resolutions: &[Resolution],
+) {
+    let decision = |i: usize| {
+        candidates
+            .iter()
+            .zip(resolutions.iter())
+            .find(|(candidate, _)| candidate.incoming == i)
+            .map(|(candidate, resolution)| (candidate.existing, *resolution))
+    };
+    let added: HashSet<Uuid> = import
+        .operations
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| matches!(decision(*i), None | Some((_, Resolution::Keep))))
+        .map(|(_, operation)| operation.id)
+        .collect();
+    for account in import.accounts {
+        store.upsert_account(account);
+    }
+    for (i, mut operation) in import.operations.into_iter().enumerate() {
+        match decision(i) {
+            Some((_, Resolution::Skip)) => {}
+            Some((existing, Resolution::Merge)) => {
+                if let Some(mut element) = store.operation(existing).cloned() {
+                    merge(&mut element, &operation);
+                    store.upsert_operation(element);
+                }
+            }
+            _ => {
+                // Вторая сторона перевода могла быть пропущена
+                if let Some(pair) = operation.transfer_id
+                    && !added.contains(&pair)
+                    && store.operation(pair).is_none()
+                {
+                    operation.transfer_id = None;
+                }
+                store.upsert_operation(operation);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::operation::OperationType;
+    use rust_decimal::dec;
+
+    fn operation(account_id: Uuid, day: u32, counterparty: Option<&str>) -> Operation {
+        Operation {
+            id: Uuid::new_v4(),
+            date_time: NaiveDate::from_ymd_opt(2026, 3, day).unwrap().into(),
+            account_id,
+            operation_type: OperationType::Buy,
+            summary: dec!(250),
+            direction: FinanseDirection::Credit,
+            receipt_id: None,
+            reference: None,
+            counterparty: counterparty.map(str::to_string),
+            note: None,
+            category: None,
+            transfer_id: None,
+        }
+    }
+
+    fn compare(a: &Operation, b: &Operation) -> Option<Confidence> {
+        Fingerprint::new(a).compare(&Fingerprint::new(b))
+    }
+
+    #[test]
+    fn confidence() {
+        let account = Uuid::new_v4();
+        let base = operation(account, 10, Some("ООО \"Ромашка\""));
+
+        let mut same_reference = operation(account, 12, None);
+        let mut base_with_reference = base.clone();
+        base_with_reference.reference = Some("AB-12".to_string());
+        same_reference.reference = Some("ab 12".to_string());
+        assert_eq!(
+            compare(&base_with_reference, &same_reference),
+            Some(Confidence::Certain)
+        );
+        same_reference.reference = Some("AB-13".to_string());
+        assert_eq!(compare(&base_with_reference, &same_reference), None);
+
+        let counterparty = operation(account, 12, Some("Ромашка"));
+        assert_eq!(compare(&base, &counterparty), Some(Confidence::Likely));
+        let same_day = operation(account, 10, Some("Другой магазин"));
+        assert_eq!(compare(&base, &same_day), Some(Confidence::Likely));
+        let near = operation(account, 13, None);
+        assert_eq!(compare(&base, &near), Some(Confidence::Possible));
+
+        assert_eq!(compare(&base, &operation(account, 14, None)), None);
+        assert_eq!(compare(&base, &operation(Uuid::new_v4(), 10, None)), None);
+        let mut other_sum = operation(account, 10, None);
+        other_sum.summary = dec!(251);
+        assert_eq!(compare(&base, &other_sum), None);
+        let mut other_direction = operation(account, 10, None);
+        other_direction.direction = FinanseDirection::Debet;
+        assert_eq!(compare(&base, &other_direction), None);
+    }
+
+    #[test]
+    fn best_candidate() {
+        let account = Uuid::new_v4();
+        let possible = operation(account, 8, None);
+        let likely = operation(account, 10, None);
+        let incoming = vec![
+            operation(account, 10, None),
+            operation(account, 20, None),
+            operation(account, 20, None),
+        ];
+        let candidates = find_duplicates(&[possible, likely.clone()], &incoming);
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].incoming, 0);
+        assert_eq!(candidates[0].existing, likely.id);
+        assert_eq!(candidates[0].confidence, Confidence::Likely);
+        // Повтор внутри одного файла
+        assert_eq!(candidates[1].incoming, 2);
+        assert_eq!(candidates[1].existing, incoming[1].id);
+        assert_eq!(candidates[1].default_resolution(), Resolution::Skip);
+        let possible = Candidate {
+            incoming: 0,
+            existing: Uuid::nil(),
+            confidence: Confidence::Possible,
+        };
+        assert_eq!(possible.default_resolution(), Resolution::Keep);
+    }
+
+    #[test]
+    fn commit_resolutions() {
+        let account = Uuid::new_v4();
+        let existing = operation(account, 10, None);
+        let mut db = Database::new();
+        db.operations.push(existing.clone());
+        let mut store = Store::new(db);
+
+        let mut merged = operation(account, 10, Some("Ромашка"));
+        merged.category = Some("Еда".to_string());
+        let skipped = operation(account, 10, None);
+        let kept = operation(account, 11, None);
+        let mut transfer = operation(account, 12, None);
+        transfer.transfer_id = Some(skipped.id);
+        let import = ImportResult {
+            operations: vec![merged, skipped, kept.clone(), transfer.clone()],
+            ..Default::default()
+        };
+        let candidates = vec![
+            Candidate {
+                incoming: 0,
+                existing: existing.id,
+                confidence: Confidence::Likely,
+            },
+            Candidate {
+                incoming: 1,
+                existing: existing.id,
+                confidence: Confidence::Likely,
+            },
+        ];
+        commit(
+            &mut store,
+            import,
+            &candidates,
+            &[Resolution::Merge, Resolution::Skip],
+        );
+        let command = store.commit().unwrap();
+        // Одна правка и две новые операции
+        assert_eq!(command.changes.len(), 3);
+
+        let ids: Vec<Uuid> = store.operations.iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec![existing.id, kept.id, transfer.id]);
+        assert_eq!(store.operations[0].counterparty.as_deref(), Some("Ромашка"));
+        assert_eq!(store.operations[0].category.as_deref(), Some("Еда"));
+        // Вторая сторона перевода пропущена, ссылка на неё снимается
+        assert_eq!(store.operations[2].transfer_id, None);
+    }
+}
@@ -0,0 +1,400 @@
+// Импорт банковской выписки в формате обмена с 1С:Предприятием (1CClientBankExchange)
+
+use chrono::{NaiveDate, NaiveDateTime};
+use encoding_rs::{IBM866, WINDOWS_1251};
+use rust_decimal::Decimal;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::account::Account;
+use crate::database::Database;
+use crate::exchange::{ImportError, ImportResult, link_pair};
+use crate::money::parse_decimal;
+use crate::operation::{FinanseDirection, Operation, OperationType};
+
+const HEADER: &str = "1CClientBankExchange";
+
+// Платёжный документ из секции СекцияДокумент
+#[derive(Debug, Default, Clone)]
+pub struct Document {
+    pub kind: String,
+    pub number: String,
+    pub date: Option<NaiveDate>,
+    pub summary: Decimal,
+    pub payer_account: String,
+    pub payer: String,
+    pub payee_account: String,
+    pub payee: String,
+    pub written_off: Option<NaiveDate>,
+    pub received: Option<NaiveDate>,
+    pub purpose: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Statement {
+    pub accounts: Vec<String>,
+    pub documents: Vec<Document>,
+}
+
+// Файл выгружается в windows-1251 (Кодировка=Windows), реже в cp866 (Кодировка=DOS);
+// некоторые банки отдают UTF-8
+pub fn decode(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+    let (text, _, _) = WINDOWS_1251.decode(bytes);
+    if text.contains("Кодировка=DOS") {
+        let (text, _, _) = IBM866.decode(bytes);
+        return text.into_owned();
+    }
+    text.into_owned()
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.trim(), "%d.%m.%Y").ok()
+}
+
+pub fn parse(text: &str) -> Result<Statement, ImportError> {
+    let mut lines = text.lines().map(|line| line.trim());
+    if lines.next() != Some(HEADER) {
+        return Err(ImportError::Format(format!("нет заголовка {}", HEADER)));
+    }
+
+    let mut statement = Statement::default();
+    let mut document: Option<Document> = None;
+    for line in lines {
+        let (key, value) = line.split_once('=').unwrap_or((line, ""));
+        match key {
+            "СекцияДокумент" => {
+                document = Some(Document {
+                    kind: value.to_string(),
+                    ..Default::default()
+                })
+            }
+            "КонецДокумента" => {
+                let element = document
+                    .take()
+                    .ok_or_else(|| ImportError::Format("КонецДокумента без начала".to_string()))?;
+                statement.documents.push(element);
+            }
+            "КонецФайла" => break,
+            _ => {
+                if let Some(element) = &mut document {
+                    match key {
+                        "Номер" => element.number = value.to_string(),
+                        "Дата" => element.date = parse_date(value),
+                        "Сумма" => {
+                            element.summary = parse_decimal(value).ok_or_else(|| {
+                                ImportError::Format(format!("неверная сумма {}", value))
+                            })?
+                        }
+                        "ПлательщикСчет" => element.payer_account = value.to_string(),
+                        "ПолучательСчет" => element.payee_account = value.to_string(),
+                        // Плательщик1 содержит только наименование, без ИНН
+                        "Плательщик1" => element.payer = value.to_string(),
+                        "Плательщик" if element.payer.is_empty() => {
+                            element.payer = value.to_string()
+                        }
+                        "Получатель1" => element.payee = value.to_string(),
+                        "Получатель" if element.payee.is_empty() => {
+                            element.payee = value.to_string()
+                        }
+                        "ДатаСписано" => element.written_off = parse_date(value),
+                        "ДатаПоступило" => element.received = parse_date(value),
+                        "НазначениеПлатежа" => element.purpose = value.to_string(),
+                        _ => {}
+                    }
+                } else if key == "РасчСчет"
+                    && !value.is_empty()
+                    && !statement.accounts.iter().any(|account| account == value)
+                {
+                    statement.accounts.push(value.to_string());
+                }
+            }
+        }
+    }
+    Ok(statement)
+}
+
+pub fn read(path: &str) -> Result<Statement, ImportError> {
+    let bytes = std::fs::read(path)?;
+    parse(&decode(&bytes))
+}
+
+fn same_account(number: &str, other: &str) -> bool {
+    let digits = |text: &str| -> String { text.chars().filter(|c| c.is_ascii_digit()).collect() };
+    !number.is_empty() && digits(number) == digits(other)
+}
+
+fn operation(
+    account_id: Uuid,
+    date: NaiveDate,
+    document: &Document,
+    direction: FinanseDirection,
+    counterparty: &str,
+) -> Operation {
+    let operation_type = match direction {
+        FinanseDirection::Debet => OperationType::DebetingAccounts,
+        FinanseDirection::Credit => OperationType::WithdrawalFromAccounts,
+    };
+    Operation {
+        id: Uuid::new_v4(),
+        date_time: NaiveDateTime::from(date),
+        account_id,
+        operation_type,
+        summary: document.summary,
+        direction,
+        receipt_id: None,
+        reference: (!document.number.is_empty()).then(|| document.number.clone()),
+        counterparty: (!counterparty.is_empty()).then(|| counterparty.to_string()),
+        note: (!document.purpose.is_empty()).then(|| document.purpose.clone()),
+        category: None,
+        transfer_id: None,
+    }
+}
+
+// Сопоставление документов выписки со счетами базы по номеру расчётного счёта.
+// Документы сопоставляются только со счетами, по которым сделана выписка (РасчСчет
+// в заголовке); номера выписки, которых нет в базе, возвращаются в unknown_accounts.
+// Списание со своего счёта становится расходом, поступление - приходом; перевод между
+// двумя своими счетами даёт обе операции, связанные как перевод, даже если
+// списание и поступление прошли в разные дни. Выписка по нескольким счетам
+// повторяет перевод между ними в секции каждого счёта; повтор пропускается.
+pub fn import(db: &Database, statement: &Statement) -> ImportResult {
+    let mut result = ImportResult::default();
+    let accounts: Vec<&Account> = db
+        .accounts
+        .iter()
+        .filter(|account| {
+            statement.accounts.is_empty()
+                || statement
+                    .accounts
+                    .iter()
+                    .any(|number| same_account(number, &account.number))
+        })
+        .collect();
+    for number in statement.accounts.iter() {
+        if !accounts
+            .iter()
+            .any(|account| same_account(number, &account.number))
+        {
+            result.unknown_accounts.push(number.clone());
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for document in statement.documents.iter() {
+        let key = (
+            &document.number,
+            document.date,
+            &document.payer_account,
+            &document.payee_account,
+            document.summary,
+        );
+        if !seen.insert(key) {
+            continue;
+        }
+        let first = result.operations.len();
+        for account in accounts.iter() {
+            let mut candidates = Vec::new();
+            if same_account(&document.payer_account, &account.number) {
+                let date = document.written_off.or(document.date);
+                candidates.push((date, FinanseDirection::Credit, &document.payee));
+            }
+            if same_account(&document.payee_account, &account.number) {
+                let date = document.received.or(document.date);
+                candidates.push((date, FinanseDirection::Debet, &document.payer));
+            }
+            // Документ без даты не импортируется и считается несопоставленным
+            for (date, direction, counterparty) in candidates {
+                if let Some(date) = date {
+                    let element = operation(account.id, date, document, direction, counterparty);
+                    result.operations.push(element);
+                }
+            }
+        }
+        match result.operations.len() - first {
+            0 => result.unmatched += 1,
+            2 => link_pair(&mut result.operations, first, first + 1),
+            _ => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::AccountType;
+    use rust_decimal::dec;
+
+    const STATEMENT: &str = "1CClientBankExchange
+ВерсияФормата=1.03
+Кодировка=Windows
+СекцияРасчСчет
+РасчСчет=40817810000000000001
+КонецРасчСчет
+СекцияРасчСчет
+РасчСчет=40817810000000000002
+КонецРасчСчет
+СекцияРасчСчет
+РасчСчет=40817810000000000009
+КонецРасчСчет
+СекцияДокумент=Платежное поручение
+Номер=15
+Дата=03.02.2025
+Сумма=1 500,50
+ПлательщикСчет=40817810000000000001
+Плательщик=ИНН 7700000000 Иванов
+Плательщик1=Иванов
+ПолучательСчет=40702810000000000777
+Получатель1=ООО Ромашка
+ДатаСписано=04.02.2025
+НазначениеПлатежа=Оплата по счёту 7
+КонецДокумента
+СекцияДокумент=Платежное поручение
+Номер=16
+Дата=05.02.2025
+Сумма=1000
+ПлательщикСчет=40817810000000000001
+ПолучательСчет=40817810000000000002
+КонецДокумента
+СекцияДокумент=Платежное поручение
+Номер=17
+Сумма=10
+ПлательщикСчет=40817810000000000001
+ПолучательСчет=40702810000000000777
+КонецДокумента
+СекцияДокумент=Платежное поручение
+Номер=18
+Дата=06.02.2025
+Сумма=20
+ПлательщикСчет=40702810000000000777
+ПолучательСчет=40817810000000000004
+КонецДокумента
+КонецФайла
+";
+
+    fn account(number: &str) -> Account {
+        Account {
+            id: Uuid::new_v4(),
+            name: number.to_string(),
+            account_type: AccountType::Account,
+            number: number.to_string(),
+            bik: 0,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Default::default(),
+        }
+    }
+
+    #[test]
+    fn parse_statement() {
+        let statement = parse(STATEMENT).unwrap();
+        assert_eq!(statement.accounts.len(), 3);
+        assert_eq!(statement.documents.len(), 4);
+        let document = &statement.documents[0];
+        assert_eq!(document.kind, "Платежное поручение");
+        assert_eq!(document.number, "15");
+        assert_eq!(document.date, NaiveDate::from_ymd_opt(2025, 2, 3));
+        assert_eq!(document.written_off, NaiveDate::from_ymd_opt(2025, 2, 4));
+        assert_eq!(document.summary, dec!(1500.50));
+        assert_eq!(document.payer, "Иванов");
+        assert_eq!(document.payee, "ООО Ромашка");
+        assert_eq!(statement.documents[2].date, None);
+    }
+
+    #[test]
+    fn format_errors() {
+        assert!(matches!(parse("Выписка"), Err(ImportError::Format(_))));
+        let text = "1CClientBankExchange\nСекцияДокумент=\nСумма=abc\nКонецДокумента\n";
+        assert!(matches!(parse(text), Err(ImportError::Format(_))));
+        let text = "1CClientBankExchange\nКонецДокумента\n";
+        assert!(matches!(parse(text), Err(ImportError::Format(_))));
+    }
+
+    #[test]
+    fn decode_encodings() {
+        let (bytes, _, _) = WINDOWS_1251.encode(STATEMENT);
+        assert_eq!(decode(&bytes), STATEMENT);
+        let mut bytes = b"\xEF\xBB\xBF".to_vec();
+        bytes.extend_from_slice(STATEMENT.as_bytes());
+        assert_eq!(decode(&bytes), STATEMENT);
+    }
+
+    #[test]
+    fn import_statement() {
+        let mut db = Database::new();
+        let first = account("40817810000000000001");
+        let second = account("408 17 810 0000 0000 0002");
+        // Счёт есть в базе, но выписка не по нему
+        let other = account("40817810000000000004");
+        db.accounts = vec![first.clone(), second.clone(), other];
+        let result = import(&db, &parse(STATEMENT).unwrap());
+
+        assert_eq!(result.unknown_accounts, vec!["40817810000000000009"]);
+        // Документ без даты и документ по чужому счёту
+        assert_eq!(result.unmatched, 2);
+        assert_eq!(result.operations.len(), 3);
+
+        let payment = &result.operations[0];
+        assert_eq!(payment.account_id, first.id);
+        assert_eq!(payment.direction, FinanseDirection::Credit);
+        assert_eq!(
+            payment.date_time.date(),
+            NaiveDate::from_ymd_opt(2025, 2, 4).unwrap()
+        );
+        assert_eq!(payment.counterparty.as_deref(), Some("ООО Ромашка"));
+        assert_eq!(payment.reference.as_deref(), Some("15"));
+        assert_eq!(payment.transfer_id, None);
+
+        let (from, to) = (&result.operations[1], &result.operations[2]);
+        assert_eq!(from.account_id, first.id);
+        assert_eq!(to.account_id, second.id);
+        assert_eq!(from.transfer_id, Some(to.id));
+        assert_eq!(to.transfer_id, Some(from.id));
+        assert_eq!(to.operation_type, OperationType::DebetingAccounts);
+    }
+
+    #[test]
+    fn transfer_on_different_days_listed_twice() {
+        let mut db = Database::new();
+        let first = account("40817810000000000001");
+        let second = account("40817810000000000002");
+        db.accounts = vec![first.clone(), second.clone()];
+        // Тот же документ в выписке по каждому из двух счетов
+        let document = "СекцияДокумент=Платежное поручение
+Номер=21
+Дата=07.02.2025
+Сумма=300
+ПлательщикСчет=40817810000000000001
+ПолучательСчет=40817810000000000002
+ДатаСписано=07.02.2025
+ДатаПоступило=10.02.2025
+КонецДокумента
+";
+        let text = format!(
+            "1CClientBankExchange
+СекцияРасчСчет
+РасчСчет=40817810000000000001
+КонецРасчСчет
+СекцияРасчСчет
+РасчСчет=40817810000000000002
+КонецРасчСчет
+{document}{document}КонецФайла
+"
+        );
+        let result = import(&db, &parse(&text).unwrap());
+        assert_eq!(result.operations.len(), 2);
+        assert_eq!(result.unmatched, 0);
+
+        let (from, to) = (&result.operations[0], &result.operations[1]);
+        assert_eq!(from.account_id, first.id);
+        assert_eq!(to.account_id, second.id);
+        assert_ne!(from.date_time.date(), to.date_time.date());
+        assert_eq!(from.transfer_id, Some(to.id));
+        assert_eq!(to.transfer_id, Some(from.id));
+        assert_eq!(from.operation_type, OperationType::WithdrawalFromAccounts);
+    }
+}
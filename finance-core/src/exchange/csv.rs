@@ -0,0 +1,384 @@
+// Импорт операций из CSV-выписок банков с настраиваемым сопоставлением колонок
+
+use chrono::{NaiveDate, NaiveDateTime};
+use encoding_rs::WINDOWS_1251;
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+use uuid::Uuid;
+
+use crate::card::card_account;
+use crate::database::Database;
+use crate::exchange::{ImportError, ImportResult};
+use crate::money::parse_decimal;
+use crate::operation::{FinanseDirection, Operation, OperationType};
+
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    Windows1251,
+}
+
+// Назначение колонки файла
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum ColumnRole {
+    #[default]
+    Skip,
+    Date,
+    Amount,
+    Direction,
+    Description,
+    Category,
+    Counterparty,
+    Reference,
+    Card,
+}
+
+// Профиль сопоставления колонок, сохраняется в базе под именем банка
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CsvProfile {
+    pub name: String,
+    pub encoding: TextEncoding,
+    pub delimiter: char,
+    pub skip_rows: usize,
+    pub date_format: String,
+    pub columns: Vec<ColumnRole>,
+    // Значение в колонке направления, означающее поступление; без колонки
+    // направления оно определяется знаком суммы
+    pub debet_marker: String,
+}
+
+impl Default for CsvProfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CsvProfile {
+    pub fn new() -> Self {
+        Self {
+            name: "".to_string(),
+            encoding: TextEncoding::Utf8,
+            delimiter: ';',
+            skip_rows: 1,
+            date_format: "%d.%m.%Y %H:%M:%S".to_string(),
+            columns: Vec::new(),
+            debet_marker: "".to_string(),
+        }
+    }
+
+    fn column(&self, role: ColumnRole) -> Option<usize> {
+        self.columns.iter().position(|column| *column == role)
+    }
+}
+
+pub fn decode(bytes: &[u8], encoding: TextEncoding) -> String {
+    match encoding {
+        TextEncoding::Utf8 => {
+            let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+            String::from_utf8_lossy(bytes).into_owned()
+        }
+        TextEncoding::Windows1251 => WINDOWS_1251.decode(bytes).0.into_owned(),
+    }
+}
+
+// Разбор CSV с учётом кавычек: разделитель и перевод строки внутри кавычек
+// относятся к значению, удвоенная кавычка - сама кавычка
+pub fn split_rows(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+        } else if c == '"' {
+            quoted = true;
+        } else if c == delimiter {
+            row.push(std::mem::take(&mut field));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            row.push(std::mem::take(&mut field));
+            if row.iter().any(|value| !value.trim().is_empty()) {
+                rows.push(std::mem::take(&mut row));
+            } else {
+                row.clear();
+            }
+        } else {
+            field.push(c);
+        }
+    }
+    row.push(field);
+    if row.iter().any(|value| !value.trim().is_empty()) {
+        rows.push(row);
+    }
+    rows
+}
+
+pub fn read(path: &str, profile: &CsvProfile) -> Result<Vec<Vec<String>>, ImportError> {
+    let bytes = std::fs::read(path)?;
+    Ok(split_rows(
+        &decode(&bytes, profile.encoding),
+        profile.delimiter,
+    ))
+}
+
+fn parse_date_time(value: &str, format: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    NaiveDateTime::parse_from_str(value, format)
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, format)
+                .ok()
+                .map(NaiveDateTime::from)
+        })
+}
+
+fn text(row: &[String], column: Option<usize>) -> Option<String> {
+    let value = row.get(column?)?.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+// Создание операций из строк файла. Если в строке есть номер карты из реестра,
+// операция относится к счёту этой карты, иначе - к выбранному счёту.
+pub fn import(
+    db: &Database,
+    profile: &CsvProfile,
+    rows: &[Vec<String>],
+    account_id: Uuid,
+) -> Result<ImportResult, ImportError> {
+    if !db.accounts.iter().any(|account| account.id == account_id) {
+        return Err(ImportError::Format("не выбран счёт".to_string()));
+    }
+    let date_column = profile
+        .column(ColumnRole::Date)
+        .ok_or_else(|| ImportError::Format("не выбрана колонка даты".to_string()))?;
+    let amount_column = profile
+        .column(ColumnRole::Amount)
+        .ok_or_else(|| ImportError::Format("не выбрана колонка суммы".to_string()))?;
+    let direction_column = profile.column(ColumnRole::Direction);
+    // Банки пишут направление по-русски, а eq_ignore_ascii_case кириллицу не сравнивает
+    let debet_marker = profile.debet_marker.trim().to_lowercase();
+
+    let mut result = ImportResult::default();
+    for (index, row) in rows.iter().enumerate().skip(profile.skip_rows) {
+        let line = index + 1;
+        let raw_date = row.get(date_column).map(String::as_str).unwrap_or("");
+        let date_time = parse_date_time(raw_date, &profile.date_format).ok_or_else(|| {
+            ImportError::Format(format!("строка {}: неверная дата '{}'", line, raw_date))
+        })?;
+        let raw_amount = row.get(amount_column).map(String::as_str).unwrap_or("");
+        let amount = parse_decimal(raw_amount).ok_or_else(|| {
+            ImportError::Format(format!("строка {}: неверная сумма '{}'", line, raw_amount))
+        })?;
+
+        let direction = match direction_column {
+            Some(column) => {
+                let value = row.get(column).map(|v| v.trim()).unwrap_or("");
+                if value.to_lowercase() == debet_marker {
+                    FinanseDirection::Debet
+                } else {
+                    FinanseDirection::Credit
+                }
+            }
+            None if amount.is_sign_negative() => FinanseDirection::Credit,
+            None => FinanseDirection::Debet,
+        };
+        let operation_type = match direction {
+            FinanseDirection::Debet => OperationType::DebetingAccounts,
+            FinanseDirection::Credit => OperationType::WithdrawalFromAccounts,
+        };
+        let account_id = text(row, profile.column(ColumnRole::Card))
+            .and_then(|card| card_account(&db.accounts, &card))
+            .unwrap_or(account_id);
+
+        let element = Operation {
+            id: Uuid::new_v4(),
+            date_time,
+            account_id,
+            operation_type,
+            summary: amount.abs(),
+            direction,
+            receipt_id: None,
+            reference: text(row, profile.column(ColumnRole::Reference)),
+            counterparty: text(row, profile.column(ColumnRole::Counterparty)),
+            note: text(row, profile.column(ColumnRole::Description)),
+            category: text(row, profile.column(ColumnRole::Category)),
+            transfer_id: None,
+        };
+        result.operations.push(element);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{Account, AccountType};
+    use crate::card::Card;
+    use rust_decimal::dec;
+
+    const FILE: &str = "Дата;Сумма;Описание;Карта\r\n\
+        01.03.2026 10:00:00;\"-1 234,50\";\"Магазин \"\"Ромашка\"\"; Москва\";*1111\n\
+        \n\
+        02.03.2026 09:00:00;500;\"Зарплата\nза февраль\";\n";
+
+    // База со счётом, на который идёт импорт
+    fn database() -> (Database, Uuid) {
+        let mut db = Database::new();
+        let account = Account {
+            id: Uuid::new_v4(),
+            name: "Main".to_string(),
+            account_type: AccountType::Account,
+            number: String::new(),
+            bik: 0,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Default::default(),
+        };
+        let id = account.id;
+        db.accounts.push(account);
+        (db, id)
+    }
+
+    fn profile(columns: Vec<ColumnRole>) -> CsvProfile {
+        let mut profile = CsvProfile::new();
+        profile.columns = columns;
+        profile
+    }
+
+    #[test]
+    fn split_quoted() {
+        let rows = split_rows(FILE, ';');
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0], vec!["Дата", "Сумма", "Описание", "Карта"]);
+        assert_eq!(rows[1][1], "-1 234,50");
+        assert_eq!(rows[1][2], "Магазин \"Ромашка\"; Москва");
+        assert_eq!(rows[2][2], "Зарплата\nза февраль");
+        assert_eq!(rows[2][3], "");
+    }
+
+    #[test]
+    fn decode_windows_1251() {
+        let (bytes, _, _) = WINDOWS_1251.encode(FILE);
+        assert_eq!(decode(&bytes, TextEncoding::Windows1251), FILE);
+        assert_eq!(decode(FILE.as_bytes(), TextEncoding::Utf8), FILE);
+    }
+
+    #[test]
+    fn import_by_sign() {
+        let (mut db, account_id) = database();
+        let card = Account {
+            id: Uuid::new_v4(),
+            name: "Card".to_string(),
+            account_type: AccountType::DebetCard,
+            number: String::new(),
+            bik: 0,
+            sum: 0,
+            cards: vec![Card::new("4111111111111111", None, None).unwrap()],
+            currency: Default::default(),
+        };
+        db.accounts.push(card.clone());
+        let profile = profile(vec![
+            ColumnRole::Date,
+            ColumnRole::Amount,
+            ColumnRole::Description,
+            ColumnRole::Card,
+        ]);
+        let result = import(&db, &profile, &split_rows(FILE, ';'), account_id).unwrap();
+        assert_eq!(result.operations.len(), 2);
+
+        let purchase = &result.operations[0];
+        assert_eq!(purchase.summary, dec!(1234.50));
+        assert_eq!(purchase.direction, FinanseDirection::Credit);
+        assert_eq!(
+            purchase.operation_type,
+            OperationType::WithdrawalFromAccounts
+        );
+        assert_eq!(purchase.account_id, card.id);
+        assert_eq!(
+            purchase.note.as_deref(),
+            Some("Магазин \"Ромашка\"; Москва")
+        );
+
+        let salary = &result.operations[1];
+        assert_eq!(salary.direction, FinanseDirection::Debet);
+        assert_eq!(salary.account_id, account_id);
+        assert_eq!(
+            salary.date_time,
+            NaiveDate::from_ymd_opt(2026, 3, 2)
+                .unwrap()
+                .and_hms_opt(9, 0, 0)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn import_by_direction_column() {
+        let text = "date;amount;type\n2026-03-01;100;IN\n2026-03-02;50;out\n";
+        let mut profile = profile(vec![
+            ColumnRole::Date,
+            ColumnRole::Amount,
+            ColumnRole::Direction,
+        ]);
+        profile.date_format = "%Y-%m-%d".to_string();
+        profile.debet_marker = "in".to_string();
+        let rows = split_rows(text, ';');
+        let (db, account_id) = database();
+        let result = import(&db, &profile, &rows, account_id).unwrap();
+        let directions: Vec<_> = result.operations.iter().map(|o| &o.direction).collect();
+        assert_eq!(
+            directions,
+            vec![&FinanseDirection::Debet, &FinanseDirection::Credit]
+        );
+
+        let text = "date;amount;type\n2026-03-01;100;ПРИХОД\n2026-03-02;50;Расход\n";
+        profile.debet_marker = "Приход".to_string();
+        let result = import(&db, &profile, &split_rows(text, ';'), account_id).unwrap();
+        let directions: Vec<_> = result.operations.iter().map(|o| &o.direction).collect();
+        assert_eq!(
+            directions,
+            vec![&FinanseDirection::Debet, &FinanseDirection::Credit]
+        );
+    }
+
+    #[test]
+    fn import_errors() {
+        let rows = split_rows(FILE, ';');
+        let (db, account_id) = database();
+        // Дата без времени не подходит к формату с временем
+        let mut profile = profile(vec![ColumnRole::Date, ColumnRole::Amount]);
+        profile.date_format = "%d.%m.%Y".to_string();
+        match import(&db, &profile, &rows, account_id) {
+            Err(ImportError::Format(message)) => assert!(message.starts_with("строка 2")),
+            other => panic!("{:?}", other),
+        }
+        // Счёт не выбран
+        let profile = self::profile(vec![ColumnRole::Date, ColumnRole::Amount]);
+        match import(&db, &profile, &rows, Uuid::nil()) {
+            Err(ImportError::Format(message)) => assert_eq!(message, "не выбран счёт"),
+            other => panic!("{:?}", other),
+        }
+        let profile = self::profile(vec![ColumnRole::Date]);
+        assert!(import(&db, &profile, &rows, account_id).is_err());
+    }
+
+    #[test]
+    fn profile_round_trip() {
+        let mut profile = profile(vec![ColumnRole::Date, ColumnRole::Skip, ColumnRole::Amount]);
+        profile.name = "Банк".to_string();
+        profile.encoding = TextEncoding::Windows1251;
+        let json = serde_json::to_string(&profile).unwrap();
+        assert_eq!(serde_json::from_str::<CsvProfile>(&json).unwrap(), profile);
+    }
+}
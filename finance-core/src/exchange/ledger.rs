@@ -0,0 +1,530 @@
+// Экспорт в журналы текстовой бухгалтерии: ledger-cli / hledger и beancount.
+// Вывод детерминирован: счета и операции упорядочены, повторная выгрузка той же
+// базы даёт тот же текст.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::account::AccountType;
+use crate::database::Database;
+use crate::exchange::ImportError;
+use crate::operation::{FinanseDirection, Operation, OperationType};
+use crate::receipt::Currency;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Dialect {
+    Ledger, // ledger-cli и hledger
+    Beancount,
+}
+
+const OPENING: &str = "Equity:Opening-Balances";
+const UNCATEGORIZED: &str = "Uncategorized";
+
+struct Posting {
+    account: String,
+    amount: Decimal,
+    currency: Currency,
+    // Цена всей суммы в валюте другой стороны перевода
+    total_price: Option<(Decimal, Currency)>,
+    comments: Vec<String>,
+}
+
+struct Transaction {
+    date_time: NaiveDateTime,
+    id: Uuid,
+    reference: Option<String>,
+    payee: Option<String>,
+    narration: Option<String>,
+    postings: Vec<Posting>,
+}
+
+fn root(account_type: &AccountType) -> &'static str {
+    match account_type {
+        AccountType::Account => "Assets:Bank",
+        AccountType::Cash => "Assets:Cash",
+        AccountType::DebetCard => "Assets:Card",
+        AccountType::AccumulativeAccount => "Assets:Savings",
+        AccountType::Deposit => "Assets:Deposit",
+        AccountType::CreditCard => "Liabilities:CreditCard",
+        AccountType::CreditAccount => "Liabilities:Credit",
+    }
+}
+
+// Компонент имени счёта: буквы, цифры и дефисы, начинается с заглавной буквы или
+// цифры (требование beancount, ledger принимает такие имена без изменений)
+fn component(name: &str) -> String {
+    let mut result = String::new();
+    for c in name.trim().chars() {
+        if c.is_alphanumeric() {
+            result.push(c);
+        } else if !result.is_empty() && !result.ends_with('-') {
+            result.push('-');
+        }
+    }
+    let result = result.trim_end_matches('-');
+    let mut chars = result.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => "X".to_string(),
+    }
+}
+
+fn category_account(prefix: &str, category: Option<&str>) -> String {
+    let path: Vec<String> = category
+        .unwrap_or(UNCATEGORIZED)
+        .split(':')
+        .filter(|part| !part.trim().is_empty())
+        .map(component)
+        .collect();
+    if path.is_empty() {
+        format!("{}:{}", prefix, UNCATEGORIZED)
+    } else {
+        format!("{}:{}", prefix, path.join(":"))
+    }
+}
+
+// Имена счетов базы; при совпадении имён добавляется начало идентификатора
+fn account_names(db: &Database) -> BTreeMap<Uuid, (String, Currency)> {
+    let base = |id: &Uuid| -> String {
+        let account = db
+            .accounts
+            .iter()
+            .find(|account| account.id == *id)
+            .unwrap();
+        format!(
+            "{}:{}",
+            root(&account.account_type),
+            component(&account.name)
+        )
+    };
+    let mut names = BTreeMap::new();
+    for account in db.accounts.iter() {
+        let name = base(&account.id);
+        let clash = db
+            .accounts
+            .iter()
+            .any(|other| other.id != account.id && base(&other.id) == name);
+        let name = if clash {
+            format!("{}-{}", name, &account.id.simple().to_string()[..8])
+        } else {
+            name
+        };
+        names.insert(account.id, (name, account.currency));
+    }
+    names
+}
+
+fn signed(operation: &Operation) -> Decimal {
+    match operation.direction {
+        FinanseDirection::Debet => operation.summary,
+        FinanseDirection::Credit => -operation.summary,
+    }
+}
+
+fn subject_comments(db: &Database, operation: &Operation) -> Vec<String> {
+    let Some(receipt) = operation
+        .receipt_id
+        .and_then(|id| db.receipts.iter().find(|receipt| receipt.id == id))
+    else {
+        return Vec::new();
+    };
+    receipt
+        .subjects
+        .iter()
+        .map(|subject| {
+            format!(
+                "{} {} {} x {:.2} = {:.2}",
+                subject.name.replace('\n', " "),
+                subject.count.normalize(),
+                subject.unit_type,
+                subject.price,
+                subject.summary
+            )
+        })
+        .collect()
+}
+
+fn transactions(db: &Database) -> Vec<Transaction> {
+    let names = account_names(db);
+    let mut operations: Vec<&Operation> = db
+        .operations
+        .iter()
+        .filter(|operation| names.contains_key(&operation.account_id))
+        .collect();
+    operations.sort_by_key(|operation| (operation.date_time, operation.id));
+
+    let mut result = Vec::new();
+    for operation in operations {
+        let (account, currency) = names[&operation.account_id].clone();
+        let pair = operation.transfer_id.and_then(|id| {
+            db.operations
+                .iter()
+                .find(|other| other.id == id && names.contains_key(&other.account_id))
+        });
+        let postings = if let Some(pair) = pair {
+            // Перевод записывается одной проводкой со стороны списания
+            let primary = if operation.direction != pair.direction {
+                operation.direction == FinanseDirection::Credit
+            } else {
+                operation.id < pair.id
+            };
+            if !primary {
+                continue;
+            }
+            let (pair_account, pair_currency) = names[&pair.account_id].clone();
+            let total_price = (pair_currency != currency).then_some((operation.summary, currency));
+            vec![
+                Posting {
+                    account,
+                    amount: signed(operation),
+                    currency,
+                    total_price: None,
+                    comments: Vec::new(),
+                },
+                Posting {
+                    account: pair_account,
+                    amount: signed(pair),
+                    currency: pair_currency,
+                    total_price,
+                    comments: Vec::new(),
+                },
+            ]
+        } else {
+            let other = match (&operation.operation_type, &operation.direction) {
+                (OperationType::Initial, _) => OPENING.to_string(),
+                (_, FinanseDirection::Debet) => {
+                    category_account("Income", operation.category.as_deref())
+                }
+                (_, FinanseDirection::Credit) => {
+                    category_account("Expenses", operation.category.as_deref())
+                }
+            };
+            vec![
+                Posting {
+                    account,
+                    amount: signed(operation),
+                    currency,
+                    total_price: None,
+                    comments: Vec::new(),
+                },
+                Posting {
+                    account: other,
+                    amount: -signed(operation),
+                    currency,
+                    total_price: None,
+                    comments: subject_comments(db, operation),
+                },
+            ]
+        };
+        let receipt_place = operation
+            .receipt_id
+            .and_then(|id| db.receipts.iter().find(|receipt| receipt.id == id))
+            .and_then(|receipt| receipt.place.clone());
+        result.push(Transaction {
+            date_time: operation.date_time,
+            id: operation.id,
+            reference: operation.reference.clone(),
+            payee: operation.counterparty.clone().or(receipt_place),
+            narration: operation.note.clone(),
+            postings,
+        });
+    }
+    result
+}
+
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn quoted(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        one_line(text).replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+fn amount(value: Decimal, currency: Currency) -> String {
+    format!("{:.2} {}", value, currency.code())
+}
+
+fn write_postings(out: &mut String, postings: &[Posting]) {
+    for posting in postings.iter() {
+        let mut line = format!(
+            "    {:<50}  {:>14}",
+            posting.account,
+            amount(posting.amount, posting.currency)
+        );
+        if let Some((price, currency)) = posting.total_price {
+            write!(line, " @@ {}", amount(price, currency)).unwrap();
+        }
+        writeln!(out, "{}", line.trim_end()).unwrap();
+        for comment in posting.comments.iter() {
+            writeln!(out, "    ; {}", one_line(comment)).unwrap();
+        }
+    }
+}
+
+// Все используемые счета с датой первой проводки
+fn used_accounts(transactions: &[Transaction]) -> BTreeMap<String, NaiveDate> {
+    let mut accounts = BTreeMap::new();
+    for transaction in transactions.iter() {
+        for posting in transaction.postings.iter() {
+            accounts
+                .entry(posting.account.clone())
+                .or_insert(transaction.date_time.date());
+        }
+    }
+    accounts
+}
+
+pub fn export(db: &Database, dialect: Dialect) -> String {
+    let transactions = transactions(db);
+    let mut accounts = used_accounts(&transactions);
+    let names = account_names(db);
+    let first = transactions
+        .first()
+        .map(|transaction| transaction.date_time.date())
+        .unwrap_or_default();
+    for (name, _) in names.values() {
+        accounts.entry(name.clone()).or_insert(first);
+    }
+    let currencies: BTreeSet<&'static str> = names
+        .values()
+        .map(|(_, currency)| currency.code())
+        .collect();
+
+    let mut out = String::new();
+    match dialect {
+        Dialect::Ledger => {
+            for currency in currencies.iter() {
+                writeln!(out, "commodity {}", currency).unwrap();
+            }
+            writeln!(out).unwrap();
+            for name in accounts.keys() {
+                writeln!(out, "account {}", name).unwrap();
+            }
+        }
+        Dialect::Beancount => {
+            for currency in currencies.iter() {
+                writeln!(out, "{} commodity {}", first, currency).unwrap();
+            }
+            writeln!(out).unwrap();
+            for (name, date) in accounts.iter() {
+                writeln!(out, "{} open {}", date, name).unwrap();
+            }
+        }
+    }
+
+    for transaction in transactions.iter() {
+        writeln!(out).unwrap();
+        let date = transaction.date_time.date();
+        match dialect {
+            Dialect::Ledger => {
+                let mut header = format!("{} *", date);
+                if let Some(reference) = &transaction.reference {
+                    write!(header, " ({})", one_line(reference)).unwrap();
+                }
+                let payee = transaction
+                    .payee
+                    .as_deref()
+                    .or(transaction.narration.as_deref())
+                    .unwrap_or("");
+                write!(header, " {}", one_line(payee)).unwrap();
+                writeln!(out, "{}", header.trim_end()).unwrap();
+                if transaction.payee.is_some()
+                    && let Some(narration) = &transaction.narration
+                {
+                    writeln!(out, "    ; {}", one_line(narration)).unwrap();
+                }
+                writeln!(out, "    ; id: {}", transaction.id).unwrap();
+            }
+            Dialect::Beancount => {
+                let mut header = format!("{} *", date);
+                if let Some(payee) = &transaction.payee {
+                    write!(header, " {}", quoted(payee)).unwrap();
+                }
+                let narration = transaction.narration.as_deref().unwrap_or("");
+                write!(header, " {}", quoted(narration)).unwrap();
+                writeln!(out, "{}", header).unwrap();
+                writeln!(out, "    id: \"{}\"", transaction.id).unwrap();
+                if let Some(reference) = &transaction.reference {
+                    writeln!(out, "    reference: {}", quoted(reference)).unwrap();
+                }
+            }
+        }
+        write_postings(&mut out, &transaction.postings);
+    }
+    out
+}
+
+pub fn write(db: &Database, path: &str, dialect: Dialect) -> Result<(), ImportError> {
+    std::fs::write(path, export(db, dialect))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::Account;
+    use rust_decimal::dec;
+
+    fn account(id: u128, name: &str, account_type: AccountType) -> Account {
+        Account {
+            id: Uuid::from_u128(id),
+            name: name.to_string(),
+            account_type,
+            number: String::new(),
+            bik: 0,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Currency::Rub,
+        }
+    }
+
+    fn operation(id: u128, account: &Account, day: u32, amount: Decimal) -> Operation {
+        Operation {
+            id: Uuid::from_u128(id),
+            date_time: NaiveDate::from_ymd_opt(2026, 3, day)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+            account_id: account.id,
+            operation_type: OperationType::Buy,
+            summary: amount.abs(),
+            direction: if amount.is_sign_negative() {
+                FinanseDirection::Credit
+            } else {
+                FinanseDirection::Debet
+            },
+            receipt_id: None,
+            reference: None,
+            counterparty: None,
+            note: None,
+            category: None,
+            transfer_id: None,
+        }
+    }
+
+    // Операции записаны в базу не по порядку: начальный остаток, перевод в
+    // кошелёк и две операции одного времени, которые идут в порядке id
+    fn database() -> Database {
+        let main = account(0xA1, "Main", AccountType::Account);
+        let cash = account(0xA2, "Кошелёк", AccountType::Cash);
+        let mut db = Database::new();
+        let mut lunch = operation(3, &main, 3, dec!(-250.50));
+        lunch.category = Some("Food:Cafe".to_string());
+        lunch.counterparty = Some("Кафе".to_string());
+        lunch.note = Some("обед".to_string());
+        lunch.reference = Some("42".to_string());
+        db.operations.push(lunch);
+        let mut from = operation(4, &main, 2, dec!(-300));
+        let mut to = operation(5, &cash, 2, dec!(300));
+        from.transfer_id = Some(to.id);
+        to.transfer_id = Some(from.id);
+        db.operations.push(to);
+        db.operations.push(from);
+        db.operations.push(operation(2, &cash, 3, dec!(50)));
+        let mut opening = operation(1, &main, 1, dec!(1000));
+        opening.operation_type = OperationType::Initial;
+        db.operations.push(opening);
+        db.accounts = vec![main, cash];
+        db
+    }
+
+    const LEDGER: &str = "commodity RUB
+
+account Assets:Bank:Main
+account Assets:Cash:Кошелёк
+account Equity:Opening-Balances
+account Expenses:Food:Cafe
+account Income:Uncategorized
+
+2026-03-01 *
+    ; id: 00000000-0000-0000-0000-000000000001
+    Assets:Bank:Main                                       1000.00 RUB
+    Equity:Opening-Balances                               -1000.00 RUB
+
+2026-03-02 *
+    ; id: 00000000-0000-0000-0000-000000000004
+    Assets:Bank:Main                                       -300.00 RUB
+    Assets:Cash:Кошелёк                                     300.00 RUB
+
+2026-03-03 *
+    ; id: 00000000-0000-0000-0000-000000000002
+    Assets:Cash:Кошелёк                                      50.00 RUB
+    Income:Uncategorized                                    -50.00 RUB
+
+2026-03-03 * (42) Кафе
+    ; обед
+    ; id: 00000000-0000-0000-0000-000000000003
+    Assets:Bank:Main                                       -250.50 RUB
+    Expenses:Food:Cafe                                      250.50 RUB
+";
+
+    const BEANCOUNT: &str = "2026-03-01 commodity RUB
+
+2026-03-01 open Assets:Bank:Main
+2026-03-02 open Assets:Cash:Кошелёк
+2026-03-01 open Equity:Opening-Balances
+2026-03-03 open Expenses:Food:Cafe
+2026-03-03 open Income:Uncategorized
+
+2026-03-01 * \"\"
+    id: \"00000000-0000-0000-0000-000000000001\"
+    Assets:Bank:Main                                       1000.00 RUB
+    Equity:Opening-Balances                               -1000.00 RUB
+
+2026-03-02 * \"\"
+    id: \"00000000-0000-0000-0000-000000000004\"
+    Assets:Bank:Main                                       -300.00 RUB
+    Assets:Cash:Кошелёк                                     300.00 RUB
+
+2026-03-03 * \"\"
+    id: \"00000000-0000-0000-0000-000000000002\"
+    Assets:Cash:Кошелёк                                      50.00 RUB
+    Income:Uncategorized                                    -50.00 RUB
+
+2026-03-03 * \"Кафе\" \"обед\"
+    id: \"00000000-0000-0000-0000-000000000003\"
+    reference: \"42\"
+    Assets:Bank:Main                                       -250.50 RUB
+    Expenses:Food:Cafe                                      250.50 RUB
+";
+
+    #[test]
+    fn golden() {
+        let mut db = database();
+        assert_eq!(export(&db, Dialect::Ledger), LEDGER);
+        assert_eq!(export(&db, Dialect::Beancount), BEANCOUNT);
+        // Порядок записей в базе на выгрузку не влияет
+        db.operations.reverse();
+        db.accounts.reverse();
+        assert_eq!(export(&db, Dialect::Ledger), LEDGER);
+    }
+
+    // Проводки каждой записи сходятся в ноль, суммы по счетам - остатки счетов
+    #[test]
+    fn balances() {
+        let mut totals: BTreeMap<String, Decimal> = BTreeMap::new();
+        for transaction in LEDGER.split("\n\n").skip(2) {
+            let mut sum = Decimal::ZERO;
+            // Проводки - строки с отступом, кроме комментариев
+            for line in transaction
+                .lines()
+                .filter(|line| line.starts_with("    ") && !line.trim_start().starts_with(';'))
+            {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                let value: Decimal = parts[1].parse().unwrap();
+                sum += value;
+                *totals.entry(parts[0].to_string()).or_default() += value;
+            }
+            assert_eq!(sum, Decimal::ZERO, "{}", transaction);
+        }
+        assert_eq!(totals["Assets:Bank:Main"], dec!(449.50));
+        assert_eq!(totals["Assets:Cash:Кошелёк"], dec!(350));
+        assert_eq!(totals["Equity:Opening-Balances"], dec!(-1000));
+    }
+}
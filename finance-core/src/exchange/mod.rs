@@ -0,0 +1,113 @@
+use std::fmt;
+use uuid::Uuid;
+
+use crate::account::{Account, AccountType};
+use crate::operation::{FinanseDirection, Operation, OperationType};
+
+pub mod client_bank;
+pub mod csv;
+pub mod ledger;
+pub mod ofx;
+pub mod qif;
+pub mod sheet;
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(std::io::Error),
+    Format(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(error) => write!(f, "Ошибка чтения файла: {}", error),
+            ImportError::Format(message) => write!(f, "Ошибка формата: {}", message),
+        }
+    }
+}
+
+impl From<std::io::Error> for ImportError {
+    fn from(error: std::io::Error) -> Self {
+        ImportError::Io(error)
+    }
+}
+
+// Операции, подготовленные к добавлению в базу. Повторы уже имеющихся операций
+// здесь не отбрасываются, их разбирает dedup перед записью в базу.
+#[derive(Default, Debug)]
+pub struct ImportResult {
+    pub accounts: Vec<Account>,
+    pub operations: Vec<Operation>,
+    // Записи файла, не ставшие операциями: без счёта или без даты
+    pub unmatched: usize,
+    // Номера счетов из файла, которых нет в базе
+    pub unknown_accounts: Vec<String>,
+}
+
+// Счёт по номеру или названию среди имеющихся и уже созданных при импорте;
+// если такого нет, он создаётся
+pub(crate) fn find_or_create_account(
+    existing: &[Account],
+    result: &mut ImportResult,
+    number: &str,
+    name: &str,
+    account_type: AccountType,
+) -> Uuid {
+    let matches = |account: &Account| {
+        (!number.is_empty() && account.number == number)
+            || (number.is_empty() && account.name == name)
+    };
+    if let Some(account) = existing
+        .iter()
+        .chain(result.accounts.iter())
+        .find(|a| matches(a))
+    {
+        return account.id;
+    }
+    let account = Account {
+        id: Uuid::new_v4(),
+        name: if name.is_empty() { number } else { name }.to_string(),
+        account_type,
+        number: number.to_string(),
+        bik: 0,
+        sum: 0,
+        cards: Vec::new(),
+        currency: Default::default(),
+    };
+    let id = account.id;
+    result.accounts.push(account);
+    id
+}
+
+// Связывание двух сторон перевода между своими счетами: одинаковая сумма и дата,
+// противоположное направление, разные счета
+pub(crate) fn link_transfers(operations: &mut [Operation], transfers: &[usize]) {
+    for (n, &i) in transfers.iter().enumerate() {
+        if operations[i].transfer_id.is_some() {
+            continue;
+        }
+        let pair = transfers[n + 1..].iter().copied().find(|&j| {
+            let (a, b) = (&operations[i], &operations[j]);
+            b.transfer_id.is_none()
+                && a.account_id != b.account_id
+                && a.summary == b.summary
+                && a.direction != b.direction
+                && a.date_time.date() == b.date_time.date()
+        });
+        if let Some(j) = pair {
+            link_pair(operations, i, j);
+        }
+    }
+}
+
+// Связывание двух операций как сторон одного перевода
+pub(crate) fn link_pair(operations: &mut [Operation], i: usize, j: usize) {
+    operations[i].transfer_id = Some(operations[j].id);
+    operations[j].transfer_id = Some(operations[i].id);
+    for k in [i, j] {
+        operations[k].operation_type = match operations[k].direction {
+            FinanseDirection::Debet => OperationType::DebetingAccounts,
+            FinanseDirection::Credit => OperationType::WithdrawalFromAccounts,
+        };
+    }
+}
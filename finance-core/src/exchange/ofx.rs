@@ -0,0 +1,606 @@
+// Импорт и экспорт Open Financial Exchange: OFX 1.x (SGML) и OFX 2.x (XML)
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1251};
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::account::{Account, AccountType};
+use crate::database::Database;
+use crate::exchange::{ImportError, ImportResult, find_or_create_account, link_transfers};
+use crate::money::parse_decimal;
+use crate::operation::{FinanseDirection, Operation, OperationType};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Version {
+    Sgml,
+    Xml,
+}
+
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    value: Option<String>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn text(&self, path: &[&str]) -> Option<&str> {
+        let mut element = self;
+        for name in path {
+            element = element.child(name)?;
+        }
+        element.value.as_deref()
+    }
+
+    // Все потомки с указанным именем на любой глубине
+    fn find_all<'a>(&'a self, name: &str, found: &mut Vec<&'a Element>) {
+        for child in self.children.iter() {
+            if child.name == name {
+                found.push(child);
+            } else {
+                child.find_all(name, found);
+            }
+        }
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// Разбор дерева элементов. В SGML у листовых элементов нет закрывающих тегов,
+// поэтому элемент со значением закрывается, как только начинается следующий.
+fn parse_tree(text: &str) -> Result<Element, ImportError> {
+    let start = text
+        .find("<OFX>")
+        .ok_or_else(|| ImportError::Format("нет элемента OFX".to_string()))?;
+    let mut stack = vec![Element::default()];
+    let mut rest = &text[start..];
+    while let Some(open) = rest.find('<') {
+        let value = rest[..open].trim();
+        if !value.is_empty()
+            && let Some(top) = stack.last_mut()
+        {
+            top.value = Some(unescape(value));
+        }
+        let close = rest[open..]
+            .find('>')
+            .ok_or_else(|| ImportError::Format("незакрытый тег".to_string()))?
+            + open;
+        let tag = &rest[open + 1..close];
+        rest = &rest[close + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            if !stack.iter().skip(1).any(|element| element.name == name) {
+                continue;
+            }
+            while let Some(element) = stack.pop() {
+                let done = element.name == name;
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(element);
+                }
+                if done {
+                    break;
+                }
+            }
+        } else {
+            if stack.len() > 1 && stack.last().is_some_and(|top| top.value.is_some()) {
+                let leaf = stack.pop().unwrap();
+                stack.last_mut().unwrap().children.push(leaf);
+            }
+            stack.push(Element {
+                name: tag.trim().to_uppercase(),
+                ..Default::default()
+            });
+        }
+    }
+    while stack.len() > 1 {
+        let element = stack.pop().unwrap();
+        stack.last_mut().unwrap().children.push(element);
+    }
+    stack
+        .pop()
+        .and_then(|root| root.children.into_iter().find(|e| e.name == "OFX"))
+        .ok_or_else(|| ImportError::Format("нет элемента OFX".to_string()))
+}
+
+// Дата в формате YYYYMMDD[HHMMSS[.XXX]][[gmt:tz]]
+fn parse_date(value: &str) -> Option<NaiveDateTime> {
+    let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let date = NaiveDate::parse_from_str(digits.get(..8)?, "%Y%m%d").ok()?;
+    let time = digits
+        .get(8..14)
+        .and_then(|time| NaiveTime::parse_from_str(time, "%H%M%S").ok())
+        .unwrap_or_default();
+    Some(NaiveDateTime::new(date, time))
+}
+
+fn format_date(date_time: &NaiveDateTime) -> String {
+    date_time.format("%Y%m%d%H%M%S").to_string()
+}
+
+fn account_type(ofx_type: &str) -> AccountType {
+    match ofx_type {
+        "SAVINGS" | "MONEYMRKT" => AccountType::AccumulativeAccount,
+        "CREDITLINE" => AccountType::CreditAccount,
+        "CD" => AccountType::Deposit,
+        _ => AccountType::Account,
+    }
+}
+
+fn ofx_type(account_type: &AccountType) -> &'static str {
+    match account_type {
+        AccountType::AccumulativeAccount => "SAVINGS",
+        AccountType::CreditAccount => "CREDITLINE",
+        AccountType::Deposit => "CD",
+        _ => "CHECKING",
+    }
+}
+
+pub fn parse(db: &Database, text: &str) -> Result<ImportResult, ImportError> {
+    let root = parse_tree(text)?;
+    let mut result = ImportResult::default();
+    let mut transfers = Vec::new();
+
+    let mut statements = Vec::new();
+    root.find_all("STMTRS", &mut statements);
+    root.find_all("CCSTMTRS", &mut statements);
+    for statement in statements {
+        let (number, account_type) = if statement.name == "CCSTMTRS" {
+            let number = statement.text(&["CCACCTFROM", "ACCTID"]).unwrap_or("");
+            (number, AccountType::CreditCard)
+        } else {
+            let number = statement.text(&["BANKACCTFROM", "ACCTID"]).unwrap_or("");
+            let kind = statement.text(&["BANKACCTFROM", "ACCTTYPE"]).unwrap_or("");
+            (number, self::account_type(kind))
+        };
+        // Счёт без номера выгружается под своим id
+        let known = Uuid::parse_str(number)
+            .ok()
+            .filter(|id| db.accounts.iter().any(|account| account.id == *id));
+        let account_id = match known {
+            Some(id) => id,
+            None => find_or_create_account(&db.accounts, &mut result, number, "", account_type),
+        };
+
+        let mut transactions = Vec::new();
+        statement.find_all("STMTTRN", &mut transactions);
+        for transaction in transactions {
+            let kind = transaction.text(&["TRNTYPE"]).unwrap_or("");
+            let raw_date = transaction.text(&["DTPOSTED"]).unwrap_or("");
+            let date_time = parse_date(raw_date)
+                .ok_or_else(|| ImportError::Format(format!("неверная дата '{}'", raw_date)))?;
+            let raw_amount = transaction.text(&["TRNAMT"]).unwrap_or("");
+            let amount = parse_decimal(raw_amount)
+                .ok_or_else(|| ImportError::Format(format!("неверная сумма '{}'", raw_amount)))?;
+            let direction = if amount.is_sign_negative() {
+                FinanseDirection::Credit
+            } else {
+                FinanseDirection::Debet
+            };
+            let operation_type = match (kind, &direction) {
+                (_, FinanseDirection::Debet) => OperationType::DebetingAccounts,
+                ("POS", FinanseDirection::Credit) => OperationType::Buy,
+                (_, FinanseDirection::Credit) => OperationType::WithdrawalFromAccounts,
+            };
+            let counterparty = transaction
+                .text(&["NAME"])
+                .or_else(|| transaction.text(&["PAYEE", "NAME"]));
+            let element = Operation {
+                id: Uuid::new_v4(),
+                date_time,
+                account_id,
+                operation_type,
+                summary: amount.abs(),
+                direction,
+                receipt_id: None,
+                reference: transaction.text(&["FITID"]).map(str::to_string),
+                counterparty: counterparty.map(str::to_string),
+                note: transaction.text(&["MEMO"]).map(str::to_string),
+                category: None,
+                transfer_id: None,
+            };
+            if kind == "XFER" {
+                transfers.push(result.operations.len());
+            }
+            result.operations.push(element);
+        }
+    }
+    link_transfers(&mut result.operations, &transfers);
+    Ok(result)
+}
+
+// Кодировку OFX 1.x объявляет заголовок до <OFX>: ENCODING:UTF-8 или
+// ENCODING:USASCII с кодовой страницей в CHARSET (1251 у российских банков).
+// OFX 2.x - XML в UTF-8. Без объявления файл читается как UTF-8, а если это не
+// UTF-8 - как windows-1251.
+pub fn decode(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    let end = bytes
+        .windows(5)
+        .position(|window| window == b"<OFX>")
+        .unwrap_or(bytes.len());
+    let header = String::from_utf8_lossy(&bytes[..end]);
+    let mut encoding = None;
+    for line in header.lines() {
+        match line.trim().split_once(':') {
+            Some(("ENCODING", "UTF-8")) => encoding = Some(UTF_8),
+            Some(("CHARSET", charset)) if encoding.is_none() => {
+                let charset = charset.trim();
+                encoding = Encoding::for_label(format!("windows-{}", charset).as_bytes())
+                    .or_else(|| Encoding::for_label(charset.as_bytes()));
+            }
+            _ => {}
+        }
+    }
+    let encoding = match encoding {
+        Some(encoding) => encoding,
+        None if std::str::from_utf8(bytes).is_ok() => UTF_8,
+        None => WINDOWS_1251,
+    };
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    text.into_owned()
+}
+
+pub fn read(db: &Database, path: &str) -> Result<ImportResult, ImportError> {
+    let bytes = std::fs::read(path)?;
+    parse(db, &decode(&bytes))
+}
+
+fn tag(out: &mut String, version: Version, name: &str, value: &str) {
+    match version {
+        Version::Sgml => writeln!(out, "<{}>{}", name, escape(value)).unwrap(),
+        Version::Xml => writeln!(out, "<{}>{}</{}>", name, escape(value), name).unwrap(),
+    }
+}
+
+// Выписка по кредитной карте - CCSTMTRS; дебетовая карта в OFX - обычный
+// текущий счёт
+fn statement(out: &mut String, version: Version, db: &Database, account: &Account) {
+    let card = account.account_type == AccountType::CreditCard;
+    let (rs, from) = if card {
+        ("CCSTMTRS", "CCACCTFROM")
+    } else {
+        ("STMTRS", "BANKACCTFROM")
+    };
+    let number = if account.number.is_empty() {
+        account.id.to_string()
+    } else {
+        account.number.clone()
+    };
+    let mut operations: Vec<&Operation> = db
+        .operations
+        .iter()
+        .filter(|operation| operation.account_id == account.id)
+        .collect();
+    operations.sort_by_key(|operation| (operation.date_time, operation.id));
+
+    let rq = if card { "CCSTMTTRNRS" } else { "STMTTRNRS" };
+    writeln!(out, "<{}>", rq).unwrap();
+    tag(out, version, "TRNUID", &account.id.to_string());
+    writeln!(out, "<STATUS>").unwrap();
+    tag(out, version, "CODE", "0");
+    tag(out, version, "SEVERITY", "INFO");
+    writeln!(out, "</STATUS>").unwrap();
+    writeln!(out, "<{}>", rs).unwrap();
+    tag(out, version, "CURDEF", account.currency.code());
+    writeln!(out, "<{}>", from).unwrap();
+    if !card {
+        tag(out, version, "BANKID", &format!("{:09}", account.bik));
+    }
+    tag(out, version, "ACCTID", &number);
+    if !card {
+        tag(out, version, "ACCTTYPE", ofx_type(&account.account_type));
+    }
+    writeln!(out, "</{}>", from).unwrap();
+    writeln!(out, "<BANKTRANLIST>").unwrap();
+    if let (Some(first), Some(last)) = (operations.first(), operations.last()) {
+        tag(out, version, "DTSTART", &format_date(&first.date_time));
+        tag(out, version, "DTEND", &format_date(&last.date_time));
+    }
+    for operation in operations {
+        let kind = match (&operation.transfer_id, &operation.direction) {
+            (Some(_), _) => "XFER",
+            (None, FinanseDirection::Debet) => "CREDIT",
+            (None, FinanseDirection::Credit) => "DEBIT",
+        };
+        let amount = match operation.direction {
+            FinanseDirection::Debet => operation.summary,
+            FinanseDirection::Credit => -operation.summary,
+        };
+        writeln!(out, "<STMTTRN>").unwrap();
+        tag(out, version, "TRNTYPE", kind);
+        tag(out, version, "DTPOSTED", &format_date(&operation.date_time));
+        tag(out, version, "TRNAMT", &amount.to_string());
+        let id = operation.id.to_string();
+        tag(
+            out,
+            version,
+            "FITID",
+            operation.reference.as_deref().unwrap_or(&id),
+        );
+        if let Some(counterparty) = &operation.counterparty {
+            tag(out, version, "NAME", counterparty);
+        }
+        if let Some(note) = &operation.note {
+            tag(out, version, "MEMO", note);
+        }
+        writeln!(out, "</STMTTRN>").unwrap();
+    }
+    writeln!(out, "</BANKTRANLIST>").unwrap();
+    writeln!(out, "</{}>", rs).unwrap();
+    writeln!(out, "</{}>", rq).unwrap();
+}
+
+// Выгрузка всех счетов базы одним файлом: банковские счета в BANKMSGSRSV1,
+// кредитные карты - в CREDITCARDMSGSRSV1
+pub fn export(db: &Database, version: Version) -> String {
+    let mut out = String::new();
+    match version {
+        Version::Sgml => {
+            out.push_str("OFXHEADER:100\nDATA:OFXSGML\nVERSION:102\nSECURITY:NONE\n");
+            out.push_str("ENCODING:UTF-8\nCHARSET:NONE\nCOMPRESSION:NONE\n");
+            out.push_str("OLDFILEUID:NONE\nNEWFILEUID:NONE\n\n");
+        }
+        Version::Xml => {
+            out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+            out.push_str("<?OFX OFXHEADER=\"200\" VERSION=\"220\" SECURITY=\"NONE\" ");
+            out.push_str("OLDFILEUID=\"NONE\" NEWFILEUID=\"NONE\"?>\n");
+        }
+    }
+    out.push_str("<OFX>\n<SIGNONMSGSRSV1>\n<SONRS>\n<STATUS>\n");
+    tag(&mut out, version, "CODE", "0");
+    tag(&mut out, version, "SEVERITY", "INFO");
+    out.push_str("</STATUS>\n");
+    tag(
+        &mut out,
+        version,
+        "DTSERVER",
+        &format_date(&chrono::Local::now().naive_local()),
+    );
+    tag(&mut out, version, "LANGUAGE", "RUS");
+    out.push_str("</SONRS>\n</SIGNONMSGSRSV1>\n");
+
+    let (cards, banks): (Vec<&Account>, Vec<&Account>) = db
+        .accounts
+        .iter()
+        .partition(|account| account.account_type == AccountType::CreditCard);
+    if !banks.is_empty() {
+        out.push_str("<BANKMSGSRSV1>\n");
+        for account in banks {
+            statement(&mut out, version, db, account);
+        }
+        out.push_str("</BANKMSGSRSV1>\n");
+    }
+    if !cards.is_empty() {
+        out.push_str("<CREDITCARDMSGSRSV1>\n");
+        for account in cards {
+            statement(&mut out, version, db, account);
+        }
+        out.push_str("</CREDITCARDMSGSRSV1>\n");
+    }
+    out.push_str("</OFX>\n");
+    out
+}
+
+pub fn write(db: &Database, path: &str, version: Version) -> Result<(), ImportError> {
+    std::fs::write(path, export(db, version))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::{Decimal, dec};
+
+    const SGML: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<BANKMSGSRSV1>
+<STMTTRNRS>
+<STMTRS>
+<CURDEF>RUB
+<BANKACCTFROM>
+<BANKID>044525225
+<ACCTID>40817810000000000001
+<ACCTTYPE>SAVINGS
+</BANKACCTFROM>
+<BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>POS
+<DTPOSTED>20260301120000.000[+3:MSK]
+<TRNAMT>-1234.50
+<FITID>A1
+<NAME>Shop &amp; Co
+<MEMO>Покупка
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20260302
+<TRNAMT>500
+<FITID>A2
+<PAYEE><NAME>Employer</PAYEE>
+</STMTTRN>
+</BANKTRANLIST>
+</STMTRS>
+</STMTTRNRS>
+</BANKMSGSRSV1>
+</OFX>
+";
+
+    fn account(name: &str, account_type: AccountType) -> Account {
+        Account {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            account_type,
+            number: format!("{}-number", name),
+            bik: 44525225,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Default::default(),
+        }
+    }
+
+    fn operation(account: &Account, summary: Decimal, direction: FinanseDirection) -> Operation {
+        Operation {
+            id: Uuid::new_v4(),
+            date_time: NaiveDate::from_ymd_opt(2026, 3, 1).unwrap().into(),
+            account_id: account.id,
+            operation_type: OperationType::Buy,
+            summary,
+            direction,
+            receipt_id: None,
+            reference: None,
+            counterparty: Some("Shop & Co <Moscow>".to_string()),
+            note: Some("memo".to_string()),
+            category: None,
+            transfer_id: None,
+        }
+    }
+
+    fn database() -> Database {
+        let mut db = Database::new();
+        let bank = account("Main", AccountType::Account);
+        let card = account("Card", AccountType::DebetCard);
+        let mut credit = account("Credit", AccountType::CreditCard);
+        credit.number = String::new();
+        let mut purchase = operation(&credit, dec!(15), FinanseDirection::Credit);
+        purchase.reference = Some("AUTH-7".to_string());
+        db.operations.push(purchase);
+        db.operations
+            .push(operation(&bank, dec!(100.50), FinanseDirection::Credit));
+        let mut from = operation(&bank, dec!(30), FinanseDirection::Credit);
+        let mut to = operation(&card, dec!(30), FinanseDirection::Debet);
+        from.transfer_id = Some(to.id);
+        to.transfer_id = Some(from.id);
+        db.operations.push(from);
+        db.operations.push(to);
+        db.accounts = vec![bank, card, credit];
+        db
+    }
+
+    #[test]
+    fn parse_sgml() {
+        let result = parse(&Database::new(), SGML).unwrap();
+        assert_eq!(result.accounts.len(), 1);
+        assert_eq!(result.accounts[0].number, "40817810000000000001");
+        assert_eq!(
+            result.accounts[0].account_type,
+            AccountType::AccumulativeAccount
+        );
+        assert_eq!(result.operations.len(), 2);
+
+        let purchase = &result.operations[0];
+        assert_eq!(purchase.summary, dec!(1234.50));
+        assert_eq!(purchase.direction, FinanseDirection::Credit);
+        assert_eq!(purchase.operation_type, OperationType::Buy);
+        assert_eq!(purchase.counterparty.as_deref(), Some("Shop & Co"));
+        assert_eq!(purchase.note.as_deref(), Some("Покупка"));
+        assert_eq!(purchase.reference.as_deref(), Some("A1"));
+        assert_eq!(
+            purchase.date_time,
+            NaiveDate::from_ymd_opt(2026, 3, 1)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+        );
+
+        let salary = &result.operations[1];
+        assert_eq!(salary.direction, FinanseDirection::Debet);
+        assert_eq!(salary.counterparty.as_deref(), Some("Employer"));
+    }
+
+    #[test]
+    fn decode_charset() {
+        let text = SGML.replace("VERSION:102", "VERSION:102\nENCODING:USASCII\nCHARSET:1251");
+        let (bytes, _, _) = WINDOWS_1251.encode(&text);
+        assert_eq!(decode(&bytes), text);
+        let result = parse(&Database::new(), &decode(&bytes)).unwrap();
+        assert_eq!(result.operations[0].note.as_deref(), Some("Покупка"));
+        // Объявленный UTF-8 и файл без объявления
+        let text = SGML.replace("VERSION:102", "VERSION:102\nENCODING:UTF-8\nCHARSET:NONE");
+        assert_eq!(decode(text.as_bytes()), text);
+        assert_eq!(decode(SGML.as_bytes()), SGML);
+        let (bytes, _, _) = WINDOWS_1251.encode(SGML);
+        assert_eq!(decode(&bytes), SGML);
+    }
+
+    #[test]
+    fn format_errors() {
+        assert!(matches!(
+            parse(&Database::new(), "<HTML></HTML>"),
+            Err(ImportError::Format(_))
+        ));
+        let text = SGML.replace("<TRNAMT>500", "<TRNAMT>five");
+        assert!(parse(&Database::new(), &text).is_err());
+        assert_eq!(parse_date("2026"), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        let db = database();
+        for version in [Version::Sgml, Version::Xml] {
+            let text = export(&db, version);
+            let result = parse(&Database::new(), &text).unwrap();
+            assert_eq!(result.accounts.len(), 3, "{}", text);
+            // Дебетовая карта выгружается как текущий счёт
+            assert_eq!(result.accounts[1].account_type, AccountType::Account);
+            assert_eq!(result.accounts[2].account_type, AccountType::CreditCard);
+            assert_eq!(result.operations.len(), 4);
+            // Операции одного дня выгружаются в порядке id
+            let mut summaries: Vec<Decimal> = result.operations.iter().map(|o| o.summary).collect();
+            summaries.sort();
+            assert_eq!(summaries, vec![dec!(15), dec!(30), dec!(30), dec!(100.50)]);
+            assert!(
+                result
+                    .operations
+                    .iter()
+                    .all(|o| o.counterparty.as_deref() == Some("Shop & Co <Moscow>"))
+            );
+            let transfers: Vec<&Operation> = result
+                .operations
+                .iter()
+                .filter(|o| o.summary == dec!(30))
+                .collect();
+            assert_eq!(transfers[0].transfer_id, Some(transfers[1].id));
+            assert_eq!(transfers[1].transfer_id, Some(transfers[0].id));
+
+            // Счета уже есть в базе и находятся по номеру, счёт без номера - по id;
+            // операции возвращаются на свои счета с тем же номером документа
+            let again = parse(&db, &text).unwrap();
+            assert!(again.accounts.is_empty());
+            for operation in db.operations.iter() {
+                let id = operation.id.to_string();
+                let reference = operation.reference.as_deref().unwrap_or(&id);
+                let imported = again
+                    .operations
+                    .iter()
+                    .find(|o| o.reference.as_deref() == Some(reference))
+                    .unwrap();
+                assert_eq!(imported.account_id, operation.account_id);
+                assert_eq!(imported.summary, operation.summary);
+                assert_eq!(imported.direction, operation.direction);
+                assert_eq!(imported.date_time, operation.date_time);
+            }
+        }
+    }
+}
@@ -0,0 +1,429 @@
+// Импорт и экспорт Quicken Interchange Format
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::account::AccountType;
+use crate::database::Database;
+use crate::exchange::{ImportError, ImportResult, find_or_create_account, link_transfers};
+use crate::money::parse_decimal;
+use crate::operation::{FinanseDirection, Operation, OperationType};
+
+fn account_type(qif_type: &str) -> AccountType {
+    match qif_type.trim() {
+        "Cash" => AccountType::Cash,
+        "CCard" => AccountType::CreditCard,
+        "Oth L" => AccountType::CreditAccount,
+        _ => AccountType::Account,
+    }
+}
+
+fn qif_type(account_type: &AccountType) -> &'static str {
+    match account_type {
+        AccountType::Cash => "Cash",
+        AccountType::CreditCard => "CCard",
+        AccountType::CreditAccount => "Oth L",
+        _ => "Bank",
+    }
+}
+
+// Даты встречаются как 03/25/2026, 3/25'26 (американский порядок) и 25.03.2026
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim().replace('\'', "/").replace(' ', "");
+    if value.contains('.') {
+        return NaiveDate::parse_from_str(&value, "%d.%m.%Y").ok();
+    }
+    let mut parts = value.split(['/', '-']);
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    let year: i32 = parts.next()?.parse().ok()?;
+    let year = if year < 100 { 2000 + year } else { year };
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+// В американских файлах запятая отделяет тысячи: -1,234.50 и -1,234. Запятая,
+// за которой не ровно три цифры, - десятичная: 12,50
+fn parse_amount(value: &str) -> Option<Decimal> {
+    let value = value.trim();
+    let mut groups = value.split(',').skip(1).peekable();
+    let thousands = groups.peek().is_some()
+        && groups.all(|group| group.len() == 3 && group.chars().all(|c| c.is_ascii_digit()));
+    if value.contains('.') || thousands {
+        parse_decimal(&value.replace(',', ""))
+    } else {
+        parse_decimal(value)
+    }
+}
+
+#[derive(Default)]
+struct Record {
+    date: Option<NaiveDate>,
+    amount: Option<String>,
+    payee: Option<String>,
+    memo: Option<String>,
+    category: Option<String>,
+    number: Option<String>,
+}
+
+pub fn parse(db: &Database, text: &str) -> Result<ImportResult, ImportError> {
+    let mut result = ImportResult::default();
+    // Переводы записаны с категорией [Счёт], обе стороны связываются после разбора
+    let mut transfers = Vec::new();
+
+    let mut account_id: Option<Uuid> = None;
+    let mut in_account = false;
+    let mut section_type = AccountType::Account;
+    let mut account_name = String::new();
+    let mut account_kind = String::new();
+    let mut record = Record::default();
+
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('!') {
+            if header == "Account" {
+                in_account = true;
+                account_name.clear();
+                account_kind.clear();
+            } else if let Some(kind) = header.strip_prefix("Type:") {
+                section_type = account_type(kind);
+                in_account = false;
+            }
+            continue;
+        }
+        // Код - первый символ, строка может начинаться и с кириллицы
+        let first = line.chars().next().unwrap_or_default();
+        let (code, value) = line.split_at(first.len_utf8());
+        if in_account {
+            match code {
+                "N" => account_name = value.to_string(),
+                "T" => account_kind = value.to_string(),
+                "^" => {
+                    account_id = Some(find_or_create_account(
+                        &db.accounts,
+                        &mut result,
+                        "",
+                        &account_name,
+                        account_type(&account_kind),
+                    ));
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match code {
+            "D" => record.date = parse_date(value),
+            "T" | "U" => record.amount = Some(value.to_string()),
+            "P" => record.payee = Some(value.to_string()),
+            "M" => record.memo = Some(value.to_string()),
+            "L" => record.category = Some(value.to_string()),
+            "N" => record.number = Some(value.to_string()),
+            "^" => {
+                let current = std::mem::take(&mut record);
+                let date = current
+                    .date
+                    .ok_or_else(|| ImportError::Format("запись без даты".to_string()))?;
+                let raw_amount = current.amount.unwrap_or_default();
+                let amount = parse_amount(&raw_amount).ok_or_else(|| {
+                    ImportError::Format(format!("неверная сумма '{}'", raw_amount))
+                })?;
+                let account = match account_id {
+                    Some(id) => id,
+                    None => {
+                        let id = find_or_create_account(
+                            &db.accounts,
+                            &mut result,
+                            "",
+                            "QIF",
+                            section_type.clone(),
+                        );
+                        account_id = Some(id);
+                        id
+                    }
+                };
+                let direction = if amount.is_sign_negative() {
+                    FinanseDirection::Credit
+                } else {
+                    FinanseDirection::Debet
+                };
+                let operation_type = match direction {
+                    FinanseDirection::Debet => OperationType::DebetingAccounts,
+                    FinanseDirection::Credit => OperationType::WithdrawalFromAccounts,
+                };
+                let transfer = current
+                    .category
+                    .as_deref()
+                    .and_then(|c| c.strip_prefix('[')?.strip_suffix(']'))
+                    .map(str::to_string);
+                let element = Operation {
+                    id: Uuid::new_v4(),
+                    date_time: NaiveDateTime::from(date),
+                    account_id: account,
+                    operation_type,
+                    summary: amount.abs(),
+                    direction,
+                    receipt_id: None,
+                    reference: current.number,
+                    counterparty: current.payee,
+                    note: current.memo,
+                    category: if transfer.is_some() {
+                        None
+                    } else {
+                        current.category
+                    },
+                    transfer_id: None,
+                };
+                if transfer.is_some() {
+                    transfers.push(result.operations.len());
+                }
+                result.operations.push(element);
+            }
+            _ => {}
+        }
+    }
+
+    link_transfers(&mut result.operations, &transfers);
+    Ok(result)
+}
+
+pub fn read(db: &Database, path: &str) -> Result<ImportResult, ImportError> {
+    let bytes = std::fs::read(path)?;
+    let text = String::from_utf8_lossy(&bytes);
+    // Метка порядка байтов, которую дописывают редакторы Windows
+    parse(db, text.strip_prefix('\u{FEFF}').unwrap_or(&text))
+}
+
+pub fn export(db: &Database) -> String {
+    let mut out = String::new();
+    let name = |id: Uuid| -> String {
+        db.accounts
+            .iter()
+            .find(|account| account.id == id)
+            .map(|account| account.name.clone())
+            .unwrap_or_default()
+    };
+    for account in db.accounts.iter() {
+        writeln!(out, "!Account").unwrap();
+        writeln!(out, "N{}", account.name).unwrap();
+        writeln!(out, "T{}", qif_type(&account.account_type)).unwrap();
+        writeln!(out, "^").unwrap();
+        writeln!(out, "!Type:{}", qif_type(&account.account_type)).unwrap();
+
+        let mut operations: Vec<&Operation> = db
+            .operations
+            .iter()
+            .filter(|operation| operation.account_id == account.id)
+            .collect();
+        operations.sort_by_key(|operation| (operation.date_time, operation.id));
+        for operation in operations {
+            let amount = match operation.direction {
+                FinanseDirection::Debet => operation.summary,
+                FinanseDirection::Credit => -operation.summary,
+            };
+            writeln!(out, "D{}", operation.date_time.format("%m/%d/%Y")).unwrap();
+            writeln!(out, "T{}", amount).unwrap();
+            if let Some(number) = &operation.reference {
+                writeln!(out, "N{}", number).unwrap();
+            }
+            if let Some(counterparty) = &operation.counterparty {
+                writeln!(out, "P{}", counterparty).unwrap();
+            }
+            if let Some(note) = &operation.note {
+                writeln!(out, "M{}", note.replace('\n', " ")).unwrap();
+            }
+            let transfer = operation.transfer_id.and_then(|id| {
+                db.operations
+                    .iter()
+                    .find(|other| other.id == id)
+                    .map(|other| name(other.account_id))
+            });
+            if let Some(target) = transfer {
+                writeln!(out, "L[{}]", target).unwrap();
+            } else if let Some(category) = &operation.category {
+                writeln!(out, "L{}", category).unwrap();
+            }
+            writeln!(out, "^").unwrap();
+        }
+    }
+    out
+}
+
+pub fn write(db: &Database, path: &str) -> Result<(), ImportError> {
+    std::fs::write(path, export(db))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::Account;
+    use rust_decimal::dec;
+
+    const QIF: &str = "!Account
+NMain
+TBank
+^
+!Type:Bank
+D03/25/2026
+T-1,234.50
+PShop
+MЕда
+LFood
+N15
+^
+D3/26'26
+T500
+PEmployer
+^
+D27.03.2026
+T-30
+L[Savings]
+^
+!Account
+NSavings
+TBank
+^
+!Type:Bank
+D03/27/2026
+T30
+L[Main]
+^
+";
+
+    fn account(name: &str, account_type: AccountType) -> Account {
+        Account {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            account_type,
+            number: String::new(),
+            bik: 0,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Default::default(),
+        }
+    }
+
+    fn operation(account: &Account, summary: Decimal, direction: FinanseDirection) -> Operation {
+        Operation {
+            id: Uuid::new_v4(),
+            date_time: NaiveDate::from_ymd_opt(2026, 3, 1).unwrap().into(),
+            account_id: account.id,
+            operation_type: OperationType::Buy,
+            summary,
+            direction,
+            receipt_id: None,
+            reference: Some("7".to_string()),
+            counterparty: Some("Shop".to_string()),
+            note: Some("memo".to_string()),
+            category: Some("Food".to_string()),
+            transfer_id: None,
+        }
+    }
+
+    #[test]
+    fn dates() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 25);
+        assert_eq!(parse_date("03/25/2026"), date);
+        assert_eq!(parse_date("3/25'26"), date);
+        assert_eq!(parse_date("3/25' 26"), date);
+        assert_eq!(parse_date("25.03.2026"), date);
+        assert_eq!(parse_date("25/03/2026"), None);
+    }
+
+    #[test]
+    fn parse_accounts_and_transfers() {
+        let result = parse(&Database::new(), QIF).unwrap();
+        let names: Vec<&str> = result.accounts.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["Main", "Savings"]);
+        assert_eq!(result.operations.len(), 4);
+
+        let purchase = &result.operations[0];
+        assert_eq!(purchase.summary, dec!(1234.50));
+        assert_eq!(purchase.direction, FinanseDirection::Credit);
+        assert_eq!(purchase.category.as_deref(), Some("Food"));
+        assert_eq!(purchase.note.as_deref(), Some("Еда"));
+        assert_eq!(purchase.reference.as_deref(), Some("15"));
+        assert_eq!(result.operations[1].direction, FinanseDirection::Debet);
+
+        let (from, to) = (&result.operations[2], &result.operations[3]);
+        assert_eq!(from.category, None);
+        assert_eq!(from.transfer_id, Some(to.id));
+        assert_eq!(to.account_id, result.accounts[1].id);
+    }
+
+    #[test]
+    fn amounts() {
+        assert_eq!(parse_amount("-1,234.50"), Some(dec!(-1234.50)));
+        assert_eq!(parse_amount("1,234"), Some(dec!(1234)));
+        assert_eq!(parse_amount("-1,234,567"), Some(dec!(-1234567)));
+        assert_eq!(parse_amount("12,50"), Some(dec!(12.50)));
+        assert_eq!(parse_amount("1,2345"), Some(dec!(1.2345)));
+        assert_eq!(parse_amount("100"), Some(dec!(100)));
+    }
+
+    #[test]
+    fn bom_and_cyrillic_lines() {
+        let path = std::env::temp_dir().join(format!("finance-core-{}.qif", Uuid::new_v4()));
+        let text = "\u{FEFF}!Account\nNКарта\n^\n!Type:Bank\nD03/25/2026\nT-100\nPМагазин\nпродолжение строки\n^\n";
+        std::fs::write(&path, text).unwrap();
+        let result = read(&Database::new(), path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(result.accounts[0].name, "Карта");
+        assert_eq!(result.operations.len(), 1);
+        assert_eq!(
+            result.operations[0].counterparty.as_deref(),
+            Some("Магазин")
+        );
+    }
+
+    #[test]
+    fn format_errors() {
+        assert!(parse(&Database::new(), "!Type:Bank\nT10\n^\n").is_err());
+        assert!(parse(&Database::new(), "!Type:Bank\nD03/25/2026\nTten\n^\n").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut db = Database::new();
+        let bank = account("Main", AccountType::Account);
+        let cash = account("Wallet", AccountType::Cash);
+        db.operations
+            .push(operation(&bank, dec!(100.50), FinanseDirection::Credit));
+        let mut from = operation(&bank, dec!(30), FinanseDirection::Credit);
+        let mut to = operation(&cash, dec!(30), FinanseDirection::Debet);
+        from.transfer_id = Some(to.id);
+        to.transfer_id = Some(from.id);
+        db.operations.push(from);
+        db.operations.push(to);
+        db.accounts = vec![bank, cash];
+
+        let text = export(&db);
+        let result = parse(&Database::new(), &text).unwrap();
+        assert_eq!(result.accounts.len(), 2, "{}", text);
+        assert_eq!(result.accounts[1].account_type, AccountType::Cash);
+        assert_eq!(result.operations.len(), 3);
+        // Операции одного дня выгружаются в порядке id
+        let first = result
+            .operations
+            .iter()
+            .find(|o| o.summary == dec!(100.50))
+            .unwrap();
+        assert_eq!(first.direction, FinanseDirection::Credit);
+        assert_eq!(first.counterparty.as_deref(), Some("Shop"));
+        assert_eq!(first.category.as_deref(), Some("Food"));
+        assert_eq!(first.reference.as_deref(), Some("7"));
+        let linked = result
+            .operations
+            .iter()
+            .filter(|o| o.transfer_id.is_some())
+            .count();
+        assert_eq!(linked, 2);
+
+        let again = parse(&db, &text).unwrap();
+        assert!(again.accounts.is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+// Выгрузка таблиц (report::Sheet) в CSV и XLSX
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::exchange::ImportError;
+use crate::report::{Cell, Sheet};
+
+impl From<XlsxError> for ImportError {
+    fn from(error: XlsxError) -> Self {
+        ImportError::Format(error.to_string())
+    }
+}
+
+fn csv_field(value: &str, delimiter: char) -> String {
+    if value.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_value(cell: &Cell) -> String {
+    match cell {
+        Cell::Empty => String::new(),
+        Cell::Text(text) => text.clone(),
+        Cell::Number(number) => number.to_string(),
+        Cell::Integer(number) => number.to_string(),
+        Cell::DateTime(date_time) => date_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+    }
+}
+
+pub fn to_csv(sheet: &Sheet, delimiter: char) -> String {
+    let mut out = String::new();
+    let line = |values: Vec<String>| -> String {
+        values
+            .iter()
+            .map(|value| csv_field(value, delimiter))
+            .collect::<Vec<_>>()
+            .join(&delimiter.to_string())
+    };
+    writeln!(out, "{}", line(sheet.columns.clone())).unwrap();
+    for row in sheet.rows.iter() {
+        writeln!(out, "{}", line(row.iter().map(csv_value).collect())).unwrap();
+    }
+    out
+}
+
+// Одна таблица пишется в указанный файл, несколько - в файлы с именем листа
+// в суффиксе: operations.csv -> operations_Receipts.csv. BOM нужен Excel,
+// чтобы распознать UTF-8.
+pub fn write_csv(sheets: &[Sheet], path: &str, delimiter: char) -> Result<(), ImportError> {
+    for sheet in sheets.iter() {
+        let target = if sheets.len() == 1 {
+            path.to_string()
+        } else {
+            let path = Path::new(path);
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("export");
+            let name = format!("{}_{}.csv", stem, sheet.name.replace(' ', "_"));
+            path.with_file_name(name).to_string_lossy().into_owned()
+        };
+        std::fs::write(target, format!("\u{FEFF}{}", to_csv(sheet, delimiter)))?;
+    }
+    Ok(())
+}
+
+// Имя листа Excel: до 31 символа, без []:*?/\
+fn sheet_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if "[]:*?/\\".contains(c) { '_' } else { c })
+        .take(31)
+        .collect()
+}
+
+// Каждая таблица - отдельный лист; суммы пишутся числами, даты - датами Excel
+pub fn write_xlsx(sheets: &[Sheet], path: &str) -> Result<(), ImportError> {
+    let mut workbook = Workbook::new();
+    let header = Format::new().set_bold();
+    let money = Format::new().set_num_format("#,##0.00###");
+    let date = Format::new().set_num_format("dd.mm.yyyy hh:mm");
+    for sheet in sheets.iter() {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(sheet_name(&sheet.name))?;
+        for (col, column) in sheet.columns.iter().enumerate() {
+            worksheet.write_string_with_format(0, col as u16, column, &header)?;
+        }
+        for (i, row) in sheet.rows.iter().enumerate() {
+            let line = i as u32 + 1;
+            for (col, cell) in row.iter().enumerate() {
+                let col = col as u16;
+                match cell {
+                    Cell::Empty => {}
+                    Cell::Text(text) => {
+                        worksheet.write_string(line, col, text)?;
+                    }
+                    Cell::Number(number) => {
+                        let value = number.to_f64().unwrap_or_default();
+                        worksheet.write_number_with_format(line, col, value, &money)?;
+                    }
+                    Cell::Integer(number) => {
+                        worksheet.write_number(line, col, *number as f64)?;
+                    }
+                    Cell::DateTime(date_time) => {
+                        worksheet.write_datetime_with_format(line, col, date_time, &date)?;
+                    }
+                }
+            }
+        }
+        worksheet.set_freeze_panes(1, 0)?;
+        worksheet.autofit();
+    }
+    workbook.save(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use rust_decimal::dec;
+    use uuid::Uuid;
+
+    fn sheet(name: &str) -> Sheet {
+        let mut sheet = Sheet::new(name, &["Date", "Note", "Amount", "No"]);
+        sheet.rows.push(vec![
+            Cell::DateTime(
+                NaiveDate::from_ymd_opt(2026, 3, 1)
+                    .unwrap()
+                    .and_hms_opt(9, 30, 0)
+                    .unwrap(),
+            ),
+            Cell::Text("Магазин \"Ромашка\"; Москва".to_string()),
+            Cell::Number(dec!(-1234.50)),
+            Cell::Integer(1),
+        ]);
+        sheet.rows.push(vec![
+            Cell::Empty,
+            Cell::Text("две\nстроки".to_string()),
+            Cell::Empty,
+            Cell::Empty,
+        ]);
+        sheet
+    }
+
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(
+            to_csv(&sheet("Operations"), ';'),
+            "Date;Note;Amount;No\n\
+             2026-03-01 09:30:00;\"Магазин \"\"Ромашка\"\"; Москва\";-1234.50;1\n\
+             ;\"две\nстроки\";;\n"
+        );
+        // Кавычки ставятся только ради выбранного разделителя
+        assert_eq!(csv_field("a;b", ','), "a;b");
+        assert_eq!(csv_field("a,b", ','), "\"a,b\"");
+    }
+
+    #[test]
+    fn csv_files_per_sheet() {
+        let dir = std::env::temp_dir().join(format!("finance-core-{}", Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("operations.csv");
+        let path = path.to_str().unwrap();
+
+        write_csv(&[sheet("Operations")], path, ';').unwrap();
+        let text = std::fs::read_to_string(path).unwrap();
+        assert!(text.starts_with("\u{FEFF}Date;"));
+
+        write_csv(&[sheet("Operations"), sheet("Monthly 2026")], path, ';').unwrap();
+        assert!(dir.join("operations_Operations.csv").exists());
+        assert!(dir.join("operations_Monthly_2026.csv").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn excel_sheet_names() {
+        assert_eq!(sheet_name("Итоги [2026]: 1/2"), "Итоги _2026__ 1_2");
+        assert_eq!(sheet_name(&"x".repeat(40)).chars().count(), 31);
+    }
+}
@@ -0,0 +1,369 @@
+// История изменений для отмены и повтора. Каждое изменение базы через Store
+// записывается как состояние записи до и после; изменения, сделанные вместе,
+// собираются в одну команду. Отмена команды возвращает состояния "до" в
+// обратном порядке, повтор - состояния "после" в прямом.
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::account::Account;
+use crate::exchange::csv::CsvProfile;
+use crate::operation::Operation;
+use crate::receipt::Receipt;
+use crate::trash::Deleted;
+use crate::view::SavedView;
+
+// Списки базы целиком, без истории и журнала
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Lists {
+    pub accounts: Vec<Account>,
+    pub operations: Vec<Operation>,
+    pub receipts: Vec<Receipt>,
+    pub views: Vec<SavedView>,
+    pub trash: Vec<Deleted>,
+    pub csv_profiles: Vec<CsvProfile>,
+}
+
+// Изменение одной записи; None - записи нет
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Change {
+    Account {
+        before: Option<Account>,
+        after: Option<Account>,
+    },
+    Operation {
+        before: Option<Operation>,
+        after: Option<Operation>,
+    },
+    // Чек заметно больше остальных записей
+    Receipt {
+        before: Option<Box<Receipt>>,
+        after: Option<Box<Receipt>>,
+    },
+    View {
+        before: Option<SavedView>,
+        after: Option<SavedView>,
+    },
+    Trash {
+        before: Option<Box<Deleted>>,
+        after: Option<Box<Deleted>>,
+    },
+    // Профили CSV не имеют идентификатора и хранятся списком целиком
+    Profiles {
+        before: Vec<CsvProfile>,
+        after: Vec<CsvProfile>,
+    },
+    // Правка в обход индексов (исправление целостности) хранит списки целиком:
+    // при сравнении по id повторы схлопываются, а отмена должна вернуть их в
+    // точности. changes - изменения отдельных записей для журнала и названия.
+    Lists {
+        before: Box<Lists>,
+        after: Box<Lists>,
+        changes: Vec<Change>,
+    },
+}
+
+impl Change {
+    pub fn entity(&self) -> &'static str {
+        match self {
+            Change::Account { .. } => "account",
+            Change::Operation { .. } => "operation",
+            Change::Receipt { .. } => "receipt",
+            Change::View { .. } => "view",
+            Change::Trash { .. } => "trash",
+            Change::Profiles { .. } => "CSV profiles",
+            Change::Lists { .. } => "database",
+        }
+    }
+
+    pub fn action(&self) -> &'static str {
+        let (before, after) = match self {
+            Change::Account { before, after } => (before.is_some(), after.is_some()),
+            Change::Operation { before, after } => (before.is_some(), after.is_some()),
+            Change::Receipt { before, after } => (before.is_some(), after.is_some()),
+            Change::View { before, after } => (before.is_some(), after.is_some()),
+            Change::Trash { before, after } => (before.is_some(), after.is_some()),
+            Change::Profiles { .. } | Change::Lists { .. } => (true, true),
+        };
+        match (before, after) {
+            (false, _) => "Create",
+            (true, true) => "Edit",
+            (true, false) => "Delete",
+        }
+    }
+
+    // Идентификатор изменённой записи
+    pub fn id(&self) -> Option<Uuid> {
+        match self {
+            Change::Account { before, after } => after.as_ref().or(before.as_ref()).map(|a| a.id),
+            Change::Operation { before, after } => after.as_ref().or(before.as_ref()).map(|o| o.id),
+            Change::Receipt { before, after } => after.as_ref().or(before.as_ref()).map(|r| r.id),
+            Change::View { before, after } => after.as_ref().or(before.as_ref()).map(|v| v.id),
+            Change::Trash { before, after } => after.as_ref().or(before.as_ref()).map(|g| g.id),
+            Change::Profiles { .. } | Change::Lists { .. } => None,
+        }
+    }
+
+    // Изменения отдельных записей: у снимка списков - описанные в нём
+    pub fn records(&self) -> &[Change] {
+        match self {
+            Change::Lists { changes, .. } if !changes.is_empty() => changes,
+            change => std::slice::from_ref(change),
+        }
+    }
+
+    // Обратное изменение: то, что делает его отмена
+    pub fn reversed(&self) -> Change {
+        match self.clone() {
+            Change::Account { before, after } => Change::Account {
+                before: after,
+                after: before,
+            },
+            Change::Operation { before, after } => Change::Operation {
+                before: after,
+                after: before,
+            },
+            Change::Receipt { before, after } => Change::Receipt {
+                before: after,
+                after: before,
+            },
+            Change::View { before, after } => Change::View {
+                before: after,
+                after: before,
+            },
+            Change::Trash { before, after } => Change::Trash {
+                before: after,
+                after: before,
+            },
+            Change::Profiles { before, after } => Change::Profiles {
+                before: after,
+                after: before,
+            },
+            Change::Lists {
+                before,
+                after,
+                changes,
+            } => Change::Lists {
+                before: after,
+                after: before,
+                changes: changes.iter().rev().map(Change::reversed).collect(),
+            },
+        }
+    }
+
+    pub fn before_json(&self) -> Result<Option<String>, serde_json::Error> {
+        self.reversed().after_json()
+    }
+
+    pub fn after_json(&self) -> Result<Option<String>, serde_json::Error> {
+        match self {
+            Change::Account { after, .. } => json(after),
+            Change::Operation { after, .. } => json(after),
+            Change::Receipt { after, .. } => json(after),
+            Change::View { after, .. } => json(after),
+            Change::Trash { after, .. } => json(after),
+            Change::Profiles { after, .. } => json(&Some(after)),
+            Change::Lists { after, .. } => json(&Some(after)),
+        }
+    }
+}
+
+fn json<T: Serialize>(state: &Option<T>) -> Result<Option<String>, serde_json::Error> {
+    state.as_ref().map(serde_json::to_string).transpose()
+}
+
+// Изменения одной записи в список, сравнение по id: новые, изменённые, удалённые
+pub(crate) fn diff<T: PartialEq + Clone>(
+    before: &[T],
+    after: &[T],
+    id: fn(&T) -> Uuid,
+    change: fn(Option<T>, Option<T>) -> Change,
+) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let old: HashMap<Uuid, &T> = before.iter().map(|item| (id(item), item)).collect();
+    let new: HashMap<Uuid, &T> = after.iter().map(|item| (id(item), item)).collect();
+    for item in after.iter() {
+        match old.get(&id(item)) {
+            Some(previous) if *previous == item => {}
+            previous => changes.push(change(previous.map(|p| (*p).clone()), Some(item.clone()))),
+        }
+    }
+    for item in before.iter() {
+        if !new.contains_key(&id(item)) {
+            changes.push(change(Some(item.clone()), None));
+        }
+    }
+    changes
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Command {
+    pub id: Uuid,
+    pub date_time: NaiveDateTime,
+    pub title: String,
+    pub changes: Vec<Change>,
+}
+
+impl Command {
+    pub fn new(changes: Vec<Change>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            date_time: Local::now().naive_local(),
+            title: title(&changes),
+            changes,
+        }
+    }
+}
+
+// Название команды по её изменениям: "Edit operation", "Create: 12 operation, 3 receipt"
+fn title(changes: &[Change]) -> String {
+    let changes: Vec<&Change> = changes.iter().flat_map(Change::records).collect();
+    if let [change] = changes.as_slice() {
+        return format!("{} {}", change.action(), change.entity());
+    }
+    let mut actions: Vec<&str> = Vec::new();
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for change in changes {
+        if !actions.contains(&change.action()) {
+            actions.push(change.action());
+        }
+        match counts
+            .iter_mut()
+            .find(|(entity, _)| *entity == change.entity())
+        {
+            Some((_, count)) => *count += 1,
+            None => counts.push((change.entity(), 1)),
+        }
+    }
+    let counts: Vec<String> = counts
+        .iter()
+        .map(|(entity, count)| format!("{} {}", count, entity))
+        .collect();
+    format!("{}: {}", actions.join("/"), counts.join(", "))
+}
+
+// Выполненные команды по порядку и отменённые, последняя отменённая в конце
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct History {
+    pub done: Vec<Command>,
+    pub undone: Vec<Command>,
+}
+
+impl History {
+    pub fn is_empty(&self) -> bool {
+        self.done.is_empty() && self.undone.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::operation::{FinanseDirection, OperationType};
+    use crate::store::Store;
+    use rust_decimal::dec;
+
+    fn operation() -> Operation {
+        Operation {
+            id: Uuid::new_v4(),
+            date_time: NaiveDateTime::default(),
+            account_id: Uuid::new_v4(),
+            operation_type: OperationType::Buy,
+            summary: dec!(100),
+            direction: FinanseDirection::Credit,
+            receipt_id: None,
+            reference: None,
+            counterparty: None,
+            note: None,
+            category: None,
+            transfer_id: None,
+        }
+    }
+
+    fn titles(commands: &[Command]) -> Vec<&str> {
+        commands
+            .iter()
+            .map(|command| command.title.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut store = Store::new(Database::new());
+        let added = operation();
+        let id = added.id;
+        store.upsert_operation(added.clone());
+        store.commit();
+        let mut edited = added.clone();
+        edited.summary = dec!(250);
+        store.upsert_operation(edited.clone());
+        store.commit();
+        store.remove_operation(id);
+        store.commit();
+        assert_eq!(
+            titles(&store.history.done),
+            ["Create operation", "Edit operation", "Delete operation"]
+        );
+        assert_eq!(store.operation(id), None);
+
+        assert!(store.undo());
+        assert_eq!(store.operation(id), Some(&edited));
+        assert!(store.undo());
+        assert_eq!(store.operation(id), Some(&added));
+        assert!(store.undo());
+        assert_eq!(store.operation(id), None);
+        assert!(store.operations.is_empty());
+        assert!(!store.undo());
+        // Последняя отменённая - в конце списка
+        assert_eq!(
+            titles(&store.history.undone),
+            ["Delete operation", "Edit operation", "Create operation"]
+        );
+
+        assert!(store.redo());
+        assert_eq!(store.operation(id), Some(&added));
+        assert_eq!(titles(&store.history.done), ["Create operation"]);
+        assert_eq!(store.history.undone.len(), 2);
+    }
+
+    #[test]
+    fn new_command_clears_redo() {
+        let mut store = Store::new(Database::new());
+        let first = operation();
+        store.upsert_operation(first.clone());
+        store.commit();
+        store.upsert_operation(operation());
+        store.commit();
+        assert!(store.undo());
+        assert_eq!(store.history.undone.len(), 1);
+
+        let other = operation();
+        store.upsert_operation(other.clone());
+        assert!(store.commit().is_some());
+        assert!(store.history.undone.is_empty());
+        assert!(!store.redo());
+        assert_eq!(store.operations, vec![first, other]);
+    }
+
+    #[test]
+    fn changes_grouped_in_one_command() {
+        let mut store = Store::new(Database::new());
+        let kept = operation();
+        let removed = operation();
+        store.upsert_operation(kept.clone());
+        store.upsert_operation(removed.clone());
+        assert_eq!(store.commit().unwrap().title, "Create: 2 operation");
+        // Без изменений команда не создаётся
+        assert!(store.commit().is_none());
+
+        let mut edited = kept.clone();
+        edited.note = Some("note".to_string());
+        store.upsert_operation(edited);
+        store.remove_operation(removed.id);
+        assert_eq!(store.commit().unwrap().title, "Edit/Delete: 2 operation");
+        assert!(store.undo());
+        assert_eq!(store.operations, vec![kept, removed]);
+    }
+}
@@ -0,0 +1,583 @@
+// Проверка целостности базы и исправление найденного. Файлы, исправленные
+// вручную или записанные старыми версиями, могут ссылаться на несуществующие
+// счета и чеки или содержать повторы идентификаторов. Проверка идёт по самой
+// Database, а не по индексам Store, которые повторы скрывают. Исправления не
+// теряют данных: недостающий счёт создаётся заново с выбранными типом и
+// валютой или операции переносятся на существующий, повтор получает новый id,
+// лишний чек уходит в корзину.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::account::{Account, AccountType};
+use crate::database::Database;
+use crate::operation::Operation;
+use crate::receipt::{Currency, Receipt};
+use crate::trash::{Deleted, Item};
+use crate::validation::{Violation, check_database};
+
+// Порядок объявления - порядок показа: сначала ошибки
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Severity {
+    // Данные противоречат друг другу
+    Error,
+    // Данные согласованы, но, вероятно, неверны
+    Warning,
+    // Допустимо, но стоит проверить
+    Info,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let title = match self {
+            Severity::Error => "Ошибка",
+            Severity::Warning => "Предупреждение",
+            Severity::Info => "Замечание",
+        };
+        write!(f, "{}", title)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Kind {
+    Account,
+    Operation,
+    Receipt,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Problem {
+    UnknownAccount { operation: Uuid, account: Uuid },
+    MissingReceipt { operation: Uuid, receipt: Uuid },
+    // Вторая операция перевода не найдена
+    MissingTransfer { operation: Uuid, pair: Uuid },
+    DuplicateId { kind: Kind, id: Uuid, count: usize },
+    // Чек, на который не ссылается ни одна операция
+    OrphanReceipt { receipt: Uuid },
+    // Нарушение внутренней согласованности чека
+    Receipt { receipt: Uuid, violation: Violation },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::UnknownAccount { account, .. } => {
+                write!(f, "Операция ссылается на несуществующий счёт {}", account)
+            }
+            Problem::MissingReceipt { receipt, .. } => {
+                write!(f, "Операция ссылается на несуществующий чек {}", receipt)
+            }
+            Problem::MissingTransfer { pair, .. } => {
+                write!(f, "Вторая операция перевода {} не существует", pair)
+            }
+            Problem::DuplicateId { kind, count, .. } => {
+                let kind = match kind {
+                    Kind::Account => "счетов",
+                    Kind::Operation => "операций",
+                    Kind::Receipt => "чеков",
+                };
+                write!(f, "Идентификатор повторяется у {} {}", count, kind)
+            }
+            Problem::OrphanReceipt { .. } => write!(f, "Чек не привязан к операции"),
+            Problem::Receipt { violation, .. } => write!(f, "{}", violation),
+        }
+    }
+}
+
+// Способ исправления
+#[derive(Debug, PartialEq, Clone)]
+pub enum Fix {
+    // Создать счёт с недостающим id, типом и валютой
+    CreateAccount(AccountType, Currency),
+    // Перенести операцию на существующий счёт
+    Reassign(Uuid),
+    UnlinkReceipt,
+    UnlinkTransfer,
+    // Одинаковые копии удалить, различающиеся - получают новые id
+    Deduplicate,
+    // Убрать чек в корзину
+    Trash,
+}
+
+impl Problem {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Problem::UnknownAccount { .. }
+            | Problem::MissingReceipt { .. }
+            | Problem::DuplicateId { .. } => Severity::Error,
+            Problem::MissingTransfer { .. } | Problem::Receipt { .. } => Severity::Warning,
+            Problem::OrphanReceipt { .. } => Severity::Info,
+        }
+    }
+
+    // Запись, к которой относится проблема
+    pub fn record(&self) -> Uuid {
+        match self {
+            Problem::UnknownAccount { operation, .. }
+            | Problem::MissingReceipt { operation, .. }
+            | Problem::MissingTransfer { operation, .. } => *operation,
+            Problem::DuplicateId { id, .. } => *id,
+            Problem::OrphanReceipt { receipt } | Problem::Receipt { receipt, .. } => *receipt,
+        }
+    }
+
+    // Возможные исправления; перенос на другой счёт сюда не входит, для него
+    // нужно выбрать счёт
+    pub fn fixes(&self) -> Vec<Fix> {
+        match self {
+            Problem::UnknownAccount { .. } => {
+                vec![Fix::CreateAccount(
+                    AccountType::Account,
+                    Currency::default(),
+                )]
+            }
+            Problem::MissingReceipt { .. } => vec![Fix::UnlinkReceipt],
+            Problem::MissingTransfer { .. } => vec![Fix::UnlinkTransfer],
+            Problem::DuplicateId { .. } => vec![Fix::Deduplicate],
+            Problem::OrphanReceipt { .. } => vec![Fix::Trash],
+            Problem::Receipt { .. } => Vec::new(),
+        }
+    }
+
+    // Исправление, которое применяется без вопросов. Чек без операции - обычное
+    // дело, его никто не трогает без спроса.
+    pub fn recommended(&self) -> Option<Fix> {
+        match self {
+            Problem::OrphanReceipt { .. } => None,
+            _ => self.fixes().first().cloned(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RepairError {
+    // Исправление не подходит к проблеме или проблемы уже нет
+    NotApplicable,
+    UnknownAccount(Uuid),
+}
+
+impl fmt::Display for RepairError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepairError::NotApplicable => write!(f, "Исправление не применимо"),
+            RepairError::UnknownAccount(id) => write!(f, "Счёт {} не существует", id),
+        }
+    }
+}
+
+fn duplicates<T>(list: &[T], id: fn(&T) -> Uuid, kind: Kind, problems: &mut Vec<Problem>) {
+    let mut counts: HashMap<Uuid, usize> = HashMap::new();
+    for item in list.iter() {
+        *counts.entry(id(item)).or_default() += 1;
+    }
+    // Порядок первых вхождений, чтобы результат не зависел от HashMap
+    let mut seen = HashSet::new();
+    for item in list.iter() {
+        let id = id(item);
+        let count = counts[&id];
+        if count > 1 && seen.insert(id) {
+            problems.push(Problem::DuplicateId { kind, id, count });
+        }
+    }
+}
+
+pub fn check(db: &Database) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    duplicates(&db.accounts, |a| a.id, Kind::Account, &mut problems);
+    duplicates(&db.operations, |o| o.id, Kind::Operation, &mut problems);
+    duplicates(&db.receipts, |r| r.id, Kind::Receipt, &mut problems);
+
+    let accounts: HashSet<Uuid> = db.accounts.iter().map(|a| a.id).collect();
+    let operations: HashSet<Uuid> = db.operations.iter().map(|o| o.id).collect();
+    let receipts: HashSet<Uuid> = db.receipts.iter().map(|r| r.id).collect();
+    let mut linked = HashSet::new();
+    for operation in db.operations.iter() {
+        if !accounts.contains(&operation.account_id) {
+            problems.push(Problem::UnknownAccount {
+                operation: operation.id,
+                account: operation.account_id,
+            });
+        }
+        if let Some(receipt) = operation.receipt_id {
+            linked.insert(receipt);
+            if !receipts.contains(&receipt) {
+                problems.push(Problem::MissingReceipt {
+                    operation: operation.id,
+                    receipt,
+                });
+            }
+        }
+        if let Some(pair) = operation.transfer_id
+            && !operations.contains(&pair)
+        {
+            problems.push(Problem::MissingTransfer {
+                operation: operation.id,
+                pair,
+            });
+        }
+    }
+    for receipt in db.receipts.iter() {
+        if !linked.contains(&receipt.id) {
+            problems.push(Problem::OrphanReceipt {
+                receipt: receipt.id,
+            });
+        }
+    }
+    for (receipt, violation) in check_database(db) {
+        problems.push(Problem::Receipt { receipt, violation });
+    }
+    problems.sort_by_key(|problem| problem.severity());
+    problems
+}
+
+fn operation_mut(db: &mut Database, id: Uuid) -> Result<&mut Operation, RepairError> {
+    db.operations
+        .iter_mut()
+        .find(|operation| operation.id == id)
+        .ok_or(RepairError::NotApplicable)
+}
+
+fn account_id(account: &mut Account) -> &mut Uuid {
+    &mut account.id
+}
+
+fn operation_id(operation: &mut Operation) -> &mut Uuid {
+    &mut operation.id
+}
+
+fn receipt_id(receipt: &mut Receipt) -> &mut Uuid {
+    &mut receipt.id
+}
+
+// Первая запись с id остаётся как есть, её точные копии удаляются, остальные
+// получают новые id. Ссылки других записей остаются на первую.
+fn deduplicate<T: PartialEq + Clone>(list: &mut Vec<T>, id: Uuid, id_mut: fn(&mut T) -> &mut Uuid) {
+    let mut copies: Vec<T> = Vec::new();
+    list.retain_mut(|item| {
+        if *id_mut(item) != id {
+            return true;
+        }
+        if copies.contains(item) {
+            return false;
+        }
+        copies.push(item.clone());
+        if copies.len() > 1 {
+            *id_mut(item) = Uuid::new_v4();
+        }
+        true
+    });
+}
+
+pub fn repair(db: &mut Database, problem: &Problem, fix: Fix) -> Result<(), RepairError> {
+    match (problem, fix) {
+        (Problem::UnknownAccount { account, .. }, Fix::CreateAccount(account_type, currency)) => {
+            // Счёт мог уже появиться при исправлении другой операции
+            if !db.accounts.iter().any(|a| a.id == *account) {
+                db.accounts.push(Account {
+                    id: *account,
+                    // Первые восемь цифр id
+                    name: format!("Recovered account {:08x}", account.as_fields().0),
+                    account_type,
+                    number: String::new(),
+                    bik: 0,
+                    sum: 0,
+                    cards: Vec::new(),
+                    currency,
+                });
+            }
+        }
+        (Problem::UnknownAccount { operation, account }, Fix::Reassign(target)) => {
+            if !db.accounts.iter().any(|a| a.id == target) {
+                return Err(RepairError::UnknownAccount(target));
+            }
+            let operation = operation_mut(db, *operation)?;
+            if operation.account_id != *account {
+                return Err(RepairError::NotApplicable);
+            }
+            operation.account_id = target;
+        }
+        (Problem::MissingReceipt { operation, receipt }, Fix::UnlinkReceipt) => {
+            let operation = operation_mut(db, *operation)?;
+            if operation.receipt_id != Some(*receipt) {
+                return Err(RepairError::NotApplicable);
+            }
+            operation.receipt_id = None;
+        }
+        (Problem::MissingTransfer { operation, pair }, Fix::UnlinkTransfer) => {
+            let operation = operation_mut(db, *operation)?;
+            if operation.transfer_id != Some(*pair) {
+                return Err(RepairError::NotApplicable);
+            }
+            operation.transfer_id = None;
+        }
+        (Problem::DuplicateId { kind, id, .. }, Fix::Deduplicate) => match kind {
+            Kind::Account => deduplicate(&mut db.accounts, *id, account_id),
+            Kind::Operation => deduplicate(&mut db.operations, *id, operation_id),
+            Kind::Receipt => deduplicate(&mut db.receipts, *id, receipt_id),
+        },
+        (Problem::OrphanReceipt { receipt }, Fix::Trash) => {
+            let index = db
+                .receipts
+                .iter()
+                .position(|r| r.id == *receipt)
+                .ok_or(RepairError::NotApplicable)?;
+            let mut group = Deleted::new();
+            group
+                .items
+                .push(Item::Receipt(Box::new(db.receipts.remove(index))));
+            db.trash.push(group);
+        }
+        _ => return Err(RepairError::NotApplicable),
+    }
+    Ok(())
+}
+
+// Применяет рекомендованные исправления, операции с несуществующим счётом
+// исправляются способом unknown_account; возвращает число исправленного.
+// Проверка повторяется: исправление повтора id открывает проблемы копии под
+// новым id.
+pub fn repair_all(db: &mut Database, unknown_account: &Fix) -> usize {
+    let mut count = 0;
+    loop {
+        let mut fixed = 0;
+        for problem in check(db) {
+            let fix = match problem {
+                Problem::UnknownAccount { .. } => Some(unknown_account.clone()),
+                _ => problem.recommended(),
+            };
+            if let Some(fix) = fix
+                && repair(db, &problem, fix).is_ok()
+            {
+                fixed += 1;
+            }
+        }
+        if fixed == 0 {
+            return count;
+        }
+        count += fixed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operation::{FinanseDirection, OperationType};
+    use crate::store::Store;
+    use chrono::NaiveDate;
+    use rust_decimal::dec;
+
+    fn account(name: &str) -> Account {
+        Account {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            account_type: AccountType::Account,
+            number: String::new(),
+            bik: 0,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Currency::Rub,
+        }
+    }
+
+    fn operation(account_id: Uuid) -> Operation {
+        Operation {
+            id: Uuid::new_v4(),
+            date_time: NaiveDate::from_ymd_opt(2026, 3, 1).unwrap().into(),
+            account_id,
+            operation_type: OperationType::Buy,
+            summary: dec!(100),
+            direction: FinanseDirection::Credit,
+            receipt_id: None,
+            reference: None,
+            counterparty: None,
+            note: None,
+            category: None,
+            transfer_id: None,
+        }
+    }
+
+    // Две операции несуществующего счёта и счёт для переноса
+    fn broken() -> (Database, Uuid) {
+        let missing = Uuid::parse_str("0123abcd-0000-4000-8000-000000000000").unwrap();
+        let mut db = Database::new();
+        db.accounts.push(account("Основной"));
+        db.operations.push(operation(missing));
+        db.operations.push(operation(missing));
+        (db, missing)
+    }
+
+    #[test]
+    fn problems() {
+        let (mut db, missing) = broken();
+        let mut receipt = Receipt::empty_new();
+        receipt.id = Uuid::new_v4();
+        db.receipts.push(receipt.clone());
+        db.receipts.push(receipt.clone());
+        let mut linked = operation(db.accounts[0].id);
+        linked.receipt_id = Some(Uuid::nil());
+        linked.transfer_id = Some(Uuid::nil());
+        db.operations.push(linked.clone());
+
+        let problems = check(&db);
+        assert!(problems.contains(&Problem::UnknownAccount {
+            operation: db.operations[0].id,
+            account: missing,
+        }));
+        assert!(problems.contains(&Problem::DuplicateId {
+            kind: Kind::Receipt,
+            id: receipt.id,
+            count: 2,
+        }));
+        assert!(problems.contains(&Problem::MissingReceipt {
+            operation: linked.id,
+            receipt: Uuid::nil(),
+        }));
+        assert!(problems.contains(&Problem::MissingTransfer {
+            operation: linked.id,
+            pair: Uuid::nil(),
+        }));
+        assert!(problems.contains(&Problem::OrphanReceipt {
+            receipt: receipt.id
+        }));
+        // Ошибки первыми
+        assert!(
+            problems
+                .windows(2)
+                .all(|pair| pair[0].severity() <= pair[1].severity())
+        );
+    }
+
+    #[test]
+    fn create_chosen_account() {
+        let (mut db, missing) = broken();
+        let problems = check(&db);
+        let fix = Fix::CreateAccount(AccountType::Cash, Currency::Usd);
+        for problem in problems.iter() {
+            repair(&mut db, problem, fix.clone()).unwrap();
+        }
+        // Второй раз счёт не создаётся
+        assert_eq!(db.accounts.len(), 2);
+        let recovered = &db.accounts[1];
+        assert_eq!(recovered.id, missing);
+        assert_eq!(recovered.name, "Recovered account 0123abcd");
+        assert_eq!(recovered.account_type, AccountType::Cash);
+        assert_eq!(recovered.currency, Currency::Usd);
+        assert!(check(&db).is_empty());
+    }
+
+    #[test]
+    fn reassign() {
+        let (mut db, missing) = broken();
+        let target = db.accounts[0].id;
+        let problem = check(&db).remove(0);
+        let unknown = Uuid::new_v4();
+        assert_eq!(
+            repair(&mut db, &problem, Fix::Reassign(unknown)),
+            Err(RepairError::UnknownAccount(unknown))
+        );
+        assert_eq!(
+            repair(&mut db, &problem, Fix::Deduplicate),
+            Err(RepairError::NotApplicable)
+        );
+        repair(&mut db, &problem, Fix::Reassign(target)).unwrap();
+        // Проблема уже исправлена
+        assert_eq!(
+            repair(&mut db, &problem, Fix::Reassign(target)),
+            Err(RepairError::NotApplicable)
+        );
+
+        assert_eq!(repair_all(&mut db, &Fix::Reassign(target)), 1);
+        assert!(db.operations.iter().all(|o| o.account_id == target));
+        assert!(!db.accounts.iter().any(|a| a.id == missing));
+    }
+
+    #[test]
+    fn repair_everything() {
+        let (mut db, _) = broken();
+        let copy = db.operations[0].clone();
+        let mut changed = copy.clone();
+        changed.summary = dec!(5);
+        db.operations.push(copy);
+        db.operations.push(changed);
+        let mut orphan = Receipt::empty_new();
+        orphan.id = Uuid::new_v4();
+        db.receipts.push(orphan.clone());
+
+        let fix = Fix::CreateAccount(AccountType::Account, Currency::Rub);
+        assert!(repair_all(&mut db, &fix) > 0);
+        // Точная копия удалена, отличающаяся получила новый id
+        assert_eq!(db.operations.len(), 3);
+        let ids: HashSet<Uuid> = db.operations.iter().map(|o| o.id).collect();
+        assert_eq!(ids.len(), 3);
+        // Чек без операции остаётся на месте, пока его не уберут явно
+        let problems = check(&db);
+        assert_eq!(
+            problems,
+            vec![Problem::OrphanReceipt { receipt: orphan.id }]
+        );
+        repair(&mut db, &problems[0], Fix::Trash).unwrap();
+        assert!(db.receipts.is_empty());
+        assert_eq!(db.trash.len(), 1);
+        assert!(check(&db).is_empty());
+    }
+
+    // Списки базы для сравнения до и после отмены
+    fn lists(db: &Database) -> (Vec<Account>, Vec<Operation>, Vec<Receipt>, Vec<Deleted>) {
+        (
+            db.accounts.clone(),
+            db.operations.clone(),
+            db.receipts.clone(),
+            db.trash.clone(),
+        )
+    }
+
+    #[test]
+    fn undo_repairs() {
+        let (mut db, _) = broken();
+        let target = db.accounts[0].id;
+        // Повтор [X:100, X:5]: при сравнении по id отмена теряла первую
+        let mut changed = db.operations[0].clone();
+        changed.summary = dec!(5);
+        db.operations.push(changed);
+        let mut linked = operation(target);
+        linked.receipt_id = Some(Uuid::nil());
+        linked.transfer_id = Some(Uuid::nil());
+        db.operations.push(linked);
+        let mut orphan = Receipt::empty_new();
+        orphan.id = Uuid::new_v4();
+        db.receipts.push(orphan);
+
+        let mut repairs: Vec<(Problem, Fix)> = Vec::new();
+        for problem in check(&db) {
+            for fix in problem.fixes() {
+                repairs.push((problem.clone(), fix));
+            }
+            if let Problem::UnknownAccount { .. } = problem {
+                repairs.push((problem.clone(), Fix::Reassign(target)));
+            }
+        }
+        for fix in [
+            Fix::CreateAccount(AccountType::Account, Currency::Rub),
+            Fix::Reassign(target),
+            Fix::UnlinkReceipt,
+            Fix::UnlinkTransfer,
+            Fix::Deduplicate,
+            Fix::Trash,
+        ] {
+            assert!(repairs.iter().any(|(_, f)| *f == fix), "{fix:?}");
+        }
+
+        for (problem, fix) in repairs {
+            let mut store = Store::new(db.clone());
+            store.update(|db| repair(db, &problem, fix.clone()).unwrap());
+            store.commit().unwrap();
+            let repaired = lists(&store);
+            assert_ne!(repaired, lists(&db), "{fix:?}");
+            assert!(store.undo());
+            assert_eq!(lists(&store), lists(&db), "{fix:?}");
+            assert!(store.redo());
+            assert_eq!(lists(&store), repaired, "{fix:?}");
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Модель данных личного бюджета без зависимости от GUI: счета, операции, чеки,
+//! хранение базы, импорт и экспорт, проверки и отчёты. Приложение на egui и
+//! командная строка - потребители этой библиотеки.
+
+pub mod account;
+pub mod audit;
+pub mod card;
+pub mod database;
+pub mod dedup;
+pub mod exchange;
+pub mod history;
+pub mod integrity;
+pub mod matching;
+pub mod money;
+pub mod operation;
+pub mod receipt;
+pub mod report;
+pub mod search;
+pub mod storage;
+pub mod store;
+pub mod trash;
+pub mod validation;
+pub mod view;
@@ -0,0 +1,199 @@
+use chrono::TimeDelta;
+use uuid::Uuid;
+
+use crate::card::card_account;
+use crate::database::Database;
+use crate::receipt::Slip;
+
+// Банк может провести операцию по карте через несколько дней после оплаты
+const POSTING_DAYS: i64 = 5;
+
+// Счёт карты из слипа: сначала по реестру карт, затем по номеру карточного счёта,
+// оканчивающемуся на те же четыре цифры
+pub fn slip_account(db: &Database, slip: &Slip) -> Option<Uuid> {
+    if let Some(acc_id) = card_account(&db.accounts, &slip.card) {
+        return Some(acc_id);
+    }
+    let last_four = slip.card_last_four()?;
+    db.accounts
+        .iter()
+        .filter(|account| account.account_type.has_cards())
+        .find(|account| {
+            let digits: String = account
+                .number
+                .chars()
+                .filter(|c| c.is_ascii_digit())
+                .collect();
+            digits.ends_with(&last_four)
+        })
+        .map(|account| account.id)
+}
+
+// Банковская операция, соответствующая слипу: та же сумма и направление, дата в пределах
+// срока проведения. При нескольких кандидатах предпочтение отдаётся совпадению кода
+// авторизации, затем счёта карты, затем ближайшей дате.
+pub fn slip_operation(db: &Database, slip: &Slip) -> Option<Uuid> {
+    let account = slip_account(db, slip);
+    let window = TimeDelta::days(POSTING_DAYS);
+    db.operations
+        .iter()
+        .filter(|operation| operation.summary == slip.summary)
+        .filter(|operation| operation.direction == slip.op_type.direction())
+        .filter(|operation| {
+            let delta = operation.date_time - slip.date_time;
+            delta >= -window && delta <= window
+        })
+        .min_by_key(|operation| {
+            let auth_code = !slip.auth_code.is_empty()
+                && operation.reference.as_deref() == Some(slip.auth_code.as_str());
+            let same_account = Some(operation.account_id) == account;
+            let distance = (operation.date_time - slip.date_time).abs();
+            (!auth_code, !same_account, distance)
+        })
+        .map(|operation| operation.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{Account, AccountType};
+    use crate::card::Card;
+    use crate::operation::{Operation, OperationType};
+    use crate::receipt::{CashlessOpType, Currency};
+    use chrono::{NaiveDate, NaiveDateTime};
+    use rust_decimal::{Decimal, dec};
+
+    fn date(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 3, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn account(number: &str, cards: Vec<Card>) -> Account {
+        Account {
+            id: Uuid::new_v4(),
+            name: "Card".to_string(),
+            account_type: AccountType::DebetCard,
+            number: number.to_string(),
+            bik: 0,
+            sum: 0,
+            cards,
+            currency: Currency::default(),
+        }
+    }
+
+    fn operation(account: &Account, date_time: NaiveDateTime, summary: Decimal) -> Operation {
+        Operation {
+            id: Uuid::new_v4(),
+            date_time,
+            account_id: account.id,
+            operation_type: OperationType::Buy,
+            summary,
+            direction: CashlessOpType::Payment.direction(),
+            receipt_id: None,
+            reference: None,
+            counterparty: None,
+            note: None,
+            category: None,
+            transfer_id: None,
+        }
+    }
+
+    fn slip() -> Slip {
+        Slip {
+            op_type: CashlessOpType::Payment,
+            date_time: date(10, 12),
+            summary: dec!(450.00),
+            card: "411111******1111".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn database() -> Database {
+        let mut db = Database::new();
+        let card = Card::new("4111 1111 1111 1111", None, None).unwrap();
+        db.accounts
+            .push(account("40817810000000000001", vec![card]));
+        db.accounts
+            .push(account("40817810000000002222", Vec::new()));
+        db
+    }
+
+    #[test]
+    fn account_by_card() {
+        let db = database();
+        assert_eq!(slip_account(&db, &slip()), Some(db.accounts[0].id));
+        // Карты нет в реестре - по окончанию номера счёта
+        let mut slip = slip();
+        slip.card = "****2222".to_string();
+        assert_eq!(slip_account(&db, &slip), Some(db.accounts[1].id));
+        slip.card = "****3333".to_string();
+        assert_eq!(slip_account(&db, &slip), None);
+    }
+
+    #[test]
+    fn exact_match() {
+        let mut db = database();
+        let expected = operation(&db.accounts[0], date(10, 12), dec!(450));
+        let id = expected.id;
+        db.operations.push(expected);
+        assert_eq!(slip_operation(&db, &slip()), Some(id));
+    }
+
+    #[test]
+    fn tolerances() {
+        let mut db = database();
+        let account = db.accounts[0].clone();
+        // Сумма должна совпасть до копейки, направление - тоже
+        db.operations
+            .push(operation(&account, date(10, 12), dec!(450.01)));
+        let mut refund = operation(&account, date(10, 12), dec!(450));
+        refund.direction = CashlessOpType::Return.direction();
+        db.operations.push(refund);
+        // Проведена позже срока
+        db.operations
+            .push(operation(&account, date(15, 13), dec!(450)));
+        assert_eq!(slip_operation(&db, &slip()), None);
+
+        // Ровно через пять дней - ещё в сроке
+        let posted = operation(&account, date(15, 12), dec!(450));
+        let id = posted.id;
+        db.operations.push(posted);
+        assert_eq!(slip_operation(&db, &slip()), Some(id));
+        // Проведённая раньше слипа операция тоже подходит
+        db.operations.clear();
+        let early = operation(&account, date(5, 12), dec!(450));
+        let id = early.id;
+        db.operations.push(early);
+        assert_eq!(slip_operation(&db, &slip()), Some(id));
+    }
+
+    #[test]
+    fn ambiguous_candidates() {
+        let mut db = database();
+        let card = db.accounts[0].clone();
+        let other = db.accounts[1].clone();
+
+        // При прочих равных - ближайшая по дате
+        let far = operation(&card, date(13, 12), dec!(450));
+        let near = operation(&card, date(11, 12), dec!(450));
+        let near_id = near.id;
+        db.operations.extend([far, near]);
+        assert_eq!(slip_operation(&db, &slip()), Some(near_id));
+
+        // Счёт карты важнее даты
+        db.operations
+            .push(operation(&other, date(10, 12), dec!(450)));
+        assert_eq!(slip_operation(&db, &slip()), Some(near_id));
+
+        // Код авторизации важнее счёта
+        let mut slip = slip();
+        slip.auth_code = "123456".to_string();
+        let mut authorized = operation(&other, date(14, 12), dec!(450));
+        authorized.reference = Some("123456".to_string());
+        let authorized_id = authorized.id;
+        db.operations.push(authorized);
+        assert_eq!(slip_operation(&db, &slip), Some(authorized_id));
+    }
+}
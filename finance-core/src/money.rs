@@ -0,0 +1,46 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+
+// Разбор суммы или количества в том виде, как его вводят руками или выгружают банки:
+// "1 234,56", "0,734", "-15.00"
+pub fn parse_decimal(text: &str) -> Option<Decimal> {
+    let normalized: String = text
+        .trim()
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '\u{a0}')
+        .map(|c| if c == ',' { '.' } else { c })
+        .collect();
+    if normalized.is_empty() {
+        return None;
+    }
+    Decimal::from_str_exact(&normalized).ok()
+}
+
+// Округление до копеек так же, как это делает ККТ: половина копейки округляется от нуля
+pub fn round_kopecks(value: Decimal) -> Decimal {
+    value.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::dec;
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_decimal("1 234,56"), Some(dec!(1234.56)));
+        assert_eq!(parse_decimal("1\u{a0}000"), Some(dec!(1000)));
+        assert_eq!(parse_decimal("0,734"), Some(dec!(0.734)));
+        assert_eq!(parse_decimal("-15.00"), Some(dec!(-15.00)));
+        assert_eq!(parse_decimal(""), None);
+        assert_eq!(parse_decimal("  "), None);
+        assert_eq!(parse_decimal("12,3,4"), None);
+        assert_eq!(parse_decimal("abc"), None);
+    }
+
+    #[test]
+    fn round() {
+        assert_eq!(round_kopecks(dec!(10.005)), dec!(10.01));
+        assert_eq!(round_kopecks(dec!(10.004)), dec!(10.00));
+        assert_eq!(round_kopecks(dec!(-10.005)), dec!(-10.01));
+    }
+}
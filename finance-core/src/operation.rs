@@ -1,4 +1,5 @@
 use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use strum::EnumIter;
 use uuid::Uuid;
@@ -21,15 +22,20 @@ pub enum FinanseDirection {
     Credit, //-
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Operation {
     pub id: Uuid,
     pub date_time: NaiveDateTime,
     pub account_id: Uuid,
     pub operation_type: OperationType,
-    pub summary: usize,
+    pub summary: Decimal, // В старых файлах целое число рублей, читается без изменений
     pub direction: FinanseDirection,
-    pub receipt_id: Option<Uuid>, // conduction: bool,
+    pub receipt_id: Option<Uuid>,     // conduction: bool,
+    pub reference: Option<String>,    // Код авторизации / номер банковского документа
+    pub counterparty: Option<String>, // Контрагент
+    pub note: Option<String>,         // Назначение платежа / примечание
+    pub category: Option<String>,     // Категория
+    pub transfer_id: Option<Uuid>,    // Вторая операция перевода между своими счетами
 }
 
 // impl Operation {
@@ -0,0 +1,806 @@
+use chrono::NaiveDateTime;
+use rust_decimal::{self, Decimal, dec};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use strum::{EnumIter, IntoEnumIterator};
+use uuid::Uuid;
+
+use crate::money::round_kopecks;
+use crate::operation::{FinanseDirection, OperationType};
+
+//Признак рассчёта - тип чека
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum CalculationType {
+    #[default]
+    Inbound, // Чек прихода
+    Outbound,       // Чек расхода
+    InboundReturn,  // Возврат прихода
+    OutboundReturn, // Возврат расхода
+}
+
+impl CalculationType {
+    // Направление движения денег по счёту покупателя
+    pub fn direction(&self) -> FinanseDirection {
+        match self {
+            CalculationType::Inbound => FinanseDirection::Credit,
+            CalculationType::Outbound => FinanseDirection::Debet,
+            CalculationType::InboundReturn => FinanseDirection::Debet,
+            CalculationType::OutboundReturn => FinanseDirection::Credit,
+        }
+    }
+
+    pub fn operation_type(&self) -> OperationType {
+        match self {
+            CalculationType::Inbound => OperationType::Buy,
+            CalculationType::Outbound => OperationType::Sell,
+            CalculationType::InboundReturn => OperationType::ReturnBuy,
+            CalculationType::OutboundReturn => OperationType::ReturnSell,
+        }
+    }
+}
+
+// Система налогообложения (тег 1055)
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum TaxationType {
+    #[default]
+    Osn, // ОСН
+    UsnIncome,        // УСН доход
+    UsnIncomeExpense, // УСН доход минус расход
+    Esxn,             // ЕСХН
+    Patent,           // ПСН
+}
+
+impl TaxationType {
+    pub fn code(&self) -> u8 {
+        match self {
+            TaxationType::Osn => 1,
+            TaxationType::UsnIncome => 2,
+            TaxationType::UsnIncomeExpense => 4,
+            TaxationType::Esxn => 16,
+            TaxationType::Patent => 32,
+        }
+    }
+}
+
+// Признак способа расчёта (тег 1214)
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum PaymentMethod {
+    FullPrepayment, // Предоплата 100%
+    Prepayment,     // Предоплата
+    Advance,        // Аванс
+    #[default]
+    FullPayment, // Полный расчёт
+    PartialPayment, // Частичный расчёт и кредит
+    CreditTransfer, // Передача в кредит
+    CreditPayment,  // Оплата кредита
+}
+
+impl PaymentMethod {
+    pub fn code(&self) -> u8 {
+        match self {
+            PaymentMethod::FullPrepayment => 1,
+            PaymentMethod::Prepayment => 2,
+            PaymentMethod::Advance => 3,
+            PaymentMethod::FullPayment => 4,
+            PaymentMethod::PartialPayment => 5,
+            PaymentMethod::CreditTransfer => 6,
+            PaymentMethod::CreditPayment => 7,
+        }
+    }
+}
+
+// Признак предмета расчёта (тег 1212)
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum SubjectType {
+    #[default]
+    Commodity, // Товар
+    Excise,                 // Подакцизный товар
+    Job,                    // Работа
+    Service,                // Услуга
+    GamblingBet,            // Ставка азартной игры
+    GamblingPrize,          // Выигрыш азартной игры
+    LotteryTicket,          // Лотерейный билет
+    LotteryPrize,           // Выигрыш лотереи
+    IntellectualActivity,   // Предоставление РИД
+    Payment,                // Платёж
+    AgentCommission,        // Агентское вознаграждение
+    PayoutComposite,        // Выплата
+    Other,                  // Иной предмет расчёта
+    PropertyRight,          // Имущественное право
+    NonOperatingIncome,     // Внереализационный доход
+    InsuranceContributions, // Иные платежи и взносы
+    TradeFee,               // Торговый сбор
+    ResortFee,              // Курортный сбор
+    Pledge,                 // Залог
+    Expense,                // Расход
+    PensionInsuranceIp,     // Взносы на ОПС ИП
+    PensionInsurance,       // Взносы на ОПС
+    MedicalInsuranceIp,     // Взносы на ОМС ИП
+    MedicalInsurance,       // Взносы на ОМС
+    SocialInsurance,        // Взносы на ОСС
+    CasinoPayment,          // Платёж казино
+    IssuanceOfFunds,        // Выдача денежных средств
+    ExciseUnmarked,         // Подакцизный товар без кода маркировки
+    ExciseMarked,           // Подакцизный товар с кодом маркировки
+    Unmarked,               // Товар без кода маркировки
+    Marked,                 // Товар с кодом маркировки
+}
+
+impl SubjectType {
+    pub fn code(&self) -> u8 {
+        match self {
+            SubjectType::Commodity => 1,
+            SubjectType::Excise => 2,
+            SubjectType::Job => 3,
+            SubjectType::Service => 4,
+            SubjectType::GamblingBet => 5,
+            SubjectType::GamblingPrize => 6,
+            SubjectType::LotteryTicket => 7,
+            SubjectType::LotteryPrize => 8,
+            SubjectType::IntellectualActivity => 9,
+            SubjectType::Payment => 10,
+            SubjectType::AgentCommission => 11,
+            SubjectType::PayoutComposite => 12,
+            SubjectType::Other => 13,
+            SubjectType::PropertyRight => 14,
+            SubjectType::NonOperatingIncome => 15,
+            SubjectType::InsuranceContributions => 16,
+            SubjectType::TradeFee => 17,
+            SubjectType::ResortFee => 18,
+            SubjectType::Pledge => 19,
+            SubjectType::Expense => 20,
+            SubjectType::PensionInsuranceIp => 21,
+            SubjectType::PensionInsurance => 22,
+            SubjectType::MedicalInsuranceIp => 23,
+            SubjectType::MedicalInsurance => 24,
+            SubjectType::SocialInsurance => 25,
+            SubjectType::CasinoPayment => 26,
+            SubjectType::IssuanceOfFunds => 27,
+            SubjectType::ExciseUnmarked => 30,
+            SubjectType::ExciseMarked => 31,
+            SubjectType::Unmarked => 32,
+            SubjectType::Marked => 33,
+        }
+    }
+}
+
+// Ставка НДС (тег 1199)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum VatType {
+    Vat20,     // НДС 20%
+    Vat10,     // НДС 10%
+    Vat7,      // НДС 7% (УСН)
+    Vat5,      // НДС 5% (УСН)
+    Vat0,      // НДС 0%
+    Vat20_120, // НДС 20/120
+    Vat10_110, // НДС 10/110
+    Vat7_107,  // НДС 7/107
+    Vat5_105,  // НДС 5/105
+    NoVat,     // Без НДС
+}
+
+impl VatType {
+    pub fn code(&self) -> u8 {
+        match self {
+            VatType::Vat20 => 1,
+            VatType::Vat10 => 2,
+            VatType::Vat20_120 => 3,
+            VatType::Vat10_110 => 4,
+            VatType::Vat0 => 5,
+            VatType::NoVat => 6,
+            VatType::Vat5 => 7,
+            VatType::Vat7 => 8,
+            VatType::Vat5_105 => 9,
+            VatType::Vat7_107 => 10,
+        }
+    }
+
+    // Ставка в процентах, для "без НДС" ставки нет
+    pub fn rate(&self) -> Option<Decimal> {
+        match self {
+            VatType::Vat20 | VatType::Vat20_120 => Some(dec!(20)),
+            VatType::Vat10 | VatType::Vat10_110 => Some(dec!(10)),
+            VatType::Vat7 | VatType::Vat7_107 => Some(dec!(7)),
+            VatType::Vat5 | VatType::Vat5_105 => Some(dec!(5)),
+            VatType::Vat0 => Some(dec!(0)),
+            VatType::NoVat => None,
+        }
+    }
+
+    // НДС, входящий в сумму: сумма × ставка / (100 + ставка).
+    // В розничном чеке цена всегда включает налог, поэтому для 20% и 20/120
+    // расчёт одинаковый, различаются они только признаком в ФД.
+    pub fn compute(&self, summary: Decimal) -> Decimal {
+        match self.rate() {
+            Some(rate) => round_kopecks(summary * rate / (dec!(100) + rate)),
+            None => Decimal::ZERO,
+        }
+    }
+}
+
+// Мера количества предмета расчёта (тег 2108)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum UnitType {
+    Pieces, // Штука
+    Gramm,  // Грамм
+    #[serde(alias = "Kilogamm")]
+    Kilogramm, // Килограмм
+    Tonne,  // Тонна
+    Centimetre, // Сантиметр
+    Decimetre, // Дециметр
+    Metre,  // Метр
+    SquareCentimetre, // Квадратный сантиметр
+    SquareDecimetre, // Квадратный дециметр
+    SquareMetre, // Квадратный метр
+    Millilitre, // Миллилитр
+    Litre,  // Литр
+    CubicMetre, // Кубический метр
+    KilowattHour, // Киловатт час
+    Gigacalorie, // Гигакалория
+    Day,    // Сутки (день)
+    Hour,   // Час
+    Minute, // Минута
+    Second, // Секунда
+    Kilobyte, // Килобайт
+    Megabyte, // Мегабайт
+    Gigabyte, // Гигабайт
+    Terabyte, // Терабайт
+    Other,  // Иные единицы измерения
+}
+
+// Физическая величина, в которой измеряется количество
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Dimension {
+    Count,
+    Mass,
+    Length,
+    Area,
+    Volume,
+    Energy,
+    Time,
+    Data,
+    Other,
+}
+
+impl UnitType {
+    pub fn code(&self) -> u8 {
+        match self {
+            UnitType::Pieces => 0,
+            UnitType::Gramm => 10,
+            UnitType::Kilogramm => 11,
+            UnitType::Tonne => 12,
+            UnitType::Centimetre => 20,
+            UnitType::Decimetre => 21,
+            UnitType::Metre => 22,
+            UnitType::SquareCentimetre => 30,
+            UnitType::SquareDecimetre => 31,
+            UnitType::SquareMetre => 32,
+            UnitType::Millilitre => 40,
+            UnitType::Litre => 41,
+            UnitType::CubicMetre => 42,
+            UnitType::KilowattHour => 50,
+            UnitType::Gigacalorie => 51,
+            UnitType::Day => 70,
+            UnitType::Hour => 71,
+            UnitType::Minute => 72,
+            UnitType::Second => 73,
+            UnitType::Kilobyte => 80,
+            UnitType::Megabyte => 81,
+            UnitType::Gigabyte => 82,
+            UnitType::Terabyte => 83,
+            UnitType::Other => 255,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        UnitType::iter().find(|unit| unit.code() == code)
+    }
+
+    pub fn name_ru(&self) -> &'static str {
+        match self {
+            UnitType::Pieces => "шт.",
+            UnitType::Gramm => "г",
+            UnitType::Kilogramm => "кг",
+            UnitType::Tonne => "т",
+            UnitType::Centimetre => "см",
+            UnitType::Decimetre => "дм",
+            UnitType::Metre => "м",
+            UnitType::SquareCentimetre => "кв. см",
+            UnitType::SquareDecimetre => "кв. дм",
+            UnitType::SquareMetre => "кв. м",
+            UnitType::Millilitre => "мл",
+            UnitType::Litre => "л",
+            UnitType::CubicMetre => "куб. м",
+            UnitType::KilowattHour => "кВт·ч",
+            UnitType::Gigacalorie => "Гкал",
+            UnitType::Day => "сутки",
+            UnitType::Hour => "час",
+            UnitType::Minute => "мин",
+            UnitType::Second => "с",
+            UnitType::Kilobyte => "Кбайт",
+            UnitType::Megabyte => "Мбайт",
+            UnitType::Gigabyte => "Гбайт",
+            UnitType::Terabyte => "Тбайт",
+            UnitType::Other => "иная",
+        }
+    }
+
+    pub fn name_en(&self) -> &'static str {
+        match self {
+            UnitType::Pieces => "pcs",
+            UnitType::Gramm => "g",
+            UnitType::Kilogramm => "kg",
+            UnitType::Tonne => "t",
+            UnitType::Centimetre => "cm",
+            UnitType::Decimetre => "dm",
+            UnitType::Metre => "m",
+            UnitType::SquareCentimetre => "cm²",
+            UnitType::SquareDecimetre => "dm²",
+            UnitType::SquareMetre => "m²",
+            UnitType::Millilitre => "ml",
+            UnitType::Litre => "l",
+            UnitType::CubicMetre => "m³",
+            UnitType::KilowattHour => "kWh",
+            UnitType::Gigacalorie => "Gcal",
+            UnitType::Day => "day",
+            UnitType::Hour => "h",
+            UnitType::Minute => "min",
+            UnitType::Second => "s",
+            UnitType::Kilobyte => "KB",
+            UnitType::Megabyte => "MB",
+            UnitType::Gigabyte => "GB",
+            UnitType::Terabyte => "TB",
+            UnitType::Other => "other",
+        }
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        match self {
+            UnitType::Pieces => Dimension::Count,
+            UnitType::Gramm | UnitType::Kilogramm | UnitType::Tonne => Dimension::Mass,
+            UnitType::Centimetre | UnitType::Decimetre | UnitType::Metre => Dimension::Length,
+            UnitType::SquareCentimetre | UnitType::SquareDecimetre | UnitType::SquareMetre => {
+                Dimension::Area
+            }
+            UnitType::Millilitre | UnitType::Litre | UnitType::CubicMetre => Dimension::Volume,
+            UnitType::KilowattHour | UnitType::Gigacalorie => Dimension::Energy,
+            UnitType::Day | UnitType::Hour | UnitType::Minute | UnitType::Second => Dimension::Time,
+            UnitType::Kilobyte | UnitType::Megabyte | UnitType::Gigabyte | UnitType::Terabyte => {
+                Dimension::Data
+            }
+            UnitType::Other => Dimension::Other,
+        }
+    }
+
+    // Сколько базовых единиц величины (г, см, кв. см, мл, кВт·ч, с, Кбайт) в одной единице
+    fn base_factor(&self) -> Decimal {
+        match self {
+            UnitType::Pieces => dec!(1),
+            UnitType::Gramm => dec!(1),
+            UnitType::Kilogramm => dec!(1000),
+            UnitType::Tonne => dec!(1000000),
+            UnitType::Centimetre => dec!(1),
+            UnitType::Decimetre => dec!(10),
+            UnitType::Metre => dec!(100),
+            UnitType::SquareCentimetre => dec!(1),
+            UnitType::SquareDecimetre => dec!(100),
+            UnitType::SquareMetre => dec!(10000),
+            UnitType::Millilitre => dec!(1),
+            UnitType::Litre => dec!(1000),
+            UnitType::CubicMetre => dec!(1000000),
+            UnitType::KilowattHour => dec!(1),
+            UnitType::Gigacalorie => dec!(1163),
+            UnitType::Day => dec!(86400),
+            UnitType::Hour => dec!(3600),
+            UnitType::Minute => dec!(60),
+            UnitType::Second => dec!(1),
+            UnitType::Kilobyte => dec!(1),
+            UnitType::Megabyte => dec!(1024),
+            UnitType::Gigabyte => dec!(1048576),
+            UnitType::Terabyte => dec!(1073741824),
+            UnitType::Other => dec!(1),
+        }
+    }
+
+    // Сколько знаков после запятой имеет смысл хранить в количестве
+    pub fn precision(&self) -> u32 {
+        match self {
+            UnitType::Pieces
+            | UnitType::Gramm
+            | UnitType::Centimetre
+            | UnitType::SquareCentimetre
+            | UnitType::Millilitre
+            | UnitType::Second
+            | UnitType::Kilobyte => 0,
+            UnitType::Day | UnitType::Hour | UnitType::Minute => 2,
+            UnitType::Gigacalorie => 4,
+            _ => 3,
+        }
+    }
+
+    pub fn is_compatible(&self, other: &UnitType) -> bool {
+        self == other
+            || (self.dimension() != Dimension::Other && self.dimension() == other.dimension())
+    }
+
+    // Перевод количества в другую единицу той же величины
+    pub fn convert(&self, quantity: Decimal, to: &UnitType) -> Option<Decimal> {
+        if !self.is_compatible(to) {
+            return None;
+        }
+        Some(quantity * self.base_factor() / to.base_factor())
+    }
+}
+
+impl fmt::Display for UnitType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name_ru())
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum CashlessOpType {
+    #[default]
+    Payment,
+    Cansel,
+    Return,
+}
+
+impl CashlessOpType {
+    // Направление движения денег по карте
+    pub fn direction(&self) -> FinanseDirection {
+        match self {
+            CashlessOpType::Payment => FinanseDirection::Credit,
+            CashlessOpType::Cansel | CashlessOpType::Return => FinanseDirection::Debet,
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum Currency {
+    #[default]
+    Rub,
+    Usd,
+}
+
+impl Currency {
+    // Код валюты ISO 4217
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::Rub => "RUB",
+            Currency::Usd => "USD",
+        }
+    }
+}
+// Предмет рассчёта
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Subject {
+    pub name: String,        // Найменование
+    pub unit_type: UnitType, // Тип количества
+    pub count: Decimal,      // Количество
+    pub price: Decimal,      // Цена
+    pub summary: Decimal,    // Сумма
+    pub vat_type: VatType,   // Тип НДС
+    pub vat: Decimal,        // НДС
+    #[serde(default)]
+    pub payment_method: PaymentMethod, // Признак способа расчёта
+    #[serde(default)]
+    pub subject_type: SubjectType, // Признак предмета расчёта
+    pub marking_code: Option<String>, // Код маркировки
+}
+
+impl Subject {
+    // Количество в указанной единице, если единицы совместимы
+    pub fn count_in(&self, unit: &UnitType) -> Option<Decimal> {
+        self.unit_type.convert(self.count, unit)
+    }
+
+    // Установка количества с округлением до точности единицы измерения
+    pub fn set_count(&mut self, count: Decimal) {
+        self.count = count.round_dp(self.unit_type.precision());
+    }
+
+    // Стоимость позиции, как её считает касса: цена × количество, округлённая до копеек
+    pub fn computed_summary(&self) -> Decimal {
+        round_kopecks(self.price * self.count)
+    }
+
+    pub fn summary_matches(&self) -> bool {
+        self.summary == self.computed_summary()
+    }
+
+    pub fn computed_vat(&self) -> Decimal {
+        self.vat_type.compute(self.summary)
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            name: "".to_string(),
+            unit_type: UnitType::Pieces,
+            count: Decimal::ZERO,
+            price: Decimal::new(0, 2),
+            summary: Decimal::new(0, 2),
+            vat_type: VatType::Vat0,
+            vat: Decimal::new(0, 2),
+            payment_method: PaymentMethod::FullPayment,
+            subject_type: SubjectType::Commodity,
+            marking_code: None,
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Slip {
+    pub id: usize,                     //Номер терминала
+    pub op_type: CashlessOpType,       // Тип операции
+    pub date_time: NaiveDateTime,      // Дата - время
+    pub summary: Decimal,              // Сумма
+    pub currency: Currency,            // Валюта
+    pub comm_summary: Option<Decimal>, // Сумма комиссионного вознаграждения
+    pub auth_code: String,             // Код авторизации
+    #[serde(serialize_with = "crate::card::serialize_masked")]
+    pub card: String, // Номер карты
+    pub address: Option<String>,       // Адрес
+    pub place: Option<String>,         // Наименование магазина
+    pub payment_system: Option<String>, // Платёжная система
+    pub doc_id: Option<usize>,         // Номер документа
+}
+
+impl Slip {
+    // Последние четыре цифры карты, напечатанные в слипе
+    pub fn card_last_four(&self) -> Option<String> {
+        let digits: Vec<char> = self.card.chars().filter(|c| c.is_ascii_digit()).collect();
+        if digits.len() < 4 {
+            return None;
+        }
+        Some(digits[digits.len() - 4..].iter().collect())
+    }
+
+    pub fn masked_card(&self) -> String {
+        match self.card_last_four() {
+            Some(last_four) => format!("•••• {}", last_four),
+            None => "••••".to_string(),
+        }
+    }
+}
+
+// Итог чека по одной ставке НДС (теги 1102 - 1107)
+#[derive(Debug, PartialEq, Clone)]
+pub struct VatTotal {
+    pub vat_type: VatType,
+    pub summary: Decimal,
+    pub vat: Decimal,
+}
+
+// Суммы по ставкам НДС, как их печатает касса: налог считается
+// от итога по ставке, а не складывается из налогов по позициям
+pub fn vat_totals(subjects: &[Subject]) -> Vec<VatTotal> {
+    VatType::iter()
+        .filter_map(|vat_type| {
+            let mut present = false;
+            let mut summary = Decimal::ZERO;
+            for subject in subjects.iter().filter(|s| s.vat_type == vat_type) {
+                present = true;
+                summary += subject.summary;
+            }
+            present.then(|| VatTotal {
+                vat_type,
+                summary,
+                vat: vat_type.compute(summary),
+            })
+        })
+        .collect()
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Receipt {
+    /// Идентификатор
+    pub id: Uuid,
+    /// Дата время
+    pub date_time: NaiveDateTime,
+    /// Признак рассчёта - тип чека
+    pub calculation_type: CalculationType,
+    /// Адрес
+    pub address: Option<String>,
+    /// Место - Название учреждения
+    pub place: Option<String>,
+    /// Предмет рассчёта - позиции в документе
+    pub subjects: Vec<Subject>,
+    /// Сумма
+    pub summary: Decimal,
+    /// Нал
+    pub cash: Option<Decimal>,
+    /// Безнал
+    pub cashless: Option<Decimal>,
+    /// Аванс
+    pub prepayment: Option<Decimal>,
+    /// Кредит
+    pub postpayment: Option<Decimal>,
+    /// За счёт з/п
+    pub in_kind: Option<Decimal>,
+    /// Сумма НДС
+    pub vat: Option<Decimal>,
+    /// Ссылка на чек
+    pub url: Option<String>,
+    /// Слип-чек
+    pub slip: Option<Slip>,
+    /// Заводской номер ФН
+    pub fn_number: Option<String>,
+    /// Номер ФД
+    pub fd_number: Option<u32>,
+    /// Фискальный признак документа (ФПД)
+    pub fiscal_sign: Option<u32>,
+    /// Номер смены
+    pub shift_number: Option<u32>,
+    /// ИНН продавца
+    pub seller_inn: Option<String>,
+    /// Система налогообложения
+    #[serde(default)]
+    pub taxation_type: TaxationType,
+    /// Кассир
+    pub cashier: Option<String>,
+}
+
+impl Receipt {
+    pub fn empty_new() -> Self {
+        Self {
+            ..Default::default()
+        }
+    }
+
+    pub fn vat_totals(&self) -> Vec<VatTotal> {
+        vat_totals(&self.subjects)
+    }
+
+    pub fn computed_vat(&self) -> Decimal {
+        self.vat_totals().iter().map(|total| total.vat).sum()
+    }
+
+    // Введённая сумма НДС совпадает с рассчитанной по позициям
+    pub fn vat_matches(&self) -> bool {
+        self.vat.unwrap_or(Decimal::ZERO) == self.computed_vat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn codes<T: IntoEnumIterator>(code: fn(&T) -> u8) -> Vec<u8> {
+        T::iter().map(|value| code(&value)).collect()
+    }
+
+    fn unique(codes: &[u8]) -> bool {
+        codes.iter().collect::<HashSet<_>>().len() == codes.len()
+    }
+
+    #[test]
+    fn ffd_codes() {
+        assert_eq!(codes(TaxationType::code), vec![1, 2, 4, 16, 32]);
+        assert_eq!(codes(PaymentMethod::code), vec![1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(PaymentMethod::default().code(), 4);
+        assert_eq!(SubjectType::default().code(), 1);
+        assert_eq!(SubjectType::Marked.code(), 33);
+        assert_eq!(SubjectType::ExciseUnmarked.code(), 30);
+        assert_eq!(VatType::Vat20_120.code(), 3);
+        assert_eq!(VatType::NoVat.code(), 6);
+        assert_eq!(VatType::Vat7_107.code(), 10);
+        assert!(unique(&codes(SubjectType::code)));
+        assert!(unique(&codes(VatType::code)));
+        // Тегов 28 и 29 в справочнике нет
+        assert!(
+            !codes(SubjectType::code)
+                .iter()
+                .any(|c| *c == 28 || *c == 29)
+        );
+    }
+
+    fn subject(unit_type: UnitType, count: Decimal, price: Decimal) -> Subject {
+        let mut subject = Subject::empty();
+        subject.unit_type = unit_type;
+        subject.price = price;
+        subject.set_count(count);
+        subject.summary = subject.computed_summary();
+        subject
+    }
+
+    #[test]
+    fn fractional_quantity() {
+        let apples = subject(UnitType::Kilogramm, dec!(0.7346), dec!(129.90));
+        assert_eq!(apples.count, dec!(0.735));
+        assert_eq!(apples.summary, dec!(95.48));
+        assert!(apples.summary_matches());
+        assert_eq!(apples.count_in(&UnitType::Gramm), Some(dec!(735)));
+        assert_eq!(apples.count_in(&UnitType::Litre), None);
+
+        let bread = subject(UnitType::Pieces, dec!(2.4), dec!(50));
+        assert_eq!(bread.count, dec!(2));
+        assert_eq!(bread.summary, dec!(100));
+
+        let mut wrong = apples.clone();
+        wrong.summary = dec!(95.47);
+        assert!(!wrong.summary_matches());
+    }
+
+    #[test]
+    fn integer_count_in_old_files() {
+        let json = r#"{"name":"Молоко","unit_type":"Pieces","count":3,"price":"89.90",
+            "summary":"269.70","vat_type":"Vat10","vat":"24.52","marking_code":null}"#;
+        let subject: Subject = serde_json::from_str(json).unwrap();
+        assert_eq!(subject.count, dec!(3));
+        assert!(subject.summary_matches());
+    }
+
+    #[test]
+    fn vat_each_rate() {
+        let expected = [
+            (VatType::Vat20, dec!(166.67)),
+            (VatType::Vat10, dec!(90.91)),
+            (VatType::Vat7, dec!(65.42)),
+            (VatType::Vat5, dec!(47.62)),
+            (VatType::Vat0, dec!(0)),
+            (VatType::Vat20_120, dec!(166.67)),
+            (VatType::Vat10_110, dec!(90.91)),
+            (VatType::Vat7_107, dec!(65.42)),
+            (VatType::Vat5_105, dec!(47.62)),
+            (VatType::NoVat, dec!(0)),
+        ];
+        assert_eq!(expected.len(), VatType::iter().count());
+        for (vat_type, vat) in expected {
+            assert_eq!(vat_type.compute(dec!(1000)), vat, "{:?}", vat_type);
+        }
+        assert_eq!(VatType::NoVat.rate(), None);
+    }
+
+    #[test]
+    fn vat_totals_per_rate() {
+        let mut receipt = Receipt::empty_new();
+        for _ in 0..3 {
+            let mut item = subject(UnitType::Pieces, dec!(1), dec!(0.10));
+            item.vat_type = VatType::Vat20;
+            receipt.subjects.push(item);
+        }
+        let mut item = subject(UnitType::Pieces, dec!(1), dec!(110));
+        item.vat_type = VatType::Vat10;
+        receipt.subjects.push(item);
+
+        // Налог по ставке считается от итога: 0,30 × 20 / 120 = 0,05, а не 3 × 0,02
+        assert_eq!(
+            receipt.vat_totals(),
+            vec![
+                VatTotal {
+                    vat_type: VatType::Vat20,
+                    summary: dec!(0.30),
+                    vat: dec!(0.05),
+                },
+                VatTotal {
+                    vat_type: VatType::Vat10,
+                    summary: dec!(110),
+                    vat: dec!(10),
+                },
+            ]
+        );
+        assert_eq!(receipt.computed_vat(), dec!(10.05));
+        assert!(!receipt.vat_matches());
+        receipt.vat = Some(dec!(10.05));
+        assert!(receipt.vat_matches());
+    }
+
+    #[test]
+    fn fiscal_attributes_default_in_old_files() {
+        let json = r#"{"name":"Хлеб","unit_type":"Pieces","count":"1","price":"50.00",
+            "summary":"50.00","vat_type":"Vat10","vat":"4.55","marking_code":null}"#;
+        let subject: Subject = serde_json::from_str(json).unwrap();
+        assert_eq!(subject.payment_method, PaymentMethod::FullPayment);
+        assert_eq!(subject.subject_type, SubjectType::Commodity);
+
+        let mut receipt = Receipt::empty_new();
+        receipt.fn_number = Some("9999078900004792".to_string());
+        receipt.fd_number = Some(12345);
+        receipt.taxation_type = TaxationType::UsnIncome;
+        let json = serde_json::to_string(&receipt).unwrap();
+        assert_eq!(serde_json::from_str::<Receipt>(&json).unwrap(), receipt);
+    }
+}
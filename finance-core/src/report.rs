@@ -0,0 +1,446 @@
+// Табличные представления базы и отчёты для выгрузки в CSV и XLSX
+
+use chrono::{Datelike, NaiveDateTime};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::operation::{FinanseDirection, Operation, OperationType};
+use crate::receipt::Receipt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Cell {
+    Empty,
+    Text(String),
+    Number(Decimal),
+    Integer(i64),
+    DateTime(NaiveDateTime),
+}
+
+impl Cell {
+    fn text(value: &Option<String>) -> Self {
+        match value {
+            Some(text) => Cell::Text(text.clone()),
+            None => Cell::Empty,
+        }
+    }
+
+    fn number(value: Option<Decimal>) -> Self {
+        match value {
+            Some(number) => Cell::Number(number),
+            None => Cell::Empty,
+        }
+    }
+}
+
+// Таблица: один лист XLSX или один файл CSV
+#[derive(Debug, Clone)]
+pub struct Sheet {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Sheet {
+    pub fn new(name: &str, columns: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+}
+
+fn account_name(db: &Database, id: Uuid) -> Cell {
+    db.accounts
+        .iter()
+        .find(|account| account.id == id)
+        .map(|account| Cell::Text(account.name.clone()))
+        .unwrap_or(Cell::Empty)
+}
+
+// Остаток счёта по всем его операциям
+pub fn balance(db: &Database, account_id: Uuid) -> Decimal {
+    db.operations
+        .iter()
+        .filter(|operation| operation.account_id == account_id)
+        .map(|operation| match operation.direction {
+            FinanseDirection::Debet => operation.summary,
+            FinanseDirection::Credit => -operation.summary,
+        })
+        .sum()
+}
+
+pub fn accounts(db: &Database) -> Sheet {
+    let mut sheet = Sheet::new(
+        "Accounts",
+        &["Id", "Name", "Type", "Number", "Currency", "Balance"],
+    );
+    for account in db.accounts.iter() {
+        sheet.rows.push(vec![
+            Cell::Text(account.id.to_string()),
+            Cell::Text(account.name.clone()),
+            Cell::Text(format!("{:?}", account.account_type)),
+            Cell::Text(account.number.clone()),
+            Cell::Text(account.currency.code().to_string()),
+            Cell::Number(balance(db, account.id)),
+        ]);
+    }
+    sheet
+}
+
+// Чеки по дате; номер в этом порядке связывает лист чеков с листом позиций
+fn sorted_receipts(db: &Database) -> Vec<&Receipt> {
+    let mut receipts: Vec<&Receipt> = db.receipts.iter().collect();
+    receipts.sort_by_key(|receipt| (receipt.date_time, receipt.id));
+    receipts
+}
+
+pub fn operations(db: &Database, operations: &[&Operation]) -> Sheet {
+    let mut sheet = Sheet::new(
+        "Operations",
+        &[
+            "Date",
+            "Account",
+            "Type",
+            "Amount",
+            "Currency",
+            "Category",
+            "Counterparty",
+            "Reference",
+            "Note",
+            "Receipt",
+            "Transfer account",
+        ],
+    );
+    let receipts = sorted_receipts(db);
+    for operation in operations.iter() {
+        let currency = db
+            .accounts
+            .iter()
+            .find(|account| account.id == operation.account_id)
+            .map(|account| Cell::Text(account.currency.code().to_string()))
+            .unwrap_or(Cell::Empty);
+        let amount = match operation.direction {
+            FinanseDirection::Debet => operation.summary,
+            FinanseDirection::Credit => -operation.summary,
+        };
+        let receipt = operation
+            .receipt_id
+            .and_then(|id| receipts.iter().position(|receipt| receipt.id == id))
+            .map(|index| Cell::Integer(index as i64 + 1))
+            .unwrap_or(Cell::Empty);
+        let transfer = operation
+            .transfer_id
+            .and_then(|id| db.operations.iter().find(|other| other.id == id))
+            .map(|other| account_name(db, other.account_id))
+            .unwrap_or(Cell::Empty);
+        sheet.rows.push(vec![
+            Cell::DateTime(operation.date_time),
+            account_name(db, operation.account_id),
+            Cell::Text(format!("{:?}", operation.operation_type)),
+            Cell::Number(amount),
+            currency,
+            Cell::text(&operation.category),
+            Cell::text(&operation.counterparty),
+            Cell::text(&operation.reference),
+            Cell::text(&operation.note),
+            receipt,
+            transfer,
+        ]);
+    }
+    sheet
+}
+
+// Все операции базы по дате
+pub fn all_operations(db: &Database) -> Sheet {
+    let mut list: Vec<&Operation> = db.operations.iter().collect();
+    list.sort_by_key(|operation| (operation.date_time, operation.id));
+    operations(db, &list)
+}
+
+pub fn receipts(db: &Database) -> Sheet {
+    let mut sheet = Sheet::new(
+        "Receipts",
+        &[
+            "No",
+            "Date",
+            "Type",
+            "Place",
+            "Address",
+            "Account",
+            "Summary",
+            "Cash",
+            "Cashless",
+            "VAT",
+            "Seller INN",
+            "FN",
+            "FD",
+            "FP",
+        ],
+    );
+    for (index, receipt) in sorted_receipts(db).iter().enumerate() {
+        let account = db
+            .operations
+            .iter()
+            .find(|operation| operation.receipt_id == Some(receipt.id))
+            .map(|operation| account_name(db, operation.account_id))
+            .unwrap_or(Cell::Empty);
+        sheet.rows.push(vec![
+            Cell::Integer(index as i64 + 1),
+            Cell::DateTime(receipt.date_time),
+            Cell::Text(format!("{:?}", receipt.calculation_type)),
+            Cell::text(&receipt.place),
+            Cell::text(&receipt.address),
+            account,
+            Cell::Number(receipt.summary),
+            Cell::number(receipt.cash),
+            Cell::number(receipt.cashless),
+            Cell::number(receipt.vat),
+            Cell::text(&receipt.seller_inn),
+            Cell::text(&receipt.fn_number),
+            receipt
+                .fd_number
+                .map(|value| Cell::Integer(value.into()))
+                .unwrap_or(Cell::Empty),
+            receipt
+                .fiscal_sign
+                .map(|value| Cell::Integer(value.into()))
+                .unwrap_or(Cell::Empty),
+        ]);
+    }
+    sheet
+}
+
+// Позиции всех чеков, по строке на предмет расчёта
+pub fn subjects(db: &Database) -> Sheet {
+    let mut sheet = Sheet::new(
+        "Subjects",
+        &[
+            "Receipt", "Date", "Place", "Name", "Count", "Unit", "Price", "Summary", "VAT rate",
+            "VAT",
+        ],
+    );
+    for (index, receipt) in sorted_receipts(db).iter().enumerate() {
+        for subject in receipt.subjects.iter() {
+            sheet.rows.push(vec![
+                Cell::Integer(index as i64 + 1),
+                Cell::DateTime(receipt.date_time),
+                Cell::text(&receipt.place),
+                Cell::Text(subject.name.clone()),
+                Cell::Number(subject.count),
+                Cell::Text(subject.unit_type.to_string()),
+                Cell::Number(subject.price),
+                Cell::Number(subject.summary),
+                Cell::Text(format!("{:?}", subject.vat_type)),
+                Cell::Number(subject.vat),
+            ]);
+        }
+    }
+    sheet
+}
+
+// Обороты по счетам за каждый месяц года. Переводы между своими счетами
+// показаны отдельно и не входят в доходы и расходы; начальный остаток - не
+// доход и в обороты не входит.
+pub fn monthly(db: &Database, year: i32) -> Sheet {
+    let mut sheet = Sheet::new(
+        &format!("Monthly {}", year),
+        &[
+            "Month",
+            "Account",
+            "Currency",
+            "Income",
+            "Expense",
+            "Transfers",
+            "Net",
+        ],
+    );
+    // (месяц, имя счёта, id) -> (доход, расход, переводы)
+    let mut totals: BTreeMap<(u32, String, Uuid), (Decimal, Decimal, Decimal)> = BTreeMap::new();
+    for operation in db.operations.iter() {
+        if operation.date_time.year() != year || operation.operation_type == OperationType::Initial
+        {
+            continue;
+        }
+        let Some(account) = db
+            .accounts
+            .iter()
+            .find(|account| account.id == operation.account_id)
+        else {
+            continue;
+        };
+        let entry = totals
+            .entry((
+                operation.date_time.month(),
+                account.name.clone(),
+                account.id,
+            ))
+            .or_default();
+        match (&operation.direction, operation.transfer_id.is_some()) {
+            (FinanseDirection::Debet, true) => entry.2 += operation.summary,
+            (FinanseDirection::Credit, true) => entry.2 -= operation.summary,
+            (FinanseDirection::Debet, false) => entry.0 += operation.summary,
+            (FinanseDirection::Credit, false) => entry.1 += operation.summary,
+        }
+    }
+    for ((month, name, id), (income, expense, transfers)) in totals {
+        let currency = db
+            .accounts
+            .iter()
+            .find(|account| account.id == id)
+            .map(|account| account.currency.code())
+            .unwrap_or_default();
+        sheet.rows.push(vec![
+            Cell::Text(format!("{}-{:02}", year, month)),
+            Cell::Text(name),
+            Cell::Text(currency.to_string()),
+            Cell::Number(income),
+            Cell::Number(expense),
+            Cell::Number(transfers),
+            Cell::Number(income - expense + transfers),
+        ]);
+    }
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{Account, AccountType};
+    use chrono::NaiveDate;
+    use rust_decimal::dec;
+
+    fn account(name: &str) -> Account {
+        Account {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            account_type: AccountType::Account,
+            number: String::new(),
+            bik: 0,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Default::default(),
+        }
+    }
+
+    fn operation(account: &Account, year: i32, month: u32, amount: Decimal) -> Operation {
+        Operation {
+            id: Uuid::new_v4(),
+            date_time: NaiveDate::from_ymd_opt(year, month, 10).unwrap().into(),
+            account_id: account.id,
+            operation_type: OperationType::Buy,
+            summary: amount.abs(),
+            direction: if amount.is_sign_negative() {
+                FinanseDirection::Credit
+            } else {
+                FinanseDirection::Debet
+            },
+            receipt_id: None,
+            reference: None,
+            counterparty: None,
+            note: None,
+            category: None,
+            transfer_id: None,
+        }
+    }
+
+    fn database() -> Database {
+        let main = account("Main");
+        let savings = account("Savings");
+        let mut db = Database::new();
+        let mut opening = operation(&main, 2026, 1, dec!(1000));
+        opening.operation_type = OperationType::Initial;
+        db.operations.push(opening);
+        db.operations.push(operation(&main, 2026, 1, dec!(500)));
+        db.operations.push(operation(&main, 2026, 1, dec!(-200)));
+        let mut from = operation(&main, 2026, 2, dec!(-100));
+        let mut to = operation(&savings, 2026, 2, dec!(100));
+        from.transfer_id = Some(to.id);
+        to.transfer_id = Some(from.id);
+        db.operations.push(to);
+        db.operations.push(from);
+        db.operations.push(operation(&main, 2025, 12, dec!(-50)));
+        db.accounts = vec![savings, main];
+        db
+    }
+
+    fn row(month: &str, name: &str, totals: [Decimal; 4]) -> Vec<Cell> {
+        let mut row = vec![
+            Cell::Text(month.to_string()),
+            Cell::Text(name.to_string()),
+            Cell::Text("RUB".to_string()),
+        ];
+        row.extend(totals.map(Cell::Number));
+        row
+    }
+
+    #[test]
+    fn monthly_totals() {
+        let sheet = monthly(&database(), 2026);
+        assert_eq!(sheet.name, "Monthly 2026");
+        assert_eq!(
+            sheet.rows,
+            vec![
+                // Начальный остаток не считается доходом
+                row(
+                    "2026-01",
+                    "Main",
+                    [dec!(500), dec!(200), dec!(0), dec!(300)]
+                ),
+                row(
+                    "2026-02",
+                    "Main",
+                    [dec!(0), dec!(0), dec!(-100), dec!(-100)]
+                ),
+                row(
+                    "2026-02",
+                    "Savings",
+                    [dec!(0), dec!(0), dec!(100), dec!(100)]
+                ),
+            ]
+        );
+        assert!(monthly(&database(), 2024).rows.is_empty());
+    }
+
+    #[test]
+    fn balances_include_opening() {
+        let db = database();
+        let sheet = accounts(&db);
+        let balances: Vec<(&Cell, &Cell)> =
+            sheet.rows.iter().map(|row| (&row[1], &row[5])).collect();
+        assert_eq!(
+            balances,
+            vec![
+                (&Cell::Text("Savings".to_string()), &Cell::Number(dec!(100))),
+                (&Cell::Text("Main".to_string()), &Cell::Number(dec!(1150))),
+            ]
+        );
+    }
+
+    #[test]
+    fn operations_with_receipts_and_transfers() {
+        let mut db = database();
+        let mut receipt = Receipt::empty_new();
+        receipt.id = Uuid::new_v4();
+        db.operations[2].receipt_id = Some(receipt.id);
+        db.receipts.push(receipt);
+        let sheet = all_operations(&db);
+        assert_eq!(sheet.rows.len(), db.operations.len());
+        // По дате: первой идёт операция прошлого года
+        assert_eq!(sheet.rows[0][3], Cell::Number(dec!(-50)));
+        let expense = sheet
+            .rows
+            .iter()
+            .find(|row| row[3] == Cell::Number(dec!(-200)))
+            .unwrap();
+        assert_eq!(expense[9], Cell::Integer(1));
+        let transfer = sheet
+            .rows
+            .iter()
+            .find(|row| row[3] == Cell::Number(dec!(-100)))
+            .unwrap();
+        assert_eq!(transfer[10], Cell::Text("Savings".to_string()));
+    }
+}
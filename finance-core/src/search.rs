@@ -0,0 +1,592 @@
+// Поиск по операциям и чекам. Запрос - слова через пробел, все условия должны
+// выполняться одновременно:
+//   place:Пятёрочка   магазин или адрес в чеке, контрагент операции
+//   item:молоко       позиция чека
+//   amount>1000       сумма; также <, >=, <=, = и amount:1000
+//   date:2026-03      начало даты в виде ГГГГ-ММ-ДД; также date>=2026-03-01 и т.п.
+//                     и date:today, date:month, date:year - текущие день, месяц, год
+//   receipt:yes       есть ли у операции чек (yes или no)
+//   type:Buy          тип операции
+//   account:, category:, note:
+//   молоко            любое текстовое поле; слово с : или сравнением, которое не
+//                     начинается с имени поля (10:30, a>b), - тоже текст
+// Значение с пробелами берётся в кавычки: place:"Красное и Белое". Текст
+// сравнивается без учёта регистра, ё и е не различаются.
+
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use std::cmp::Ordering;
+use std::fmt;
+use uuid::Uuid;
+
+use crate::money::parse_decimal;
+use crate::operation::Operation;
+use crate::receipt::Receipt;
+use crate::store::Store;
+
+#[derive(Debug, PartialEq)]
+pub enum QueryError {
+    BadValue(String, String),
+    UnclosedQuote,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::BadValue(field, value) => {
+                write!(f, "Неверное значение {} для поля {}", value, field)
+            }
+            QueryError::UnclosedQuote => write!(f, "Не закрыта кавычка"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Compare {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Compare {
+    fn accepts(&self, ordering: Ordering) -> bool {
+        match self {
+            Compare::Less => ordering == Ordering::Less,
+            Compare::LessOrEqual => ordering != Ordering::Greater,
+            Compare::Equal => ordering == Ordering::Equal,
+            Compare::GreaterOrEqual => ordering != Ordering::Less,
+            Compare::Greater => ordering == Ordering::Greater,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Term {
+    Text(String),
+    Place(String),
+    Item(String),
+    Account(String),
+    Category(String),
+    Note(String),
+    Type(String),
+    Amount(Compare, Decimal),
+    DatePrefix(String),
+    Date(Compare, NaiveDate),
+    Receipt(bool),
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+fn normalize(text: &str) -> String {
+    text.to_lowercase().replace('ё', "е")
+}
+
+// Разбивка на слова с учётом кавычек; кавычки в результат не попадают
+fn words(text: &str) -> Result<Vec<String>, QueryError> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for character in text.chars() {
+        match character {
+            '"' => quoted = !quoted,
+            character if character.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            character => word.push(character),
+        }
+    }
+    if quoted {
+        return Err(QueryError::UnclosedQuote);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+// Имя поля и сравнение: "amount>=" -> ("amount", GreaterOrEqual, остаток)
+fn split(word: &str) -> Option<(&str, Compare, &str)> {
+    let position = word.find([':', '<', '>', '='])?;
+    let (name, rest) = word.split_at(position);
+    let (compare, value) = if let Some(value) = rest.strip_prefix(">=") {
+        (Compare::GreaterOrEqual, value)
+    } else if let Some(value) = rest.strip_prefix("<=") {
+        (Compare::LessOrEqual, value)
+    } else if let Some(value) = rest.strip_prefix('>') {
+        (Compare::Greater, value)
+    } else if let Some(value) = rest.strip_prefix('<') {
+        (Compare::Less, value)
+    } else {
+        (Compare::Equal, &rest[1..])
+    };
+    Some((name, compare, value))
+}
+
+// Периоды относительно текущей даты раскрываются в начало даты
+fn relative_date(text: &str) -> String {
+    let today = Local::now().date_naive();
+    match text {
+        "today" => today.format("%Y-%m-%d").to_string(),
+        "month" => today.format("%Y-%m").to_string(),
+        "year" => today.format("%Y").to_string(),
+        _ => text.to_string(),
+    }
+}
+
+fn term(word: &str) -> Result<Term, QueryError> {
+    let Some((name, compare, value)) = split(word) else {
+        return Ok(Term::Text(normalize(word)));
+    };
+    let bad = || QueryError::BadValue(name.to_string(), value.to_string());
+    let text = normalize(value);
+    let term = match normalize(name).as_str() {
+        "amount" | "sum" => Term::Amount(compare, parse_decimal(value).ok_or_else(bad)?),
+        "date" if compare == Compare::Equal => Term::DatePrefix(relative_date(&text)),
+        "date" => Term::Date(
+            compare,
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| bad())?,
+        ),
+        "place" | "item" | "account" | "category" | "note" | "type" | "receipt"
+            if compare != Compare::Equal =>
+        {
+            return Err(bad());
+        }
+        "place" => Term::Place(text),
+        "item" => Term::Item(text),
+        "account" => Term::Account(text),
+        "category" => Term::Category(text),
+        "note" => Term::Note(text),
+        "type" => Term::Type(text),
+        "receipt" => match text.as_str() {
+            "yes" => Term::Receipt(true),
+            "no" => Term::Receipt(false),
+            _ => return Err(bad()),
+        },
+        // Не поле: время 10:30, адрес с двоеточием и т.п. ищутся как текст
+        _ => Term::Text(normalize(word)),
+    };
+    Ok(term)
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Self, QueryError> {
+        let terms = words(text)?
+            .iter()
+            .map(|word| term(word))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { terms })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches_operation(&self, store: &Store, operation: &Operation) -> bool {
+        let entry = Entry::of_operation(store, operation);
+        self.terms.iter().all(|term| entry.matches(term))
+    }
+}
+
+// Найденная запись: операция (вместе с её чеком) или чек без операции
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Hit {
+    Operation(Uuid),
+    Receipt(Uuid),
+}
+
+fn contains(value: Option<&str>, text: &str) -> bool {
+    value.is_some_and(|value| normalize(value).contains(text))
+}
+
+fn places(receipt: &Receipt) -> Vec<&str> {
+    let mut places = vec![receipt.place.as_deref(), receipt.address.as_deref()];
+    if let Some(slip) = &receipt.slip {
+        places.push(slip.place.as_deref());
+        places.push(slip.address.as_deref());
+    }
+    places.into_iter().flatten().collect()
+}
+
+// Запись поиска: операция и/или чек с общими датой и суммой
+struct Entry<'a> {
+    operation: Option<&'a Operation>,
+    receipt: Option<&'a Receipt>,
+    account: Option<&'a str>,
+}
+
+impl<'a> Entry<'a> {
+    fn of_operation(store: &'a Store, operation: &'a Operation) -> Self {
+        Entry {
+            operation: Some(operation),
+            receipt: operation.receipt_id.and_then(|id| store.receipt(id)),
+            account: store
+                .account(operation.account_id)
+                .map(|account| account.name.as_str()),
+        }
+    }
+
+    fn date_time(&self) -> NaiveDateTime {
+        self.operation
+            .map(|operation| operation.date_time)
+            .or(self.receipt.map(|receipt| receipt.date_time))
+            .unwrap_or_default()
+    }
+
+    fn amount(&self) -> Decimal {
+        self.operation
+            .map(|operation| operation.summary)
+            .or(self.receipt.map(|receipt| receipt.summary))
+            .unwrap_or_default()
+    }
+
+    fn place(&self, text: &str) -> bool {
+        self.receipt.is_some_and(|receipt| {
+            places(receipt)
+                .into_iter()
+                .any(|place| contains(Some(place), text))
+        }) || contains(self.operation.and_then(|o| o.counterparty.as_deref()), text)
+    }
+
+    fn item(&self, text: &str) -> bool {
+        self.receipt.is_some_and(|receipt| {
+            receipt
+                .subjects
+                .iter()
+                .any(|subject| contains(Some(&subject.name), text))
+        })
+    }
+
+    fn operation_field(&self, text: &str, field: fn(&Operation) -> Option<&str>) -> bool {
+        contains(self.operation.and_then(field), text)
+    }
+
+    fn matches(&self, term: &Term) -> bool {
+        match term {
+            Term::Text(text) => {
+                self.place(text)
+                    || self.item(text)
+                    || contains(self.account, text)
+                    || self.operation_field(text, |o| o.category.as_deref())
+                    || self.operation_field(text, |o| o.note.as_deref())
+                    || self.operation_field(text, |o| o.reference.as_deref())
+            }
+            Term::Place(text) => self.place(text),
+            Term::Item(text) => self.item(text),
+            Term::Account(text) => contains(self.account, text),
+            Term::Category(text) => self.operation_field(text, |o| o.category.as_deref()),
+            Term::Note(text) => self.operation_field(text, |o| o.note.as_deref()),
+            Term::Type(text) => self.operation.is_some_and(|operation| {
+                normalize(&format!("{:?}", operation.operation_type)) == *text
+            }),
+            Term::Amount(compare, value) => compare.accepts(self.amount().cmp(value)),
+            Term::DatePrefix(prefix) => self
+                .date_time()
+                .format("%Y-%m-%d")
+                .to_string()
+                .starts_with(prefix.as_str()),
+            Term::Date(compare, date) => compare.accepts(self.date_time().date().cmp(date)),
+            Term::Receipt(present) => self.receipt.is_some() == *present,
+        }
+    }
+}
+
+// Найденные записи, новые первыми
+pub fn search(store: &Store, query: &Query) -> Vec<Hit> {
+    let operations = store
+        .operations_by_date()
+        .rev()
+        .map(|operation| Entry::of_operation(store, operation));
+    let receipts = store
+        .receipts
+        .iter()
+        .filter(|receipt| store.receipt_operation(receipt.id).is_none())
+        .map(|receipt| Entry {
+            operation: None,
+            receipt: Some(receipt),
+            account: None,
+        });
+    let mut hits: Vec<(NaiveDateTime, Hit)> = operations
+        .chain(receipts)
+        .filter(|entry| query.terms.iter().all(|term| entry.matches(term)))
+        .map(|entry| {
+            let hit = match (entry.operation, entry.receipt) {
+                (Some(operation), _) => Hit::Operation(operation.id),
+                (None, receipt) => Hit::Receipt(receipt.unwrap().id),
+            };
+            (entry.date_time(), hit)
+        })
+        .collect();
+    hits.sort_by_key(|(date_time, _)| std::cmp::Reverse(*date_time));
+    hits.into_iter().map(|(_, hit)| hit).collect()
+}
+
+// Позиции чека записи, подходящие под условия item: и слова запроса, для показа
+// в результатах
+pub fn matched_items(store: &Store, query: &Query, hit: Hit) -> Vec<String> {
+    let receipt = match hit {
+        Hit::Operation(id) => store
+            .operation(id)
+            .and_then(|operation| operation.receipt_id)
+            .and_then(|id| store.receipt(id)),
+        Hit::Receipt(id) => store.receipt(id),
+    };
+    let Some(receipt) = receipt else {
+        return Vec::new();
+    };
+    receipt
+        .subjects
+        .iter()
+        .filter(|subject| {
+            query.terms.iter().any(|term| match term {
+                Term::Item(text) | Term::Text(text) => contains(Some(&subject.name), text),
+                _ => false,
+            })
+        })
+        .map(|subject| subject.name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{Account, AccountType};
+    use crate::database::Database;
+    use crate::operation::{FinanseDirection, OperationType};
+    use crate::receipt::{Currency, Subject};
+    use rust_decimal::dec;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+    }
+
+    #[test]
+    fn grammar() {
+        let query =
+            Query::parse("  молоко  place:\"Красное и Белое\" amount>=\"1 000,50\" ").unwrap();
+        assert_eq!(
+            query.terms,
+            vec![
+                Term::Text("молоко".to_string()),
+                Term::Place("красное и белое".to_string()),
+                Term::Amount(Compare::GreaterOrEqual, dec!(1000.50)),
+            ]
+        );
+        // Без кавычек пробел разделяет слова
+        assert_eq!(
+            Query::parse("amount>=1 000").unwrap().terms,
+            vec![
+                Term::Amount(Compare::GreaterOrEqual, dec!(1)),
+                Term::Text("000".to_string()),
+            ]
+        );
+        let query = Query::parse("amount<10 sum<=10 amount>10 amount=10 amount:10").unwrap();
+        let compares: Vec<Compare> = query
+            .terms
+            .iter()
+            .map(|term| match term {
+                Term::Amount(compare, value) => {
+                    assert_eq!(*value, dec!(10));
+                    *compare
+                }
+                other => panic!("{:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            compares,
+            vec![
+                Compare::Less,
+                Compare::LessOrEqual,
+                Compare::Greater,
+                Compare::Equal,
+                Compare::Equal,
+            ]
+        );
+        // Имя поля без учёта регистра, ё не отличается от е
+        assert_eq!(
+            Query::parse("Category:Ёлка").unwrap().terms,
+            vec![Term::Category("елка".to_string())]
+        );
+        assert!(Query::parse("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(
+            Query::parse("date:2026-03 date>=2026-03-01 date<2026-04-01")
+                .unwrap()
+                .terms,
+            vec![
+                Term::DatePrefix("2026-03".to_string()),
+                Term::Date(Compare::GreaterOrEqual, date(1)),
+                Term::Date(Compare::Less, NaiveDate::from_ymd_opt(2026, 4, 1).unwrap()),
+            ]
+        );
+        let today = Local::now().date_naive();
+        assert_eq!(
+            Query::parse("date:month").unwrap().terms,
+            vec![Term::DatePrefix(today.format("%Y-%m").to_string())]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Query::parse("place:\"Пятёрочка"),
+            Err(QueryError::UnclosedQuote)
+        );
+        assert_eq!(
+            Query::parse("amount>много"),
+            Err(QueryError::BadValue(
+                "amount".to_string(),
+                "много".to_string()
+            ))
+        );
+        assert_eq!(
+            Query::parse("date>2026-03"),
+            Err(QueryError::BadValue(
+                "date".to_string(),
+                "2026-03".to_string()
+            ))
+        );
+        // Сравнение только для суммы и даты
+        assert_eq!(
+            Query::parse("place>Лента"),
+            Err(QueryError::BadValue(
+                "place".to_string(),
+                "Лента".to_string()
+            ))
+        );
+        assert_eq!(
+            Query::parse("receipt:maybe"),
+            Err(QueryError::BadValue(
+                "receipt".to_string(),
+                "maybe".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn text_with_separators() {
+        // Слово с двоеточием или сравнением без имени поля - обычный текст
+        assert_eq!(
+            Query::parse("10:30 shop:Лента a>b ул.Мира=5")
+                .unwrap()
+                .terms,
+            vec![
+                Term::Text("10:30".to_string()),
+                Term::Text("shop:лента".to_string()),
+                Term::Text("a>b".to_string()),
+                Term::Text("ул.мира=5".to_string()),
+            ]
+        );
+        let (mut store, _, salary, _) = store();
+        let mut operation = store.operation(salary).unwrap().clone();
+        operation.note = Some("Встреча в 10:30".to_string());
+        store.upsert_operation(operation);
+        assert_eq!(find(&store, "10:30"), vec![Hit::Operation(salary)]);
+    }
+
+    fn store() -> (Store, Uuid, Uuid, Uuid) {
+        let mut db = Database::new();
+        let account = Account {
+            id: Uuid::new_v4(),
+            name: "Карта".to_string(),
+            account_type: AccountType::DebetCard,
+            number: String::new(),
+            bik: 0,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Currency::Rub,
+        };
+        let mut receipt = Receipt::empty_new();
+        receipt.id = Uuid::new_v4();
+        receipt.date_time = date(5).into();
+        receipt.summary = dec!(349.90);
+        receipt.place = Some("Пятёрочка".to_string());
+        let mut milk = Subject::empty();
+        milk.name = "Молоко 3,2%".to_string();
+        receipt.subjects.push(milk);
+        let mut free = Receipt::empty_new();
+        free.id = Uuid::new_v4();
+        free.date_time = date(20).into();
+        free.summary = dec!(80);
+        let with_receipt = Operation {
+            id: Uuid::new_v4(),
+            date_time: date(5).into(),
+            account_id: account.id,
+            operation_type: OperationType::Buy,
+            summary: dec!(349.90),
+            direction: FinanseDirection::Credit,
+            receipt_id: Some(receipt.id),
+            reference: None,
+            counterparty: None,
+            note: None,
+            category: Some("Еда".to_string()),
+            transfer_id: None,
+        };
+        let mut salary = with_receipt.clone();
+        salary.id = Uuid::new_v4();
+        salary.date_time = date(10).into();
+        salary.summary = dec!(50000);
+        salary.direction = FinanseDirection::Debet;
+        salary.receipt_id = None;
+        salary.category = None;
+        salary.counterparty = Some("ООО Ромашка".to_string());
+        let ids = (with_receipt.id, salary.id, free.id);
+        db.accounts.push(account);
+        db.receipts.push(receipt);
+        db.receipts.push(free);
+        db.operations.push(with_receipt);
+        db.operations.push(salary);
+        (Store::new(db), ids.0, ids.1, ids.2)
+    }
+
+    fn find(store: &Store, text: &str) -> Vec<Hit> {
+        search(store, &Query::parse(text).unwrap())
+    }
+
+    #[test]
+    fn search_entries() {
+        let (store, bought, salary, free) = store();
+        // Новые первыми, чек без операции тоже находится
+        assert_eq!(
+            find(&store, ""),
+            vec![
+                Hit::Receipt(free),
+                Hit::Operation(salary),
+                Hit::Operation(bought),
+            ]
+        );
+        assert_eq!(find(&store, "пятерочка"), vec![Hit::Operation(bought)]);
+        assert_eq!(find(&store, "place:ромашка"), vec![Hit::Operation(salary)]);
+        assert_eq!(find(&store, "item:молоко"), vec![Hit::Operation(bought)]);
+        assert_eq!(
+            find(&store, "account:карта receipt:no"),
+            vec![Hit::Operation(salary)]
+        );
+        assert_eq!(
+            find(&store, "amount<1000 date>=2026-03-05"),
+            vec![Hit::Receipt(free), Hit::Operation(bought)]
+        );
+        assert_eq!(
+            find(&store, "date:2026-03-1 type:buy"),
+            vec![Hit::Operation(salary)]
+        );
+        assert!(find(&store, "category:еда note:что-то").is_empty());
+        assert_eq!(
+            matched_items(
+                &store,
+                &Query::parse("молоко").unwrap(),
+                Hit::Operation(bought)
+            ),
+            vec!["Молоко 3,2%".to_string()]
+        );
+    }
+}
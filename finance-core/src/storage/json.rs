@@ -0,0 +1,373 @@
+// База целиком в одном JSON-файле. Каждое изменение переписывает файл через
+// временный, чтобы сбой записи не оставил его обрезанным. Журнал изменений
+// лежит рядом в файле <база>.audit.jsonl по записи в строке и только
+// дописывается: старая копия базы или отмена не укорачивают его.
+
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::account::Account;
+use crate::audit::AuditEntry;
+use crate::database::Database;
+use crate::operation::Operation;
+use crate::receipt::Receipt;
+use crate::storage::{OperationQuery, Storage, StorageError};
+
+pub struct JsonStorage {
+    path: String,
+    // База без журнала
+    db: Database,
+    audit: Vec<AuditEntry>,
+    // Записи, которые уже есть в файле журнала
+    written: HashSet<Uuid>,
+}
+
+pub fn audit_path(path: &str) -> String {
+    format!("{}.audit.jsonl", path)
+}
+
+// Записи файла журнала. Последняя строка без перевода строки могла остаться
+// недописанной при сбое, её нельзя разобрать - она пропускается.
+fn read_audit(path: &str) -> Result<Vec<AuditEntry>, StorageError> {
+    if !Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        if !line.trim().is_empty() {
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(_) if !line.ends_with('\n') => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+        line.clear();
+    }
+    Ok(entries)
+}
+
+fn insert<T: Clone>(list: &mut Vec<T>, item: &T, id: fn(&T) -> Uuid) -> Result<(), StorageError> {
+    if list.iter().any(|element| id(element) == id(item)) {
+        return Err(StorageError::Duplicate(id(item)));
+    }
+    list.push(item.clone());
+    Ok(())
+}
+
+fn update<T: Clone>(list: &mut [T], item: &T, id: fn(&T) -> Uuid) -> Result<(), StorageError> {
+    let element = list
+        .iter_mut()
+        .find(|element| id(element) == id(item))
+        .ok_or(StorageError::NotFound(id(item)))?;
+    *element = item.clone();
+    Ok(())
+}
+
+fn delete<T>(list: &mut Vec<T>, key: Uuid, id: fn(&T) -> Uuid) -> Result<(), StorageError> {
+    let index = list
+        .iter()
+        .position(|element| id(element) == key)
+        .ok_or(StorageError::NotFound(key))?;
+    list.remove(index);
+    Ok(())
+}
+
+// Пустой файл или файл, последняя строка которого дописана
+fn ends_with_newline(path: &str) -> Result<bool, StorageError> {
+    if !Path::new(path).exists() {
+        return Ok(true);
+    }
+    let mut file = File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+    file.seek(SeekFrom::End(-1))?;
+    let mut last = [0];
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+impl JsonStorage {
+    pub fn open(path: &str) -> Result<Self, StorageError> {
+        let mut db: Database = if Path::new(path).exists() {
+            let reader = BufReader::new(File::open(path)?);
+            serde_json::from_reader(reader)?
+        } else {
+            Database::new()
+        };
+        let entries = read_audit(&audit_path(path))?;
+        let written: HashSet<Uuid> = entries.iter().map(|entry| entry.id).collect();
+        // Журнал из файла старого формата идёт первым и переносится в файл
+        // журнала при следующем сохранении
+        let mut audit = std::mem::take(&mut db.audit);
+        audit.retain(|entry| !written.contains(&entry.id));
+        audit.extend(entries);
+        Ok(Self {
+            path: path.to_string(),
+            db,
+            audit,
+            written,
+        })
+    }
+
+    // Дописывает в файл журнала записи, которых там ещё нет
+    fn append_audit(&mut self, audit: &[AuditEntry]) -> Result<(), StorageError> {
+        let new: Vec<&AuditEntry> = audit
+            .iter()
+            .filter(|entry| !self.written.contains(&entry.id))
+            .collect();
+        if new.is_empty() {
+            return Ok(());
+        }
+        let path = audit_path(&self.path);
+        // Недописанная при сбое строка отрезается, чтобы не испортить следующую
+        if !ends_with_newline(&path)? {
+            let text = std::fs::read(&path)?;
+            let length = text
+                .iter()
+                .rposition(|byte| *byte == b'\n')
+                .map_or(0, |i| i + 1);
+            OpenOptions::new()
+                .write(true)
+                .open(&path)?
+                .set_len(length as u64)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let mut writer = BufWriter::new(file);
+        for entry in new.iter() {
+            serde_json::to_writer(&mut writer, entry)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        self.written.extend(new.iter().map(|entry| entry.id));
+        Ok(())
+    }
+
+    fn write(&self) -> Result<(), StorageError> {
+        let temporary = format!("{}.tmp", self.path);
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        serde_json::to_writer_pretty(&mut writer, &self.db)?;
+        writer.flush()?;
+        drop(writer);
+        std::fs::rename(&temporary, &self.path)?;
+        Ok(())
+    }
+}
+
+impl Storage for JsonStorage {
+    fn load(&self) -> Result<Database, StorageError> {
+        let mut db = self.db.clone();
+        db.audit = self.audit.clone();
+        Ok(db)
+    }
+
+    // Журнал дописывается до записи базы: при сбое между ними в журнале
+    // окажутся лишние записи, а не пропадут нужные
+    fn save(&mut self, db: &Database) -> Result<(), StorageError> {
+        self.append_audit(&db.audit)?;
+        let known: HashSet<Uuid> = self.audit.iter().map(|entry| entry.id).collect();
+        let new = db.audit.iter().filter(|entry| !known.contains(&entry.id));
+        self.audit.extend(new.cloned());
+        self.db = db.clone();
+        self.db.audit.clear();
+        self.write()
+    }
+
+    fn accounts(&self) -> Result<Vec<Account>, StorageError> {
+        Ok(self.db.accounts.clone())
+    }
+
+    fn account(&self, id: Uuid) -> Result<Option<Account>, StorageError> {
+        Ok(self.db.accounts.iter().find(|a| a.id == id).cloned())
+    }
+
+    fn insert_account(&mut self, account: &Account) -> Result<(), StorageError> {
+        insert(&mut self.db.accounts, account, |a| a.id)?;
+        self.write()
+    }
+
+    fn update_account(&mut self, account: &Account) -> Result<(), StorageError> {
+        update(&mut self.db.accounts, account, |a| a.id)?;
+        self.write()
+    }
+
+    fn delete_account(&mut self, id: Uuid) -> Result<(), StorageError> {
+        delete(&mut self.db.accounts, id, |a| a.id)?;
+        self.write()
+    }
+
+    fn operations(&self, query: &OperationQuery) -> Result<Vec<Operation>, StorageError> {
+        let mut result: Vec<Operation> = self
+            .db
+            .operations
+            .iter()
+            .filter(|operation| query.matches(operation))
+            .cloned()
+            .collect();
+        result.sort_by_key(|operation| (operation.date_time, operation.id));
+        Ok(result)
+    }
+
+    fn operation(&self, id: Uuid) -> Result<Option<Operation>, StorageError> {
+        Ok(self.db.operations.iter().find(|o| o.id == id).cloned())
+    }
+
+    fn insert_operation(&mut self, operation: &Operation) -> Result<(), StorageError> {
+        insert(&mut self.db.operations, operation, |o| o.id)?;
+        self.write()
+    }
+
+    fn update_operation(&mut self, operation: &Operation) -> Result<(), StorageError> {
+        update(&mut self.db.operations, operation, |o| o.id)?;
+        self.write()
+    }
+
+    fn delete_operation(&mut self, id: Uuid) -> Result<(), StorageError> {
+        delete(&mut self.db.operations, id, |o| o.id)?;
+        self.write()
+    }
+
+    fn receipts(&self) -> Result<Vec<Receipt>, StorageError> {
+        let mut result = self.db.receipts.clone();
+        result.sort_by_key(|receipt| (receipt.date_time, receipt.id));
+        Ok(result)
+    }
+
+    fn receipt(&self, id: Uuid) -> Result<Option<Receipt>, StorageError> {
+        Ok(self.db.receipts.iter().find(|r| r.id == id).cloned())
+    }
+
+    fn insert_receipt(&mut self, receipt: &Receipt) -> Result<(), StorageError> {
+        insert(&mut self.db.receipts, receipt, |r| r.id)?;
+        self.write()
+    }
+
+    fn update_receipt(&mut self, receipt: &Receipt) -> Result<(), StorageError> {
+        update(&mut self.db.receipts, receipt, |r| r.id)?;
+        self.write()
+    }
+
+    fn delete_receipt(&mut self, id: Uuid) -> Result<(), StorageError> {
+        delete(&mut self.db.receipts, id, |r| r.id)?;
+        self.write()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::Change;
+    use chrono::NaiveDateTime;
+
+    fn entry(command: &str) -> AuditEntry {
+        AuditEntry {
+            id: Uuid::new_v4(),
+            date_time: NaiveDateTime::default(),
+            user: "user@host".to_string(),
+            command: command.to_string(),
+            change: Change::Profiles {
+                before: Vec::new(),
+                after: Vec::new(),
+            },
+        }
+    }
+
+    fn temporary() -> String {
+        let path = std::env::temp_dir().join(format!("finance-core-{}.json", Uuid::new_v4()));
+        path.to_str().unwrap().to_string()
+    }
+
+    fn lines(path: &str) -> usize {
+        std::fs::read_to_string(audit_path(path))
+            .unwrap()
+            .lines()
+            .count()
+    }
+
+    #[test]
+    fn audit_only_appended() {
+        let path = temporary();
+        let mut storage = JsonStorage::open(&path).unwrap();
+        let mut db = Database::new();
+        db.audit = vec![entry("first"), entry("second")];
+        storage.save(&db).unwrap();
+        assert_eq!(lines(&path), 2);
+        // Журнал не попадает в файл базы
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("first"));
+
+        // Старая копия базы с более коротким журналом его не укорачивает
+        let mut old = Database::new();
+        old.audit = vec![db.audit[0].clone(), entry("third")];
+        storage.save(&old).unwrap();
+        assert_eq!(lines(&path), 3);
+        storage.save(&old).unwrap();
+        assert_eq!(lines(&path), 3);
+
+        let reopened = JsonStorage::open(&path).unwrap().load().unwrap();
+        let commands: Vec<&str> = reopened
+            .audit
+            .iter()
+            .map(|entry| entry.command.as_str())
+            .collect();
+        assert_eq!(commands, vec!["first", "second", "third"]);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(audit_path(&path)).unwrap();
+    }
+
+    #[test]
+    fn audit_from_old_file() {
+        let path = temporary();
+        let mut db = Database::new();
+        db.audit = vec![entry("old")];
+        // Старый формат: журнал внутри файла базы
+        let mut text = serde_json::to_string(&db).unwrap();
+        assert!(text.contains("\"audit\""));
+        std::fs::write(&path, &text).unwrap();
+        let mut storage = JsonStorage::open(&path).unwrap();
+        let mut loaded = storage.load().unwrap();
+        assert_eq!(loaded.audit, db.audit);
+
+        loaded.audit.push(entry("new"));
+        storage.save(&loaded).unwrap();
+        assert_eq!(lines(&path), 2);
+        text = std::fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("\"audit\""));
+        assert_eq!(
+            JsonStorage::open(&path).unwrap().load().unwrap().audit,
+            loaded.audit
+        );
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(audit_path(&path)).unwrap();
+    }
+
+    #[test]
+    fn unfinished_audit_line() {
+        let path = temporary();
+        let first = serde_json::to_string(&entry("first")).unwrap();
+        let second = serde_json::to_string(&entry("second")).unwrap();
+        std::fs::write(audit_path(&path), format!("{}\n{}", first, &second[..20])).unwrap();
+        let mut storage = JsonStorage::open(&path).unwrap();
+        let mut db = storage.load().unwrap();
+        assert_eq!(db.audit.len(), 1);
+        db.audit.push(entry("third"));
+        storage.save(&db).unwrap();
+        assert_eq!(
+            JsonStorage::open(&path).unwrap().load().unwrap().audit,
+            db.audit
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        // Испорченная строка в середине - ошибка формата
+        std::fs::write(audit_path(&path), format!("{}\n{}\n", &second[..20], first)).unwrap();
+        assert!(matches!(
+            JsonStorage::open(&path),
+            Err(StorageError::Format(_))
+        ));
+        std::fs::remove_file(audit_path(&path)).unwrap();
+    }
+}
@@ -0,0 +1,314 @@
+// Хранилище базы: JSON-файл целиком или встроенная SQLite с построчным доступом
+
+use chrono::NaiveDateTime;
+use std::fmt;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::account::Account;
+use crate::database::Database;
+use crate::operation::Operation;
+use crate::receipt::Receipt;
+
+pub mod json;
+pub mod sqlite;
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(std::io::Error),
+    Format(String),
+    Sqlite(rusqlite::Error),
+    NotFound(Uuid),
+    Duplicate(Uuid),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(error) => write!(f, "Ошибка файла: {}", error),
+            StorageError::Format(message) => write!(f, "Ошибка формата: {}", message),
+            StorageError::Sqlite(error) => write!(f, "Ошибка SQLite: {}", error),
+            StorageError::NotFound(id) => write!(f, "Запись {} не найдена", id),
+            StorageError::Duplicate(id) => write!(f, "Запись {} уже существует", id),
+        }
+    }
+}
+
+impl From<std::io::Error> for StorageError {
+    fn from(error: std::io::Error) -> Self {
+        StorageError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(error: serde_json::Error) -> Self {
+        StorageError::Format(error.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(error: rusqlite::Error) -> Self {
+        StorageError::Sqlite(error)
+    }
+}
+
+// Отбор операций; пустые поля не ограничивают выборку, период - [from, to)
+#[derive(Debug, Default, Clone)]
+pub struct OperationQuery {
+    pub account_id: Option<Uuid>,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+impl OperationQuery {
+    pub fn matches(&self, operation: &Operation) -> bool {
+        self.account_id.is_none_or(|id| operation.account_id == id)
+            && self.from.is_none_or(|from| operation.date_time >= from)
+            && self.to.is_none_or(|to| operation.date_time < to)
+    }
+}
+
+// Операции выборки возвращаются упорядоченными по дате и идентификатору.
+// Добавление записи с существующим id - Duplicate, изменение и удаление
+// отсутствующей - NotFound.
+pub trait Storage {
+    // Вся база целиком, для GUI и отчётов
+    fn load(&self) -> Result<Database, StorageError>;
+    // Замена всего содержимого хранилища
+    fn save(&mut self, db: &Database) -> Result<(), StorageError>;
+
+    fn accounts(&self) -> Result<Vec<Account>, StorageError>;
+    fn account(&self, id: Uuid) -> Result<Option<Account>, StorageError>;
+    fn insert_account(&mut self, account: &Account) -> Result<(), StorageError>;
+    fn update_account(&mut self, account: &Account) -> Result<(), StorageError>;
+    fn delete_account(&mut self, id: Uuid) -> Result<(), StorageError>;
+
+    fn operations(&self, query: &OperationQuery) -> Result<Vec<Operation>, StorageError>;
+    fn operation(&self, id: Uuid) -> Result<Option<Operation>, StorageError>;
+    fn insert_operation(&mut self, operation: &Operation) -> Result<(), StorageError>;
+    fn update_operation(&mut self, operation: &Operation) -> Result<(), StorageError>;
+    fn delete_operation(&mut self, id: Uuid) -> Result<(), StorageError>;
+
+    fn receipts(&self) -> Result<Vec<Receipt>, StorageError>;
+    fn receipt(&self, id: Uuid) -> Result<Option<Receipt>, StorageError>;
+    fn insert_receipt(&mut self, receipt: &Receipt) -> Result<(), StorageError>;
+    fn update_receipt(&mut self, receipt: &Receipt) -> Result<(), StorageError>;
+    fn delete_receipt(&mut self, id: Uuid) -> Result<(), StorageError>;
+}
+
+// Файлы .sqlite, .sqlite3 и .db открываются в SQLite, остальные - как JSON
+pub fn is_sqlite(path: &str) -> bool {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    matches!(extension.as_deref(), Some("sqlite" | "sqlite3" | "db"))
+}
+
+// Открытие хранилища по расширению файла; отсутствующий файл создаётся пустым
+pub fn open(path: &str) -> Result<Box<dyn Storage>, StorageError> {
+    if is_sqlite(path) {
+        Ok(Box::new(sqlite::SqliteStorage::open(path)?))
+    } else {
+        Ok(Box::new(json::JsonStorage::open(path)?))
+    }
+}
+
+// Перенос всего содержимого из одного хранилища в другое
+pub fn convert(from: &dyn Storage, to: &mut dyn Storage) -> Result<(), StorageError> {
+    to.save(&from.load()?)
+}
+
+// Общий контракт хранилища проверяется на обоих backend'ах
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::AccountType;
+    use crate::exchange::csv::CsvProfile;
+    use crate::operation::{FinanseDirection, OperationType};
+    use chrono::NaiveDate;
+    use rust_decimal::dec;
+
+    fn date(day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap().into()
+    }
+
+    fn account(name: &str) -> Account {
+        Account {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            account_type: AccountType::Account,
+            number: String::new(),
+            bik: 0,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Default::default(),
+        }
+    }
+
+    fn operation(account: &Account, day: u32) -> Operation {
+        Operation {
+            id: Uuid::new_v4(),
+            date_time: date(day),
+            account_id: account.id,
+            operation_type: OperationType::Buy,
+            summary: dec!(100),
+            direction: FinanseDirection::Credit,
+            receipt_id: None,
+            reference: None,
+            counterparty: None,
+            note: None,
+            category: None,
+            transfer_id: None,
+        }
+    }
+
+    fn receipt(day: u32) -> Receipt {
+        let mut receipt = Receipt::empty_new();
+        receipt.id = Uuid::new_v4();
+        receipt.date_time = date(day);
+        receipt.summary = dec!(100);
+        receipt
+    }
+
+    fn contract(storage: &mut dyn Storage) {
+        assert!(storage.load().unwrap().accounts.is_empty());
+
+        let main = account("Main");
+        let card = account("Card");
+        storage.insert_account(&main).unwrap();
+        storage.insert_account(&card).unwrap();
+        assert!(matches!(
+            storage.insert_account(&main),
+            Err(StorageError::Duplicate(id)) if id == main.id
+        ));
+        let mut renamed = main.clone();
+        renamed.name = "Renamed".to_string();
+        storage.update_account(&renamed).unwrap();
+        assert_eq!(storage.account(main.id).unwrap(), Some(renamed));
+        assert!(matches!(
+            storage.update_account(&account("Missing")),
+            Err(StorageError::NotFound(_))
+        ));
+
+        let late = operation(&main, 20);
+        let early = operation(&main, 5);
+        let other = operation(&card, 10);
+        for element in [&late, &early, &other] {
+            storage.insert_operation(element).unwrap();
+        }
+        assert!(matches!(
+            storage.insert_operation(&late),
+            Err(StorageError::Duplicate(_))
+        ));
+        let all = storage.operations(&OperationQuery::default()).unwrap();
+        let ids: Vec<Uuid> = all.iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec![early.id, other.id, late.id]);
+        let query = OperationQuery {
+            account_id: Some(main.id),
+            from: Some(date(5)),
+            to: Some(date(20)),
+        };
+        assert_eq!(storage.operations(&query).unwrap(), vec![early.clone()]);
+
+        let mut moved = late.clone();
+        moved.date_time = date(1);
+        moved.account_id = card.id;
+        storage.update_operation(&moved).unwrap();
+        let query = OperationQuery {
+            account_id: Some(card.id),
+            ..Default::default()
+        };
+        let ids: Vec<Uuid> = storage
+            .operations(&query)
+            .unwrap()
+            .iter()
+            .map(|o| o.id)
+            .collect();
+        assert_eq!(ids, vec![late.id, other.id]);
+        storage.delete_operation(other.id).unwrap();
+        assert_eq!(storage.operation(other.id).unwrap(), None);
+        assert!(matches!(
+            storage.delete_operation(other.id),
+            Err(StorageError::NotFound(_))
+        ));
+
+        let second = receipt(2);
+        let first = receipt(1);
+        storage.insert_receipt(&second).unwrap();
+        storage.insert_receipt(&first).unwrap();
+        assert_eq!(
+            storage.receipts().unwrap(),
+            vec![first.clone(), second.clone()]
+        );
+        let mut edited = second.clone();
+        edited.summary = dec!(50);
+        storage.update_receipt(&edited).unwrap();
+        assert_eq!(storage.receipt(second.id).unwrap(), Some(edited));
+        storage.delete_receipt(first.id).unwrap();
+        assert!(matches!(
+            storage.delete_receipt(first.id),
+            Err(StorageError::NotFound(_))
+        ));
+
+        // Сохранение заменяет содержимое целиком
+        let mut db = storage.load().unwrap();
+        assert_eq!(db.accounts.len(), 2);
+        assert_eq!(db.operations.len(), 2);
+        assert_eq!(db.receipts.len(), 1);
+        db.accounts.retain(|a| a.id != card.id);
+        db.operations.retain(|o| o.account_id == main.id);
+        db.operations.push(operation(&main, 15));
+        db.receipts.clear();
+        let mut profile = CsvProfile::new();
+        profile.name = "Bank".to_string();
+        db.csv_profiles.push(profile);
+        storage.save(&db).unwrap();
+
+        let loaded = storage.load().unwrap();
+        assert_eq!(loaded.accounts, db.accounts);
+        let mut expected = db.operations.clone();
+        expected.sort_by_key(|o| (o.date_time, o.id));
+        assert_eq!(loaded.operations, expected);
+        assert!(loaded.receipts.is_empty());
+        assert_eq!(loaded.csv_profiles, db.csv_profiles);
+    }
+
+    #[test]
+    fn json_contract() {
+        let path = std::env::temp_dir().join(format!("finance-core-{}.json", Uuid::new_v4()));
+        let path = path.to_str().unwrap().to_string();
+        contract(&mut json::JsonStorage::open(&path).unwrap());
+        // Изменения записаны в файл
+        let reopened = json::JsonStorage::open(&path).unwrap();
+        assert_eq!(reopened.load().unwrap().operations.len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn sqlite_contract() {
+        contract(&mut sqlite::SqliteStorage::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn convert_between_backends() {
+        let mut from = sqlite::SqliteStorage::open_in_memory().unwrap();
+        let main = account("Main");
+        from.insert_account(&main).unwrap();
+        from.insert_operation(&operation(&main, 1)).unwrap();
+        let mut to = sqlite::SqliteStorage::open_in_memory().unwrap();
+        convert(&from, &mut to).unwrap();
+        assert_eq!(
+            to.load().unwrap().operations,
+            from.load().unwrap().operations
+        );
+    }
+
+    #[test]
+    fn backend_by_extension() {
+        assert!(is_sqlite("base.sqlite"));
+        assert!(is_sqlite("/tmp/base.DB"));
+        assert!(!is_sqlite("base.json"));
+        assert!(!is_sqlite("base"));
+    }
+}
@@ -0,0 +1,497 @@
+// Встроенная база SQLite. Записи хранятся как JSON в колонке data, чтобы схема не
+// менялась вместе с моделью; поля для отбора и связей вынесены в отдельные
+// колонки с индексами.
+
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, ErrorCode, OptionalExtension, params};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::account::Account;
+use crate::database::Database;
+use crate::operation::Operation;
+use crate::receipt::Receipt;
+use crate::storage::{OperationQuery, Storage, StorageError};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS accounts (
+    id TEXT PRIMARY KEY,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS operations (
+    id TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL,
+    date_time TEXT NOT NULL,
+    receipt_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS operations_account ON operations (account_id, date_time);
+CREATE INDEX IF NOT EXISTS operations_date ON operations (date_time);
+CREATE INDEX IF NOT EXISTS operations_receipt ON operations (receipt_id);
+CREATE TABLE IF NOT EXISTS receipts (
+    id TEXT PRIMARY KEY,
+    date_time TEXT NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS receipts_date ON receipts (date_time);
+CREATE TABLE IF NOT EXISTS audit (
+    id TEXT PRIMARY KEY,
+    date_time TEXT NOT NULL,
+    data TEXT NOT NULL
+);
+";
+
+// Постоянная ширина, чтобы строки сортировались как даты
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.9f";
+
+fn date_text(date_time: &NaiveDateTime) -> String {
+    date_time.format(DATE_FORMAT).to_string()
+}
+
+fn decode<T: DeserializeOwned>(rows: Vec<String>) -> Result<Vec<T>, StorageError> {
+    rows.iter()
+        .map(|data| serde_json::from_str(data).map_err(StorageError::from))
+        .collect()
+}
+
+pub struct SqliteStorage {
+    connection: Connection,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &str) -> Result<Self, StorageError> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        let connection = Connection::open_in_memory()?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    // Таблицы задаются константами, не пользовательским вводом
+    fn all<T: DeserializeOwned>(&self, table: &str, order: &str) -> Result<Vec<T>, StorageError> {
+        let mut statement = self
+            .connection
+            .prepare(&format!("SELECT data FROM {} ORDER BY {}", table, order))?;
+        let rows = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        decode(rows)
+    }
+
+    fn one<T: DeserializeOwned>(&self, table: &str, id: Uuid) -> Result<Option<T>, StorageError> {
+        let data: Option<String> = self
+            .connection
+            .query_row(
+                &format!("SELECT data FROM {} WHERE id = ?1", table),
+                [id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        data.map(|data| serde_json::from_str(&data).map_err(StorageError::from))
+            .transpose()
+    }
+
+    fn meta(&self, key: &str) -> Result<Option<String>, StorageError> {
+        Ok(self
+            .connection
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()?)
+    }
+}
+
+fn json<T: Serialize>(value: &T) -> Result<String, StorageError> {
+    Ok(serde_json::to_string(value)?)
+}
+
+// Нарушение первичного ключа означает повтор идентификатора
+fn inserted(result: rusqlite::Result<usize>, id: Uuid) -> Result<(), StorageError> {
+    match result {
+        Ok(_) => Ok(()),
+        Err(rusqlite::Error::SqliteFailure(error, _))
+            if error.code == ErrorCode::ConstraintViolation =>
+        {
+            Err(StorageError::Duplicate(id))
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+fn updated(result: rusqlite::Result<usize>, id: Uuid) -> Result<(), StorageError> {
+    match result? {
+        0 => Err(StorageError::NotFound(id)),
+        _ => Ok(()),
+    }
+}
+
+fn insert_account(connection: &Connection, account: &Account) -> Result<(), StorageError> {
+    let result = connection.execute(
+        "INSERT INTO accounts (id, data) VALUES (?1, ?2)",
+        params![account.id.to_string(), json(account)?],
+    );
+    inserted(result, account.id)
+}
+
+fn insert_operation(connection: &Connection, operation: &Operation) -> Result<(), StorageError> {
+    let result = connection.execute(
+        "INSERT INTO operations (id, account_id, date_time, receipt_id, data)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            operation.id.to_string(),
+            operation.account_id.to_string(),
+            date_text(&operation.date_time),
+            operation.receipt_id.map(|id| id.to_string()),
+            json(operation)?
+        ],
+    );
+    inserted(result, operation.id)
+}
+
+fn insert_receipt(connection: &Connection, receipt: &Receipt) -> Result<(), StorageError> {
+    let result = connection.execute(
+        "INSERT INTO receipts (id, date_time, data) VALUES (?1, ?2, ?3)",
+        params![
+            receipt.id.to_string(),
+            date_text(&receipt.date_time),
+            json(receipt)?
+        ],
+    );
+    inserted(result, receipt.id)
+}
+
+fn update_account(connection: &Connection, account: &Account) -> Result<(), StorageError> {
+    let result = connection.execute(
+        "UPDATE accounts SET data = ?2 WHERE id = ?1",
+        params![account.id.to_string(), json(account)?],
+    );
+    updated(result, account.id)
+}
+
+fn update_operation(connection: &Connection, operation: &Operation) -> Result<(), StorageError> {
+    let result = connection.execute(
+        "UPDATE operations SET account_id = ?2, date_time = ?3, receipt_id = ?4, data = ?5
+         WHERE id = ?1",
+        params![
+            operation.id.to_string(),
+            operation.account_id.to_string(),
+            date_text(&operation.date_time),
+            operation.receipt_id.map(|id| id.to_string()),
+            json(operation)?
+        ],
+    );
+    updated(result, operation.id)
+}
+
+fn update_receipt(connection: &Connection, receipt: &Receipt) -> Result<(), StorageError> {
+    let result = connection.execute(
+        "UPDATE receipts SET date_time = ?2, data = ?3 WHERE id = ?1",
+        params![
+            receipt.id.to_string(),
+            date_text(&receipt.date_time),
+            json(receipt)?
+        ],
+    );
+    updated(result, receipt.id)
+}
+
+fn delete(connection: &Connection, table: &str, id: Uuid) -> Result<(), StorageError> {
+    let changed = connection.execute(
+        &format!("DELETE FROM {} WHERE id = ?1", table),
+        [id.to_string()],
+    )?;
+    if changed == 0 {
+        return Err(StorageError::NotFound(id));
+    }
+    Ok(())
+}
+
+// Сохранённые записи таблицы: id и JSON
+fn stored(connection: &Connection, table: &str) -> Result<HashMap<Uuid, String>, StorageError> {
+    let mut statement = connection.prepare(&format!("SELECT id, data FROM {}", table))?;
+    let rows = statement
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<(String, String)>, _>>()?;
+    rows.into_iter()
+        .map(|(id, data)| {
+            let id = Uuid::parse_str(&id).map_err(|e| StorageError::Format(e.to_string()))?;
+            Ok((id, data))
+        })
+        .collect()
+}
+
+// Запись списка в таблицу по разнице с сохранённым: новые добавляются, изменённые
+// обновляются, отсутствующие в списке удаляются, остальные строки не трогаются
+fn sync<T: Serialize>(
+    connection: &Connection,
+    table: &str,
+    list: &[T],
+    id: fn(&T) -> Uuid,
+    insert: fn(&Connection, &T) -> Result<(), StorageError>,
+    update: fn(&Connection, &T) -> Result<(), StorageError>,
+) -> Result<(), StorageError> {
+    let mut stored = stored(connection, table)?;
+    for item in list.iter() {
+        match stored.remove(&id(item)) {
+            None => insert(connection, item)?,
+            Some(data) if data != json(item)? => update(connection, item)?,
+            Some(_) => {}
+        }
+    }
+    for id in stored.into_keys() {
+        delete(connection, table, id)?;
+    }
+    Ok(())
+}
+
+fn put_meta<T: Serialize>(
+    connection: &Connection,
+    key: &str,
+    value: &T,
+) -> Result<(), StorageError> {
+    connection.execute(
+        "INSERT INTO meta (key, value) VALUES (?1, ?2)
+         ON CONFLICT (key) DO UPDATE SET value = excluded.value WHERE value != excluded.value",
+        params![key, json(value)?],
+    )?;
+    Ok(())
+}
+
+impl Storage for SqliteStorage {
+    fn load(&self) -> Result<Database, StorageError> {
+        let mut db = Database::new();
+        db.accounts = self.accounts()?;
+        db.operations = self.operations(&OperationQuery::default())?;
+        db.receipts = self.receipts()?;
+        if let Some(profiles) = self.meta("csv_profiles")? {
+            db.csv_profiles = serde_json::from_str(&profiles)?;
+        }
+        if let Some(views) = self.meta("views")? {
+            db.views = serde_json::from_str(&views)?;
+        }
+        if let Some(trash) = self.meta("trash")? {
+            db.trash = serde_json::from_str(&trash)?;
+        }
+        if let Some(history) = self.meta("history")? {
+            db.history = serde_json::from_str(&history)?;
+        }
+        db.audit = self.all("audit", "rowid")?;
+        Ok(db)
+    }
+
+    // Замена содержимого одной транзакцией: при ошибке база остаётся прежней.
+    // Записываются только отличия от сохранённого, поэтому правка одной операции
+    // не переписывает всю таблицу.
+    fn save(&mut self, db: &Database) -> Result<(), StorageError> {
+        let transaction = self.connection.transaction()?;
+        sync(
+            &transaction,
+            "accounts",
+            &db.accounts,
+            |a| a.id,
+            insert_account,
+            update_account,
+        )?;
+        sync(
+            &transaction,
+            "operations",
+            &db.operations,
+            |o| o.id,
+            insert_operation,
+            update_operation,
+        )?;
+        sync(
+            &transaction,
+            "receipts",
+            &db.receipts,
+            |r| r.id,
+            insert_receipt,
+            update_receipt,
+        )?;
+        put_meta(&transaction, "csv_profiles", &db.csv_profiles)?;
+        put_meta(&transaction, "views", &db.views)?;
+        put_meta(&transaction, "trash", &db.trash)?;
+        put_meta(&transaction, "history", &db.history)?;
+        // Журнал только дополняется: записи, уже сохранённые в файле, остаются
+        for entry in db.audit.iter() {
+            transaction.execute(
+                "INSERT OR IGNORE INTO audit (id, date_time, data) VALUES (?1, ?2, ?3)",
+                params![
+                    entry.id.to_string(),
+                    date_text(&entry.date_time),
+                    json(entry)?
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn accounts(&self) -> Result<Vec<Account>, StorageError> {
+        self.all("accounts", "rowid")
+    }
+
+    fn account(&self, id: Uuid) -> Result<Option<Account>, StorageError> {
+        self.one("accounts", id)
+    }
+
+    fn insert_account(&mut self, account: &Account) -> Result<(), StorageError> {
+        insert_account(&self.connection, account)
+    }
+
+    fn update_account(&mut self, account: &Account) -> Result<(), StorageError> {
+        update_account(&self.connection, account)
+    }
+
+    fn delete_account(&mut self, id: Uuid) -> Result<(), StorageError> {
+        delete(&self.connection, "accounts", id)
+    }
+
+    fn operations(&self, query: &OperationQuery) -> Result<Vec<Operation>, StorageError> {
+        let mut statement = self.connection.prepare(
+            "SELECT data FROM operations
+             WHERE (?1 IS NULL OR account_id = ?1)
+               AND (?2 IS NULL OR date_time >= ?2)
+               AND (?3 IS NULL OR date_time < ?3)
+             ORDER BY date_time, id",
+        )?;
+        let rows = statement
+            .query_map(
+                params![
+                    query.account_id.map(|id| id.to_string()),
+                    query.from.as_ref().map(date_text),
+                    query.to.as_ref().map(date_text)
+                ],
+                |row| row.get(0),
+            )?
+            .collect::<Result<Vec<String>, _>>()?;
+        decode(rows)
+    }
+
+    fn operation(&self, id: Uuid) -> Result<Option<Operation>, StorageError> {
+        self.one("operations", id)
+    }
+
+    fn insert_operation(&mut self, operation: &Operation) -> Result<(), StorageError> {
+        insert_operation(&self.connection, operation)
+    }
+
+    fn update_operation(&mut self, operation: &Operation) -> Result<(), StorageError> {
+        update_operation(&self.connection, operation)
+    }
+
+    fn delete_operation(&mut self, id: Uuid) -> Result<(), StorageError> {
+        delete(&self.connection, "operations", id)
+    }
+
+    fn receipts(&self) -> Result<Vec<Receipt>, StorageError> {
+        self.all("receipts", "date_time, id")
+    }
+
+    fn receipt(&self, id: Uuid) -> Result<Option<Receipt>, StorageError> {
+        self.one("receipts", id)
+    }
+
+    fn insert_receipt(&mut self, receipt: &Receipt) -> Result<(), StorageError> {
+        insert_receipt(&self.connection, receipt)
+    }
+
+    fn update_receipt(&mut self, receipt: &Receipt) -> Result<(), StorageError> {
+        update_receipt(&self.connection, receipt)
+    }
+
+    fn delete_receipt(&mut self, id: Uuid) -> Result<(), StorageError> {
+        delete(&self.connection, "receipts", id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::AccountType;
+    use crate::operation::{FinanseDirection, OperationType};
+    use rust_decimal::Decimal;
+
+    fn operation(account_id: Uuid, summary: i64) -> Operation {
+        Operation {
+            id: Uuid::new_v4(),
+            date_time: NaiveDateTime::default(),
+            account_id,
+            operation_type: OperationType::Buy,
+            summary: Decimal::from(summary),
+            direction: FinanseDirection::Credit,
+            receipt_id: None,
+            reference: None,
+            counterparty: None,
+            note: None,
+            category: None,
+            transfer_id: None,
+        }
+    }
+
+    fn rowid(storage: &SqliteStorage, id: Uuid) -> Option<i64> {
+        storage
+            .connection
+            .query_row(
+                "SELECT rowid FROM operations WHERE id = ?1",
+                [id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()
+            .unwrap()
+    }
+
+    #[test]
+    fn save_writes_only_changes() {
+        let mut storage = SqliteStorage::open_in_memory().unwrap();
+        let mut db = Database::new();
+        db.accounts.push(Account {
+            id: Uuid::new_v4(),
+            name: "Main".to_string(),
+            account_type: AccountType::Account,
+            number: String::new(),
+            bik: 0,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Default::default(),
+        });
+        let account_id = db.accounts[0].id;
+        db.operations = (0..3).map(|n| operation(account_id, n)).collect();
+        storage.save(&db).unwrap();
+        let rows: Vec<Option<i64>> = db
+            .operations
+            .iter()
+            .map(|o| rowid(&storage, o.id))
+            .collect();
+
+        db.operations[1].summary = Decimal::from(10);
+        let removed = db.operations.remove(2);
+        db.operations.push(operation(account_id, 4));
+        storage.save(&db).unwrap();
+
+        // Неизменённые и изменённые строки остаются на месте, удалённая исчезает
+        assert_eq!(rowid(&storage, db.operations[0].id), rows[0]);
+        assert_eq!(rowid(&storage, db.operations[1].id), rows[1]);
+        assert_eq!(rowid(&storage, removed.id), None);
+        assert_eq!(
+            storage.operation(db.operations[1].id).unwrap(),
+            Some(db.operations[1].clone())
+        );
+        assert_eq!(storage.load().unwrap().operations.len(), 3);
+
+        // Повтор id в базе - ошибка, содержимое хранилища не меняется
+        db.operations.push(db.operations[0].clone());
+        assert!(matches!(storage.save(&db), Err(StorageError::Duplicate(_))));
+        assert_eq!(storage.load().unwrap().operations.len(), 3);
+    }
+}
@@ -0,0 +1,917 @@
+// База в памяти с индексами: поиск по UUID, операции счёта и всей базы в порядке
+// дат, остатки счетов нарастающим итогом. Изменения идут через методы Store,
+// которые поддерживают индексы и записывают изменения в историю; чтение - через
+// Deref к Database.
+
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Deref;
+use uuid::Uuid;
+
+use crate::account::Account;
+use crate::audit;
+use crate::database::Database;
+use crate::exchange::csv::CsvProfile;
+use crate::history::{self, Change, Command, Lists};
+use crate::operation::{FinanseDirection, Operation};
+use crate::receipt::Receipt;
+use crate::trash::Deleted;
+use crate::view::SavedView;
+
+type Key = (NaiveDateTime, Uuid);
+
+fn key(operation: &Operation) -> Key {
+    (operation.date_time, operation.id)
+}
+
+pub(crate) fn signed(operation: &Operation) -> Decimal {
+    match operation.direction {
+        FinanseDirection::Debet => operation.summary,
+        FinanseDirection::Credit => -operation.summary,
+    }
+}
+
+fn insert_sorted(list: &mut Vec<Key>, item: Key) -> usize {
+    let position = list.partition_point(|element| *element < item);
+    list.insert(position, item);
+    position
+}
+
+fn remove_sorted(list: &mut Vec<Key>, item: &Key) -> Option<usize> {
+    let position = list.binary_search(item).ok()?;
+    list.remove(position);
+    Some(position)
+}
+
+fn positions<T>(list: &[T], id: fn(&T) -> Uuid) -> HashMap<Uuid, usize> {
+    list.iter()
+        .enumerate()
+        .map(|(index, element)| (id(element), index))
+        .collect()
+}
+
+// Списки базы без истории и журнала
+fn lists(db: &Database) -> Lists {
+    Lists {
+        accounts: db.accounts.clone(),
+        operations: db.operations.clone(),
+        receipts: db.receipts.clone(),
+        views: db.views.clone(),
+        trash: db.trash.clone(),
+        csv_profiles: db.csv_profiles.clone(),
+    }
+}
+
+// Состояние, которое устанавливает изменение, и противоположное ему
+fn states<'a, T>(
+    before: &'a Option<T>,
+    after: &'a Option<T>,
+    forward: bool,
+) -> (Option<&'a T>, Option<&'a T>) {
+    if forward {
+        (after.as_ref(), before.as_ref())
+    } else {
+        (before.as_ref(), after.as_ref())
+    }
+}
+
+pub struct Store {
+    db: Database,
+    accounts: HashMap<Uuid, usize>,
+    operations: HashMap<Uuid, usize>,
+    receipts: HashMap<Uuid, usize>,
+    // Операции, к которым привязан чек
+    receipt_operations: HashMap<Uuid, Vec<Uuid>>,
+    by_date: Vec<Key>,
+    by_account: HashMap<Uuid, Vec<Key>>,
+    // Остатки нарастающим итогом по операциям счёта в порядке by_account. Считаются
+    // при чтении; изменение операции отбрасывает только хвост начиная с её места.
+    balances: RefCell<HashMap<Uuid, Vec<Decimal>>>,
+    // Растёт при каждом изменении; по нему сбрасываются кэши выборок
+    revision: u64,
+    // Изменения, ещё не собранные в команду истории
+    pending: Vec<Change>,
+    // Кто вносит изменения, для журнала
+    user: String,
+}
+
+impl Deref for Store {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Store {
+    pub fn new(db: Database) -> Self {
+        let mut store = Self {
+            db,
+            accounts: HashMap::new(),
+            operations: HashMap::new(),
+            receipts: HashMap::new(),
+            receipt_operations: HashMap::new(),
+            by_date: Vec::new(),
+            by_account: HashMap::new(),
+            balances: RefCell::new(HashMap::new()),
+            revision: 0,
+            pending: Vec::new(),
+            user: String::new(),
+        };
+        store.rebuild();
+        store
+    }
+
+    pub fn into_database(self) -> Database {
+        self.db
+    }
+
+    fn rebuild(&mut self) {
+        self.accounts = positions(&self.db.accounts, |a| a.id);
+        self.operations = positions(&self.db.operations, |o| o.id);
+        self.receipts = positions(&self.db.receipts, |r| r.id);
+        self.receipt_operations.clear();
+        for operation in self.db.operations.iter() {
+            if let Some(receipt_id) = operation.receipt_id {
+                self.receipt_operations
+                    .entry(receipt_id)
+                    .or_default()
+                    .push(operation.id);
+            }
+        }
+        self.by_date = self.db.operations.iter().map(key).collect();
+        self.by_date.sort();
+        self.by_account.clear();
+        for operation in self.db.operations.iter() {
+            self.by_account
+                .entry(operation.account_id)
+                .or_default()
+                .push(key(operation));
+        }
+        for list in self.by_account.values_mut() {
+            list.sort();
+        }
+        self.balances.borrow_mut().clear();
+        self.revision += 1;
+    }
+
+    // Изменение базы целиком, в обход индексов: нужно исправлению целостности,
+    // которое работает с повторами id. В историю попадают списки до и после
+    // целиком, чтобы отмена вернула повторы в точности, и записи, которые
+    // отличаются от прежних, для журнала. Индексы строятся заново.
+    // Обычные правки, в том числе массовые, идут через upsert_* и remove_*.
+    pub fn update(&mut self, change: impl FnOnce(&mut Database)) {
+        let history = std::mem::take(&mut self.db.history);
+        let audit = std::mem::take(&mut self.db.audit);
+        let before = lists(&self.db);
+        change(&mut self.db);
+        self.db.history = history;
+        self.db.audit = audit;
+        let after = lists(&self.db);
+        if before == after {
+            self.rebuild();
+            return;
+        }
+        let mut changes = Vec::new();
+        changes.extend(history::diff(
+            &before.accounts,
+            &after.accounts,
+            |a| a.id,
+            |before, after| Change::Account { before, after },
+        ));
+        changes.extend(history::diff(
+            &before.operations,
+            &after.operations,
+            |o| o.id,
+            |before, after| Change::Operation { before, after },
+        ));
+        changes.extend(history::diff(
+            &before.receipts,
+            &after.receipts,
+            |r| r.id,
+            |before, after| Change::Receipt {
+                before: before.map(Box::new),
+                after: after.map(Box::new),
+            },
+        ));
+        changes.extend(history::diff(
+            &before.views,
+            &after.views,
+            |v| v.id,
+            |before, after| Change::View { before, after },
+        ));
+        changes.extend(history::diff(
+            &before.trash,
+            &after.trash,
+            |g| g.id,
+            |before, after| Change::Trash {
+                before: before.map(Box::new),
+                after: after.map(Box::new),
+            },
+        ));
+        if before.csv_profiles != after.csv_profiles {
+            changes.push(Change::Profiles {
+                before: before.csv_profiles.clone(),
+                after: after.csv_profiles.clone(),
+            });
+        }
+        self.pending.push(Change::Lists {
+            before: Box::new(before),
+            after: Box::new(after),
+            changes,
+        });
+        self.rebuild();
+    }
+
+    // Изменения с прошлого вызова становятся одной командой истории; новая
+    // команда отбрасывает отменённые. Вызывается после каждой правки.
+    pub fn commit(&mut self) -> Option<&Command> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let command = Command::new(std::mem::take(&mut self.pending));
+        let entries = audit::entries(&self.user, &command.title, command.changes.iter().cloned());
+        self.db.audit.extend(entries);
+        self.db.history.undone.clear();
+        self.db.history.done.push(command);
+        self.db.history.done.last()
+    }
+
+    pub fn set_user(&mut self, user: &str) {
+        self.user = user.to_string();
+    }
+
+    pub fn undo(&mut self) -> bool {
+        self.commit();
+        let Some(command) = self.db.history.done.pop() else {
+            return false;
+        };
+        for change in command.changes.iter().rev() {
+            self.apply(change, false);
+        }
+        let entries = audit::entries(
+            &self.user,
+            &format!("Undo {}", command.title),
+            command.changes.iter().rev().map(Change::reversed),
+        );
+        self.db.audit.extend(entries);
+        self.db.history.undone.push(command);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.commit();
+        let Some(command) = self.db.history.undone.pop() else {
+            return false;
+        };
+        for change in command.changes.iter() {
+            self.apply(change, true);
+        }
+        let entries = audit::entries(
+            &self.user,
+            &format!("Redo {}", command.title),
+            command.changes.iter().cloned(),
+        );
+        self.db.audit.extend(entries);
+        self.db.history.done.push(command);
+        true
+    }
+
+    // База без истории, для сохранения в файл без неё
+    pub fn without_history<R>(&mut self, action: impl FnOnce(&Database) -> R) -> R {
+        let history = std::mem::take(&mut self.db.history);
+        let result = action(&self.db);
+        self.db.history = history;
+        result
+    }
+
+    // Установка состояния записи из изменения: forward - "после", иначе "до"
+    fn apply(&mut self, change: &Change, forward: bool) {
+        match change {
+            Change::Account { before, after } => match states(before, after, forward) {
+                (Some(account), _) => self.put_account(account.clone()),
+                (None, Some(account)) => {
+                    self.take_account(account.id);
+                }
+                (None, None) => {}
+            },
+            Change::Operation { before, after } => match states(before, after, forward) {
+                (Some(operation), _) => self.put_operation(operation.clone()),
+                (None, Some(operation)) => {
+                    self.take_operation(operation.id);
+                }
+                (None, None) => {}
+            },
+            Change::Receipt { before, after } => match states(before, after, forward) {
+                (Some(receipt), _) => self.put_receipt(receipt.as_ref().clone()),
+                (None, Some(receipt)) => {
+                    self.take_receipt(receipt.id);
+                }
+                (None, None) => {}
+            },
+            Change::View { before, after } => match states(before, after, forward) {
+                (Some(view), _) => self.put_view(view.clone()),
+                (None, Some(view)) => {
+                    self.take_view(view.id);
+                }
+                (None, None) => {}
+            },
+            Change::Trash { before, after } => match states(before, after, forward) {
+                (Some(group), _) => self.put_trash(group.as_ref().clone()),
+                (None, Some(group)) => {
+                    self.take_trash(group.id);
+                }
+                (None, None) => {}
+            },
+            Change::Profiles { before, after } => {
+                self.db.csv_profiles = if forward { after } else { before }.clone();
+                self.revision += 1;
+            }
+            Change::Lists { before, after, .. } => {
+                let lists = if forward { after } else { before };
+                self.db.accounts = lists.accounts.clone();
+                self.db.operations = lists.operations.clone();
+                self.db.receipts = lists.receipts.clone();
+                self.db.views = lists.views.clone();
+                self.db.trash = lists.trash.clone();
+                self.db.csv_profiles = lists.csv_profiles.clone();
+                self.rebuild();
+            }
+        }
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn account(&self, id: Uuid) -> Option<&Account> {
+        self.accounts
+            .get(&id)
+            .map(|index| &self.db.accounts[*index])
+    }
+
+    pub fn operation(&self, id: Uuid) -> Option<&Operation> {
+        self.operations
+            .get(&id)
+            .map(|index| &self.db.operations[*index])
+    }
+
+    pub fn receipt(&self, id: Uuid) -> Option<&Receipt> {
+        self.receipts
+            .get(&id)
+            .map(|index| &self.db.receipts[*index])
+    }
+
+    pub fn receipt_operation(&self, receipt_id: Uuid) -> Option<&Operation> {
+        self.receipt_operations
+            .get(&receipt_id)
+            .and_then(|list| list.first())
+            .and_then(|id| self.operation(*id))
+    }
+
+    pub fn receipt_operations(&self, receipt_id: Uuid) -> impl Iterator<Item = &Operation> {
+        self.receipt_operations
+            .get(&receipt_id)
+            .map(|list| list.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| self.operation(*id))
+    }
+
+    pub fn operation_count(&self) -> usize {
+        self.by_date.len()
+    }
+
+    // Операция по номеру в порядке дат, для постраничного показа
+    pub fn operation_at(&self, position: usize) -> Option<&Operation> {
+        let (_, id) = self.by_date.get(position)?;
+        self.operation(*id)
+    }
+
+    // Номер первой операции не раньше указанной даты в порядке дат
+    pub fn position_of_date(&self, date_time: NaiveDateTime) -> usize {
+        self.by_date
+            .partition_point(|(element, _)| *element < date_time)
+    }
+
+    pub fn operations_by_date(&self) -> impl DoubleEndedIterator<Item = &Operation> {
+        self.by_date
+            .iter()
+            .filter_map(|(_, id)| self.operation(*id))
+    }
+
+    // Операции за период [from, to)
+    pub fn operations_between(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> impl DoubleEndedIterator<Item = &Operation> {
+        let start = self.position_of_date(from);
+        let end = self.position_of_date(to).max(start);
+        self.by_date[start..end]
+            .iter()
+            .filter_map(|(_, id)| self.operation(*id))
+    }
+
+    pub fn account_operations(
+        &self,
+        account_id: Uuid,
+    ) -> impl DoubleEndedIterator<Item = &Operation> {
+        self.by_account
+            .get(&account_id)
+            .map(|list| list.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|(_, id)| self.operation(*id))
+    }
+
+    // Досчитывает кэш остатков счёта до position включительно
+    fn running(&self, account_id: Uuid, position: usize) -> Option<Decimal> {
+        let list = self.by_account.get(&account_id)?;
+        if position >= list.len() {
+            return None;
+        }
+        let mut balances = self.balances.borrow_mut();
+        let cache = balances.entry(account_id).or_default();
+        let mut total = cache.last().copied().unwrap_or_default();
+        while cache.len() <= position {
+            let (_, id) = list[cache.len()];
+            total += self.operation(id).map(signed).unwrap_or_default();
+            cache.push(total);
+        }
+        Some(cache[position])
+    }
+
+    // Остаток счёта после операции
+    pub fn running_balance(&self, operation_id: Uuid) -> Option<Decimal> {
+        let operation = self.operation(operation_id)?;
+        let list = self.by_account.get(&operation.account_id)?;
+        let position = list.binary_search(&key(operation)).ok()?;
+        self.running(operation.account_id, position)
+    }
+
+    pub fn balance(&self, account_id: Uuid) -> Decimal {
+        let count = self
+            .by_account
+            .get(&account_id)
+            .map(|list| list.len())
+            .unwrap_or_default();
+        match count {
+            0 => Decimal::ZERO,
+            _ => self.running(account_id, count - 1).unwrap_or_default(),
+        }
+    }
+
+    fn invalidate(&self, account_id: Uuid, position: usize) {
+        if let Some(cache) = self.balances.borrow_mut().get_mut(&account_id) {
+            cache.truncate(position);
+        }
+    }
+
+    fn index_operation(&mut self, operation: &Operation) {
+        insert_sorted(&mut self.by_date, key(operation));
+        let list = self.by_account.entry(operation.account_id).or_default();
+        let position = insert_sorted(list, key(operation));
+        self.invalidate(operation.account_id, position);
+        if let Some(receipt_id) = operation.receipt_id {
+            self.receipt_operations
+                .entry(receipt_id)
+                .or_default()
+                .push(operation.id);
+        }
+    }
+
+    fn unindex_operation(&mut self, operation: &Operation) {
+        remove_sorted(&mut self.by_date, &key(operation));
+        if let Some(list) = self.by_account.get_mut(&operation.account_id)
+            && let Some(position) = remove_sorted(list, &key(operation))
+        {
+            self.invalidate(operation.account_id, position);
+        }
+        if let Some(receipt_id) = operation.receipt_id
+            && let Some(list) = self.receipt_operations.get_mut(&receipt_id)
+        {
+            list.retain(|id| *id != operation.id);
+            if list.is_empty() {
+                self.receipt_operations.remove(&receipt_id);
+            }
+        }
+    }
+
+    // Добавление новой или замена операции с тем же id
+    pub fn upsert_operation(&mut self, operation: Operation) {
+        let before = self.operation(operation.id).cloned();
+        if before.as_ref() == Some(&operation) {
+            return;
+        }
+        self.pending.push(Change::Operation {
+            before,
+            after: Some(operation.clone()),
+        });
+        self.put_operation(operation);
+    }
+
+    pub fn remove_operation(&mut self, id: Uuid) -> Option<Operation> {
+        let operation = self.take_operation(id)?;
+        self.pending.push(Change::Operation {
+            before: Some(operation.clone()),
+            after: None,
+        });
+        Some(operation)
+    }
+
+    fn put_operation(&mut self, operation: Operation) {
+        self.revision += 1;
+        match self.operations.get(&operation.id).copied() {
+            Some(index) => {
+                let old = self.db.operations[index].clone();
+                self.unindex_operation(&old);
+                self.index_operation(&operation);
+                self.db.operations[index] = operation;
+            }
+            None => {
+                self.index_operation(&operation);
+                self.operations
+                    .insert(operation.id, self.db.operations.len());
+                self.db.operations.push(operation);
+            }
+        }
+    }
+
+    fn take_operation(&mut self, id: Uuid) -> Option<Operation> {
+        let index = self.operations.remove(&id)?;
+        self.revision += 1;
+        let operation = self.db.operations.swap_remove(index);
+        if let Some(moved) = self.db.operations.get(index) {
+            self.operations.insert(moved.id, index);
+        }
+        self.unindex_operation(&operation);
+        Some(operation)
+    }
+
+    pub fn upsert_account(&mut self, account: Account) {
+        let before = self.account(account.id).cloned();
+        if before.as_ref() == Some(&account) {
+            return;
+        }
+        self.pending.push(Change::Account {
+            before,
+            after: Some(account.clone()),
+        });
+        self.put_account(account);
+    }
+
+    // Операции счёта не удаляются и остаются в индексах
+    pub fn remove_account(&mut self, id: Uuid) -> Option<Account> {
+        let account = self.take_account(id)?;
+        self.pending.push(Change::Account {
+            before: Some(account.clone()),
+            after: None,
+        });
+        Some(account)
+    }
+
+    fn put_account(&mut self, account: Account) {
+        self.revision += 1;
+        match self.accounts.get(&account.id) {
+            Some(index) => self.db.accounts[*index] = account,
+            None => {
+                self.accounts.insert(account.id, self.db.accounts.len());
+                self.db.accounts.push(account);
+            }
+        }
+    }
+
+    fn take_account(&mut self, id: Uuid) -> Option<Account> {
+        let index = self.accounts.remove(&id)?;
+        self.revision += 1;
+        let account = self.db.accounts.remove(index);
+        self.accounts = positions(&self.db.accounts, |a| a.id);
+        Some(account)
+    }
+
+    pub fn upsert_view(&mut self, view: SavedView) {
+        let before = self
+            .db
+            .views
+            .iter()
+            .find(|other| other.id == view.id)
+            .cloned();
+        if before.as_ref() == Some(&view) {
+            return;
+        }
+        self.pending.push(Change::View {
+            before,
+            after: Some(view.clone()),
+        });
+        self.put_view(view);
+    }
+
+    pub fn remove_view(&mut self, id: Uuid) -> Option<SavedView> {
+        let view = self.take_view(id)?;
+        self.pending.push(Change::View {
+            before: Some(view.clone()),
+            after: None,
+        });
+        Some(view)
+    }
+
+    fn put_view(&mut self, view: SavedView) {
+        self.revision += 1;
+        match self.db.views.iter().position(|other| other.id == view.id) {
+            Some(index) => self.db.views[index] = view,
+            None => self.db.views.push(view),
+        }
+    }
+
+    fn take_view(&mut self, id: Uuid) -> Option<SavedView> {
+        let index = self.db.views.iter().position(|view| view.id == id)?;
+        self.revision += 1;
+        Some(self.db.views.remove(index))
+    }
+
+    // Профиль с тем же названием заменяется, иначе добавляется
+    pub fn upsert_csv_profile(&mut self, profile: CsvProfile) {
+        let before = self.db.csv_profiles.clone();
+        let profiles = &mut self.db.csv_profiles;
+        match profiles.iter().position(|other| other.name == profile.name) {
+            Some(index) => profiles[index] = profile,
+            None => profiles.push(profile),
+        }
+        if before != self.db.csv_profiles {
+            self.pending.push(Change::Profiles {
+                before,
+                after: self.db.csv_profiles.clone(),
+            });
+            self.revision += 1;
+        }
+    }
+
+    pub(crate) fn push_trash(&mut self, group: Deleted) {
+        self.pending.push(Change::Trash {
+            before: None,
+            after: Some(Box::new(group.clone())),
+        });
+        self.put_trash(group);
+    }
+
+    pub(crate) fn remove_trash(&mut self, id: Uuid) -> Option<Deleted> {
+        let group = self.take_trash(id)?;
+        self.pending.push(Change::Trash {
+            before: Some(Box::new(group.clone())),
+            after: None,
+        });
+        Some(group)
+    }
+
+    fn put_trash(&mut self, group: Deleted) {
+        self.revision += 1;
+        match self.db.trash.iter().position(|other| other.id == group.id) {
+            Some(index) => self.db.trash[index] = group,
+            None => self.db.trash.push(group),
+        }
+    }
+
+    fn take_trash(&mut self, id: Uuid) -> Option<Deleted> {
+        let index = self.db.trash.iter().position(|group| group.id == id)?;
+        self.revision += 1;
+        Some(self.db.trash.remove(index))
+    }
+
+    pub fn upsert_receipt(&mut self, receipt: Receipt) {
+        let before = self.receipt(receipt.id).cloned();
+        if before.as_ref() == Some(&receipt) {
+            return;
+        }
+        self.pending.push(Change::Receipt {
+            before: before.map(Box::new),
+            after: Some(Box::new(receipt.clone())),
+        });
+        self.put_receipt(receipt);
+    }
+
+    pub fn remove_receipt(&mut self, id: Uuid) -> Option<Receipt> {
+        let receipt = self.take_receipt(id)?;
+        self.pending.push(Change::Receipt {
+            before: Some(Box::new(receipt.clone())),
+            after: None,
+        });
+        Some(receipt)
+    }
+
+    fn put_receipt(&mut self, receipt: Receipt) {
+        self.revision += 1;
+        match self.receipts.get(&receipt.id) {
+            Some(index) => self.db.receipts[*index] = receipt,
+            None => {
+                self.receipts.insert(receipt.id, self.db.receipts.len());
+                self.db.receipts.push(receipt);
+            }
+        }
+    }
+
+    fn take_receipt(&mut self, id: Uuid) -> Option<Receipt> {
+        let index = self.receipts.remove(&id)?;
+        self.revision += 1;
+        let receipt = self.db.receipts.swap_remove(index);
+        if let Some(moved) = self.db.receipts.get(index) {
+            self.receipts.insert(moved.id, index);
+        }
+        Some(receipt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operation::OperationType;
+    use crate::view::TableView;
+    use chrono::NaiveDate;
+    use rust_decimal::dec;
+
+    fn date(day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap().into()
+    }
+
+    // Приход (положительная сумма) или расход по счёту
+    fn operation(account_id: Uuid, day: u32, amount: Decimal) -> Operation {
+        Operation {
+            id: Uuid::new_v4(),
+            date_time: date(day),
+            account_id,
+            operation_type: OperationType::Buy,
+            summary: amount.abs(),
+            direction: if amount.is_sign_negative() {
+                FinanseDirection::Credit
+            } else {
+                FinanseDirection::Debet
+            },
+            receipt_id: None,
+            reference: None,
+            counterparty: None,
+            note: None,
+            category: None,
+            transfer_id: None,
+        }
+    }
+
+    // Индекс id -> позиция совпадает со списком базы
+    fn assert_indexed(store: &Store) {
+        assert_eq!(store.operations.len(), store.db.operations.len());
+        for (index, operation) in store.db.operations.iter().enumerate() {
+            assert_eq!(store.operations[&operation.id], index);
+        }
+        assert_eq!(store.receipts.len(), store.db.receipts.len());
+        for (index, receipt) in store.db.receipts.iter().enumerate() {
+            assert_eq!(store.receipts[&receipt.id], index);
+        }
+        assert_eq!(store.operation_count(), store.db.operations.len());
+    }
+
+    #[test]
+    fn index_after_swap_remove() {
+        let mut store = Store::new(Database::new());
+        let account = Uuid::new_v4();
+        let mut receipts = Vec::new();
+        for day in 1..=3 {
+            let mut receipt = Receipt::empty_new();
+            receipt.id = Uuid::new_v4();
+            let mut element = operation(account, day, dec!(-10));
+            element.receipt_id = Some(receipt.id);
+            store.upsert_receipt(receipt.clone());
+            store.upsert_operation(element);
+            receipts.push(receipt);
+        }
+        store.commit();
+        let first = store.db.operations[0].clone();
+        let last = store.db.operations[2].clone();
+
+        // Последняя операция переезжает на место удалённой первой
+        assert_eq!(store.remove_operation(first.id), Some(first.clone()));
+        assert_eq!(store.db.operations[0], last);
+        assert_eq!(store.operation(last.id), Some(&last));
+        assert_eq!(store.operation(first.id), None);
+        assert_eq!(store.receipt_operation(receipts[0].id), None);
+        assert_eq!(store.receipt_operation(receipts[2].id), Some(&last));
+        assert!(store.remove_receipt(receipts[0].id).is_some());
+        assert_eq!(store.receipt(receipts[2].id), Some(&receipts[2]));
+        assert_indexed(&store);
+
+        store.commit();
+        store.undo();
+        assert_indexed(&store);
+        assert_eq!(store.operation(first.id), Some(&first));
+        assert_eq!(store.receipt_operation(receipts[0].id), Some(&first));
+    }
+
+    #[test]
+    fn positions_by_date() {
+        let mut store = Store::new(Database::new());
+        let account = Uuid::new_v4();
+        for day in [3, 1, 2] {
+            store.upsert_operation(operation(account, day, dec!(10)));
+        }
+        let days: Vec<NaiveDateTime> = (0..3)
+            .map(|position| store.operation_at(position).unwrap().date_time)
+            .collect();
+        assert_eq!(days, vec![date(1), date(2), date(3)]);
+        assert_eq!(store.operation_at(3), None);
+
+        assert_eq!(store.position_of_date(date(1)), 0);
+        assert_eq!(store.position_of_date(date(2)), 1);
+        assert_eq!(
+            store.position_of_date(date(2) + chrono::Duration::hours(1)),
+            2
+        );
+        assert_eq!(store.position_of_date(date(10)), 3);
+        assert_eq!(store.operations_between(date(2), date(3)).count(), 1);
+    }
+
+    #[test]
+    fn running_balance_after_changes() {
+        let mut store = Store::new(Database::new());
+        let account = Uuid::new_v4();
+        let mut income = operation(account, 1, dec!(100));
+        let expense = operation(account, 2, dec!(-30));
+        let last = operation(account, 3, dec!(50));
+        for element in [&income, &expense, &last] {
+            store.upsert_operation(element.clone());
+        }
+        store.commit();
+        // Кэш остатков заполнен до последней операции
+        assert_eq!(store.running_balance(last.id), Some(dec!(120)));
+        assert_eq!(store.running_balance(expense.id), Some(dec!(70)));
+
+        income.summary = dec!(200);
+        store.upsert_operation(income.clone());
+        store.commit();
+        assert_eq!(store.running_balance(last.id), Some(dec!(220)));
+
+        store.remove_operation(expense.id);
+        store.commit();
+        assert_eq!(store.running_balance(last.id), Some(dec!(250)));
+        assert_eq!(store.running_balance(expense.id), None);
+        assert_eq!(store.balance(account), dec!(250));
+
+        store.undo();
+        assert_eq!(store.running_balance(last.id), Some(dec!(220)));
+        store.undo();
+        assert_eq!(store.running_balance(last.id), Some(dec!(120)));
+        assert_eq!(store.balance(account), dec!(120));
+        store.redo();
+        assert_eq!(store.balance(account), dec!(220));
+    }
+
+    fn view(name: &str, query: &str) -> SavedView {
+        SavedView {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            query: query.to_string(),
+            table: TableView::default(),
+        }
+    }
+
+    #[test]
+    fn view_changes() {
+        let mut store = Store::new(Database::new());
+        let mut saved = view("Еда", "category:еда");
+        store.upsert_view(saved.clone());
+        assert_eq!(store.commit().unwrap().title, "Create view");
+        // Та же запись не даёт изменения
+        store.upsert_view(saved.clone());
+        assert!(store.commit().is_none());
+
+        saved.query = "category:кафе".to_string();
+        store.upsert_view(saved.clone());
+        store.commit();
+        assert!(store.undo());
+        assert_eq!(store.views[0].query, "category:еда");
+        assert!(store.redo());
+        assert_eq!(store.views[0].query, "category:кафе");
+
+        assert_eq!(store.remove_view(saved.id), Some(saved));
+        store.commit();
+        assert!(store.views.is_empty());
+        store.undo();
+        assert_eq!(store.views.len(), 1);
+    }
+
+    #[test]
+    fn csv_profile_replaced_by_name() {
+        let mut store = Store::new(Database::new());
+        let mut profile = CsvProfile::new();
+        profile.name = "Банк".to_string();
+        store.upsert_csv_profile(profile.clone());
+        store.commit();
+        profile.skip_rows = 3;
+        store.upsert_csv_profile(profile.clone());
+        store.commit();
+        assert_eq!(store.csv_profiles, vec![profile]);
+        store.undo();
+        assert_eq!(store.csv_profiles[0].skip_rows, 1);
+        store.undo();
+        assert!(store.csv_profiles.is_empty());
+    }
+}
@@ -0,0 +1,452 @@
+// Удаление записей с сохранением связей и корзина. Удалённое не пропадает, а
+// переносится в корзину базы одной группой вместе со всем, что удалено
+// каскадом, и может быть восстановлено, пока корзину не очистили. Связи,
+// которые пришлось разорвать (чек операции, пара перевода), запоминаются в
+// группе и восстанавливаются вместе с ней.
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::account::Account;
+use crate::operation::Operation;
+use crate::receipt::Receipt;
+use crate::store::Store;
+
+#[derive(Debug, PartialEq)]
+pub enum DeleteError {
+    NotFound(Uuid),
+    // У счёта есть операции, нужен перенос или каскадное удаление
+    HasOperations(usize),
+    SameAccount,
+    // Восстановление: счёт операции удалён, запись с тем же id уже есть
+    MissingAccount(Uuid),
+    Duplicate(Uuid),
+}
+
+impl fmt::Display for DeleteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeleteError::NotFound(id) => write!(f, "Запись {} не найдена", id),
+            DeleteError::HasOperations(count) => {
+                write!(f, "У счёта есть операции: {}", count)
+            }
+            DeleteError::SameAccount => write!(f, "Операции переносятся на тот же счёт"),
+            DeleteError::MissingAccount(id) => write!(f, "Счёт {} не существует", id),
+            DeleteError::Duplicate(id) => write!(f, "Запись {} уже существует", id),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Item {
+    Account(Account),
+    Operation(Operation),
+    // Чек заметно больше остальных записей
+    Receipt(Box<Receipt>),
+}
+
+impl Item {
+    pub fn id(&self) -> Uuid {
+        match self {
+            Item::Account(account) => account.id,
+            Item::Operation(operation) => operation.id,
+            Item::Receipt(receipt) => receipt.id,
+        }
+    }
+}
+
+// Разорванная связь оставшейся записи с удалённой
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Link {
+    // Операция ссылалась на удалённый чек
+    Receipt { operation: Uuid, receipt: Uuid },
+    // Вторая операция перевода ссылалась на удалённую
+    Transfer { operation: Uuid, pair: Uuid },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Deleted {
+    pub id: Uuid,
+    pub date_time: NaiveDateTime,
+    pub items: Vec<Item>,
+    #[serde(default)]
+    pub links: Vec<Link>,
+}
+
+impl Deleted {
+    pub(crate) fn new() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            date_time: Local::now().naive_local(),
+            items: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+}
+
+// Что делать с операциями удаляемого счёта
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AccountOperations {
+    // Не удалять счёт с операциями
+    Block,
+    // Перенести операции на другой счёт
+    Reassign(Uuid),
+    // Удалить операции вместе со счётом
+    Cascade,
+}
+
+// Разрывает ссылку второй операции перевода на удаляемую
+fn unlink_transfer(store: &mut Store, operation: &Operation, group: &mut Deleted) {
+    let Some(pair_id) = operation.transfer_id else {
+        return;
+    };
+    if let Some(pair) = store.operation(pair_id)
+        && pair.transfer_id == Some(operation.id)
+    {
+        let mut pair = pair.clone();
+        pair.transfer_id = None;
+        store.upsert_operation(pair);
+        group.links.push(Link::Transfer {
+            operation: pair_id,
+            pair: operation.id,
+        });
+    }
+}
+
+fn take_operation(store: &mut Store, id: Uuid, with_receipt: bool, group: &mut Deleted) {
+    let Some(operation) = store.remove_operation(id) else {
+        return;
+    };
+    unlink_transfer(store, &operation, group);
+    if with_receipt
+        && let Some(receipt_id) = operation.receipt_id
+        && store.receipt_operation(receipt_id).is_none()
+        && let Some(receipt) = store.remove_receipt(receipt_id)
+    {
+        group.items.push(Item::Receipt(Box::new(receipt)));
+    }
+    group.items.push(Item::Operation(operation));
+}
+
+fn finish(store: &mut Store, group: Deleted) -> Uuid {
+    let id = group.id;
+    store.push_trash(group);
+    id
+}
+
+// Удаление операции; её чек удаляется вместе с ней, если with_receipt и на него
+// не ссылаются другие операции, иначе остаётся без операции
+pub fn delete_operation(
+    store: &mut Store,
+    id: Uuid,
+    with_receipt: bool,
+) -> Result<Uuid, DeleteError> {
+    if store.operation(id).is_none() {
+        return Err(DeleteError::NotFound(id));
+    }
+    let mut group = Deleted::new();
+    take_operation(store, id, with_receipt, &mut group);
+    Ok(finish(store, group))
+}
+
+// Удаление чека; операции, ссылавшиеся на него, остаются без чека
+pub fn delete_receipt(store: &mut Store, id: Uuid) -> Result<Uuid, DeleteError> {
+    let receipt = store.remove_receipt(id).ok_or(DeleteError::NotFound(id))?;
+    let mut group = Deleted::new();
+    let linked: Vec<Operation> = store.receipt_operations(id).cloned().collect();
+    for mut operation in linked {
+        group.links.push(Link::Receipt {
+            operation: operation.id,
+            receipt: id,
+        });
+        operation.receipt_id = None;
+        store.upsert_operation(operation);
+    }
+    group.items.push(Item::Receipt(Box::new(receipt)));
+    Ok(finish(store, group))
+}
+
+// Удаление счёта; каскадом удаляются и чеки его операций
+pub fn delete_account(
+    store: &mut Store,
+    id: Uuid,
+    operations: AccountOperations,
+) -> Result<Uuid, DeleteError> {
+    if store.account(id).is_none() {
+        return Err(DeleteError::NotFound(id));
+    }
+    let ids: Vec<Uuid> = store
+        .account_operations(id)
+        .map(|operation| operation.id)
+        .collect();
+    let mut group = Deleted::new();
+    match operations {
+        AccountOperations::Block if !ids.is_empty() => {
+            return Err(DeleteError::HasOperations(ids.len()));
+        }
+        AccountOperations::Block => {}
+        AccountOperations::Reassign(target) if target == id => {
+            return Err(DeleteError::SameAccount);
+        }
+        AccountOperations::Reassign(target) => {
+            if store.account(target).is_none() {
+                return Err(DeleteError::NotFound(target));
+            }
+            for operation_id in ids {
+                let mut operation = store
+                    .operation(operation_id)
+                    .ok_or(DeleteError::NotFound(operation_id))?
+                    .clone();
+                operation.account_id = target;
+                store.upsert_operation(operation);
+            }
+        }
+        AccountOperations::Cascade => {
+            for operation_id in ids {
+                take_operation(store, operation_id, true, &mut group);
+            }
+        }
+    }
+    let account = store.remove_account(id).ok_or(DeleteError::NotFound(id))?;
+    group.items.insert(0, Item::Account(account));
+    Ok(finish(store, group))
+}
+
+// Возврат группы из корзины. Группа восстанавливается целиком или не
+// восстанавливается совсем.
+pub fn restore(store: &mut Store, id: Uuid) -> Result<(), DeleteError> {
+    let group = store
+        .trash
+        .iter()
+        .find(|group| group.id == id)
+        .ok_or(DeleteError::NotFound(id))?
+        .clone();
+    for item in group.items.iter() {
+        let exists = match item {
+            Item::Account(account) => store.account(account.id).is_some(),
+            Item::Operation(operation) => store.operation(operation.id).is_some(),
+            Item::Receipt(receipt) => store.receipt(receipt.id).is_some(),
+        };
+        if exists {
+            return Err(DeleteError::Duplicate(item.id()));
+        }
+        if let Item::Operation(operation) = item
+            && store.account(operation.account_id).is_none()
+            && !group
+                .items
+                .iter()
+                .any(|other| matches!(other, Item::Account(a) if a.id == operation.account_id))
+        {
+            return Err(DeleteError::MissingAccount(operation.account_id));
+        }
+    }
+    for item in group.items {
+        match item {
+            Item::Account(account) => store.upsert_account(account),
+            Item::Operation(operation) => store.upsert_operation(operation),
+            Item::Receipt(receipt) => store.upsert_receipt(*receipt),
+        }
+    }
+    // Связи восстанавливаются, только если оставшаяся запись их не сменила
+    for link in group.links {
+        match link {
+            Link::Receipt { operation, receipt } => {
+                if let Some(found) = store.operation(operation)
+                    && found.receipt_id.is_none()
+                {
+                    let mut found = found.clone();
+                    found.receipt_id = Some(receipt);
+                    store.upsert_operation(found);
+                }
+            }
+            Link::Transfer { operation, pair } => {
+                if let Some(found) = store.operation(operation)
+                    && found.transfer_id.is_none()
+                {
+                    let mut found = found.clone();
+                    found.transfer_id = Some(pair);
+                    store.upsert_operation(found);
+                }
+            }
+        }
+    }
+    store.remove_trash(id);
+    Ok(())
+}
+
+// Окончательное удаление группы; None - очистка всей корзины
+pub fn purge(store: &mut Store, id: Option<Uuid>) {
+    let ids: Vec<Uuid> = match id {
+        Some(id) => vec![id],
+        None => store.trash.iter().map(|group| group.id).collect(),
+    };
+    for id in ids {
+        store.remove_trash(id);
+    }
+}
+
+// Чеки, на которые не ссылается ни одна операция
+pub fn orphan_receipts(store: &Store) -> Vec<Uuid> {
+    store
+        .receipts
+        .iter()
+        .filter(|receipt| store.receipt_operation(receipt.id).is_none())
+        .map(|receipt| receipt.id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::AccountType;
+    use crate::database::Database;
+    use crate::operation::{FinanseDirection, OperationType};
+    use crate::receipt::Currency;
+    use chrono::NaiveDate;
+    use rust_decimal::dec;
+
+    fn account(name: &str) -> Account {
+        Account {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            account_type: AccountType::Account,
+            number: String::new(),
+            bik: 0,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Currency::Rub,
+        }
+    }
+
+    fn operation(account_id: Uuid) -> Operation {
+        Operation {
+            id: Uuid::new_v4(),
+            date_time: NaiveDate::from_ymd_opt(2026, 3, 1).unwrap().into(),
+            account_id,
+            operation_type: OperationType::Buy,
+            summary: dec!(100),
+            direction: FinanseDirection::Credit,
+            receipt_id: None,
+            reference: None,
+            counterparty: None,
+            note: None,
+            category: None,
+            transfer_id: None,
+        }
+    }
+
+    // Счёт с операцией по чеку и переводом на второй счёт
+    fn store() -> (Store, Account, Operation, Operation, Receipt) {
+        let main = account("Основной");
+        let other = account("Второй");
+        let mut receipt = Receipt::empty_new();
+        receipt.id = Uuid::new_v4();
+        let mut bought = operation(main.id);
+        bought.receipt_id = Some(receipt.id);
+        let mut sent = operation(main.id);
+        let mut received = operation(other.id);
+        sent.transfer_id = Some(received.id);
+        received.transfer_id = Some(sent.id);
+        received.direction = FinanseDirection::Debet;
+        let mut db = Database::new();
+        db.accounts = vec![main.clone(), other];
+        db.operations = vec![bought.clone(), sent, received.clone()];
+        db.receipts = vec![receipt.clone()];
+        (Store::new(db), main, bought, received, receipt)
+    }
+
+    #[test]
+    fn cascade_and_restore() {
+        let (mut store, main, bought, received, receipt) = store();
+        let group = delete_account(&mut store, main.id, AccountOperations::Cascade).unwrap();
+        assert_eq!(store.accounts.len(), 1);
+        assert_eq!(store.operations.len(), 1);
+        assert!(store.receipts.is_empty());
+        // Вторая сторона перевода остаётся без пары
+        assert_eq!(store.operation(received.id).unwrap().transfer_id, None);
+        assert_eq!(store.trash[0].items.len(), 4);
+
+        restore(&mut store, group).unwrap();
+        assert!(store.trash.is_empty());
+        assert_eq!(store.account(main.id), Some(&main));
+        assert_eq!(store.operation(bought.id), Some(&bought));
+        assert_eq!(store.receipt(receipt.id), Some(&receipt));
+        assert_eq!(
+            store.operation(received.id).unwrap().transfer_id,
+            received.transfer_id
+        );
+        assert_eq!(store.operations.len(), 3);
+    }
+
+    #[test]
+    fn restore_receipt_links() {
+        let (mut store, _, bought, _, receipt) = store();
+        let group = delete_receipt(&mut store, receipt.id).unwrap();
+        assert_eq!(store.operation(bought.id).unwrap().receipt_id, None);
+        restore(&mut store, group).unwrap();
+        assert_eq!(
+            store.operation(bought.id).unwrap().receipt_id,
+            Some(receipt.id)
+        );
+    }
+
+    #[test]
+    fn restore_conflicts() {
+        let (mut store, main, bought, _, _) = store();
+        let group = delete_operation(&mut store, bought.id, false).unwrap();
+        // Чек остаётся без операции
+        assert_eq!(store.receipts.len(), 1);
+        store.upsert_operation(bought.clone());
+        assert_eq!(
+            restore(&mut store, group),
+            Err(DeleteError::Duplicate(bought.id))
+        );
+        // Неудачное восстановление ничего не меняет
+        assert_eq!(store.trash.len(), 1);
+
+        store.remove_operation(bought.id);
+        let ids: Vec<Uuid> = store.account_operations(main.id).map(|o| o.id).collect();
+        for id in ids {
+            delete_operation(&mut store, id, true).unwrap();
+        }
+        delete_account(&mut store, main.id, AccountOperations::Block).unwrap();
+        assert_eq!(
+            restore(&mut store, group),
+            Err(DeleteError::MissingAccount(main.id))
+        );
+        assert_eq!(
+            restore(&mut store, Uuid::nil()),
+            Err(DeleteError::NotFound(Uuid::nil()))
+        );
+    }
+
+    #[test]
+    fn delete_errors() {
+        let (mut store, main, _, _, _) = store();
+        let missing = Uuid::new_v4();
+        assert_eq!(
+            delete_operation(&mut store, missing, true),
+            Err(DeleteError::NotFound(missing))
+        );
+        assert_eq!(
+            delete_receipt(&mut store, missing),
+            Err(DeleteError::NotFound(missing))
+        );
+        assert_eq!(
+            delete_account(&mut store, main.id, AccountOperations::Block),
+            Err(DeleteError::HasOperations(2))
+        );
+        assert_eq!(
+            delete_account(&mut store, main.id, AccountOperations::Reassign(main.id)),
+            Err(DeleteError::SameAccount)
+        );
+        assert_eq!(
+            delete_account(&mut store, main.id, AccountOperations::Reassign(missing)),
+            Err(DeleteError::NotFound(missing))
+        );
+        // Ничего из этого не попало в историю
+        assert!(store.commit().is_none());
+    }
+}
@@ -0,0 +1,350 @@
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::fmt;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::operation::{FinanseDirection, Operation};
+use crate::receipt::Receipt;
+
+// Нарушение внутренней согласованности чека
+#[derive(Debug, PartialEq, Clone)]
+pub enum Violation {
+    SubjectSum {
+        index: usize,
+        summary: Decimal,
+        computed: Decimal,
+    },
+    SubjectsTotal {
+        subjects: Decimal,
+        summary: Decimal,
+    },
+    PaymentsTotal {
+        payments: Decimal,
+        summary: Decimal,
+    },
+    Vat {
+        entered: Decimal,
+        computed: Decimal,
+    },
+    OperationSum {
+        operation: Decimal,
+        summary: Decimal,
+    },
+    OperationDirection {
+        operation: FinanseDirection,
+        expected: FinanseDirection,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::SubjectSum {
+                index,
+                summary,
+                computed,
+            } => write!(
+                f,
+                "Позиция {}: сумма {} не равна цене × количество = {}",
+                index, summary, computed
+            ),
+            Violation::SubjectsTotal { subjects, summary } => write!(
+                f,
+                "Сумма позиций {} не равна итогу чека {}",
+                subjects, summary
+            ),
+            Violation::PaymentsTotal { payments, summary } => write!(
+                f,
+                "Сумма оплат {} не равна итогу чека {}",
+                payments, summary
+            ),
+            Violation::Vat { entered, computed } => write!(
+                f,
+                "НДС в чеке {} не равен рассчитанному {}",
+                entered, computed
+            ),
+            Violation::OperationSum { operation, summary } => write!(
+                f,
+                "Сумма операции {} не равна итогу чека {}",
+                operation, summary
+            ),
+            Violation::OperationDirection {
+                operation,
+                expected,
+            } => write!(
+                f,
+                "Направление операции {:?} не соответствует типу чека, ожидается {:?}",
+                operation, expected
+            ),
+        }
+    }
+}
+
+pub fn validate_receipt(receipt: &Receipt, operation: Option<&Operation>) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    for (index, subject) in receipt.subjects.iter().enumerate() {
+        if !subject.summary_matches() {
+            violations.push(Violation::SubjectSum {
+                index,
+                summary: subject.summary,
+                computed: subject.computed_summary(),
+            });
+        }
+    }
+
+    let subjects: Decimal = receipt.subjects.iter().map(|s| s.summary).sum();
+    if subjects != receipt.summary {
+        violations.push(Violation::SubjectsTotal {
+            subjects,
+            summary: receipt.summary,
+        });
+    }
+
+    // Разбивку по видам оплаты печатают не все чеки; если её нет, сверять не с чем
+    let payments = [
+        receipt.cash,
+        receipt.cashless,
+        receipt.prepayment,
+        receipt.postpayment,
+        receipt.in_kind,
+    ];
+    let entered = payments.iter().any(|payment| payment.is_some());
+    let payments: Decimal = payments.iter().flatten().sum();
+    if entered && payments != receipt.summary {
+        violations.push(Violation::PaymentsTotal {
+            payments,
+            summary: receipt.summary,
+        });
+    }
+
+    if !receipt.vat_matches() {
+        violations.push(Violation::Vat {
+            entered: receipt.vat.unwrap_or(Decimal::ZERO),
+            computed: receipt.computed_vat(),
+        });
+    }
+
+    if let Some(operation) = operation {
+        violations.extend(validate_operation(receipt, operation));
+    }
+
+    violations
+}
+
+// Согласованность операции с привязанным к ней чеком
+pub fn validate_operation(receipt: &Receipt, operation: &Operation) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    if operation.summary != receipt.summary {
+        violations.push(Violation::OperationSum {
+            operation: operation.summary,
+            summary: receipt.summary,
+        });
+    }
+    violations.extend(check_direction(receipt, operation));
+    violations
+}
+
+fn check_direction(receipt: &Receipt, operation: &Operation) -> Option<Violation> {
+    let expected = receipt.calculation_type.direction();
+    (operation.direction != expected).then(|| Violation::OperationDirection {
+        operation: operation.direction.clone(),
+        expected,
+    })
+}
+
+// Проверка всех чеков базы, нарушения возвращаются вместе с идентификатором чека.
+// Один чек может быть оплачен несколькими операциями: с суммой чека сверяется
+// их общая сумма, направление - у каждой.
+pub fn check_database(db: &Database) -> Vec<(Uuid, Violation)> {
+    let mut linked: HashMap<Uuid, Vec<&Operation>> = HashMap::new();
+    for operation in db.operations.iter() {
+        if let Some(receipt) = operation.receipt_id {
+            linked.entry(receipt).or_default().push(operation);
+        }
+    }
+    let mut result = Vec::new();
+    for receipt in db.receipts.iter() {
+        let mut violations = validate_receipt(receipt, None);
+        if let Some(operations) = linked.get(&receipt.id) {
+            let total: Decimal = operations.iter().map(|operation| operation.summary).sum();
+            if total != receipt.summary {
+                violations.push(Violation::OperationSum {
+                    operation: total,
+                    summary: receipt.summary,
+                });
+            }
+            for operation in operations.iter() {
+                violations.extend(check_direction(receipt, operation));
+            }
+        }
+        for violation in violations {
+            result.push((receipt.id, violation));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operation::OperationType;
+    use crate::receipt::{Subject, VatType};
+    use chrono::NaiveDateTime;
+    use rust_decimal::dec;
+
+    fn receipt() -> Receipt {
+        let mut subject = Subject::empty();
+        subject.count = dec!(2);
+        subject.price = dec!(60);
+        subject.summary = dec!(120);
+        subject.vat_type = VatType::Vat20;
+        let mut receipt = Receipt::empty_new();
+        receipt.id = Uuid::new_v4();
+        receipt.subjects.push(subject);
+        receipt.summary = dec!(120);
+        receipt.vat = Some(dec!(20));
+        receipt
+    }
+
+    fn operation(receipt: &Receipt, summary: Decimal) -> Operation {
+        Operation {
+            id: Uuid::new_v4(),
+            date_time: NaiveDateTime::default(),
+            account_id: Uuid::new_v4(),
+            operation_type: OperationType::Buy,
+            summary,
+            direction: receipt.calculation_type.direction(),
+            receipt_id: Some(receipt.id),
+            reference: None,
+            counterparty: None,
+            note: None,
+            category: None,
+            transfer_id: None,
+        }
+    }
+
+    #[test]
+    fn consistent_receipt() {
+        let receipt = receipt();
+        let operation = operation(&receipt, dec!(120));
+        assert_eq!(validate_receipt(&receipt, Some(&operation)), Vec::new());
+    }
+
+    #[test]
+    fn subjects_and_vat() {
+        let mut receipt = receipt();
+        receipt.subjects[0].summary = dec!(100);
+        receipt.vat = Some(dec!(16.66));
+        assert_eq!(
+            validate_receipt(&receipt, None),
+            vec![
+                Violation::SubjectSum {
+                    index: 0,
+                    summary: dec!(100),
+                    computed: dec!(120),
+                },
+                Violation::SubjectsTotal {
+                    subjects: dec!(100),
+                    summary: dec!(120),
+                },
+                Violation::Vat {
+                    entered: dec!(16.66),
+                    computed: dec!(16.67),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn payments_checked_only_when_entered() {
+        let mut receipt = receipt();
+        assert_eq!(validate_receipt(&receipt, None), Vec::new());
+
+        receipt.cashless = Some(dec!(100));
+        assert_eq!(
+            validate_receipt(&receipt, None),
+            vec![Violation::PaymentsTotal {
+                payments: dec!(100),
+                summary: dec!(120),
+            }]
+        );
+        receipt.cash = Some(dec!(20));
+        assert_eq!(validate_receipt(&receipt, None), Vec::new());
+    }
+
+    #[test]
+    fn operation_mismatch() {
+        let receipt = receipt();
+        let mut operation = operation(&receipt, dec!(121));
+        operation.direction = FinanseDirection::Debet;
+        assert_eq!(
+            validate_receipt(&receipt, Some(&operation)),
+            vec![
+                Violation::OperationSum {
+                    operation: dec!(121),
+                    summary: dec!(120),
+                },
+                Violation::OperationDirection {
+                    operation: FinanseDirection::Debet,
+                    expected: FinanseDirection::Credit,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn database_checks_every_linked_operation() {
+        let receipt = receipt();
+        let mut db = Database::new();
+        db.operations.push(operation(&receipt, dec!(120)));
+        let mut refund = operation(&receipt, dec!(60));
+        refund.direction = FinanseDirection::Debet;
+        db.operations.push(refund);
+        db.receipts.push(receipt.clone());
+        assert_eq!(
+            check_database(&db),
+            vec![
+                (
+                    receipt.id,
+                    Violation::OperationSum {
+                        operation: dec!(180),
+                        summary: dec!(120),
+                    }
+                ),
+                (
+                    receipt.id,
+                    Violation::OperationDirection {
+                        operation: FinanseDirection::Debet,
+                        expected: FinanseDirection::Credit,
+                    }
+                ),
+            ]
+        );
+
+        // Чек без операции проверяется только сам по себе
+        db.operations.clear();
+        assert_eq!(check_database(&db), Vec::new());
+    }
+
+    #[test]
+    fn split_payment() {
+        let receipt = receipt();
+        let mut db = Database::new();
+        db.operations.push(operation(&receipt, dec!(70)));
+        db.operations.push(operation(&receipt, dec!(50)));
+        db.receipts.push(receipt.clone());
+        assert_eq!(check_database(&db), Vec::new());
+    }
+
+    #[test]
+    fn integer_operation_summary_in_old_files() {
+        let receipt = receipt();
+        let mut json = serde_json::to_value(operation(&receipt, dec!(120))).unwrap();
+        json["summary"] = serde_json::json!(120);
+        let operation: Operation = serde_json::from_value(json).unwrap();
+        assert_eq!(operation.summary, dec!(120));
+        assert_eq!(validate_operation(&receipt, &operation), Vec::new());
+    }
+}
@@ -0,0 +1,337 @@
+// Настройка таблицы операций: порядок и видимость столбцов, сортировка и отбор
+// по столбцам. Строки считаются по индексированной базе заново только при
+// изменении базы или настройки, а не в каждом кадре. Сохранённые виды хранятся
+// в базе и служат источником для экспорта и отчётов.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use strum::{EnumIter, IntoEnumIterator};
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::money::parse_decimal;
+use crate::operation::Operation;
+use crate::search::{Query, QueryError};
+use crate::store::{Store, signed};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy, EnumIter)]
+pub enum Field {
+    Account,
+    Date,
+    Type,
+    Amount,
+    Category,
+    Receipt,
+    Note,
+    Balance,
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let title = match self {
+            Field::Account => "Account",
+            Field::Date => "Date",
+            Field::Type => "Type",
+            Field::Amount => "Amount",
+            Field::Category => "Category",
+            Field::Receipt => "Receipt",
+            Field::Note => "Note",
+            Field::Balance => "Balance",
+        };
+        write!(f, "{}", title)
+    }
+}
+
+impl Field {
+    // Значение ячейки в том виде, в каком оно показывается в таблице
+    pub fn text(&self, store: &Store, operation: &Operation) -> String {
+        match self {
+            Field::Account => store
+                .account(operation.account_id)
+                .map(|account| account.name.clone())
+                .unwrap_or_else(|| "?".to_string()),
+            Field::Date => operation.date_time.format("%d.%m.%Y %H:%M").to_string(),
+            Field::Type => format!("{:?}", operation.operation_type),
+            Field::Amount => signed(operation).to_string(),
+            Field::Category => operation.category.clone().unwrap_or_default(),
+            Field::Receipt => match operation.receipt_id {
+                Some(id) => store
+                    .receipt(id)
+                    .and_then(|receipt| receipt.place.clone())
+                    .unwrap_or_else(|| "Receipt".to_string()),
+                None => String::new(),
+            },
+            Field::Note => operation.note.clone().unwrap_or_default(),
+            Field::Balance => store
+                .running_balance(operation.id)
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+
+    fn number(&self, store: &Store, operation: &Operation) -> Option<Decimal> {
+        match self {
+            Field::Amount => Some(signed(operation)),
+            Field::Balance => store.running_balance(operation.id),
+            _ => None,
+        }
+    }
+
+    // Отбор: подстрока без учёта регистра; для сумм - сравнение вида ">1000",
+    // "<=0", "=250.50"; дату можно искать и как "2026-03"
+    pub fn matches(&self, filter: &str, store: &Store, operation: &Operation) -> bool {
+        let filter = filter.trim();
+        if filter.is_empty() {
+            return true;
+        }
+        if let Some(value) = self.number(store, operation)
+            && let Some((ordering, bound)) = comparison(filter)
+        {
+            return ordering.contains(&value.cmp(&bound));
+        }
+        let filter = filter.to_lowercase();
+        if *self == Field::Date
+            && operation
+                .date_time
+                .format("%Y-%m-%d")
+                .to_string()
+                .contains(&filter)
+        {
+            return true;
+        }
+        self.text(store, operation).to_lowercase().contains(&filter)
+    }
+}
+
+// Допустимые результаты сравнения значения с границей
+fn comparison(filter: &str) -> Option<(&'static [Ordering], Decimal)> {
+    let (orderings, rest): (&'static [Ordering], &str) =
+        if let Some(rest) = filter.strip_prefix(">=") {
+            (&[Ordering::Greater, Ordering::Equal], rest)
+        } else if let Some(rest) = filter.strip_prefix("<=") {
+            (&[Ordering::Less, Ordering::Equal], rest)
+        } else if let Some(rest) = filter.strip_prefix('>') {
+            (&[Ordering::Greater], rest)
+        } else if let Some(rest) = filter.strip_prefix('<') {
+            (&[Ordering::Less], rest)
+        } else if let Some(rest) = filter.strip_prefix('=') {
+            (&[Ordering::Equal], rest)
+        } else {
+            return None;
+        };
+    Some((orderings, parse_decimal(rest.trim())?))
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Column {
+    pub field: Field,
+    pub visible: bool,
+    #[serde(default)]
+    pub filter: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TableView {
+    pub columns: Vec<Column>,
+    pub sort: Field,
+    pub descending: bool,
+}
+
+impl Default for TableView {
+    fn default() -> Self {
+        Self {
+            columns: Field::iter()
+                .map(|field| Column {
+                    field,
+                    visible: true,
+                    filter: String::new(),
+                })
+                .collect(),
+            sort: Field::Date,
+            descending: false,
+        }
+    }
+}
+
+impl TableView {
+    // Столбцы, которых нет в сохранённой настройке, добавляются скрытыми в конец;
+    // повторы убираются
+    pub fn normalize(&mut self) {
+        let mut seen = Vec::new();
+        self.columns.retain(|column| {
+            let new = !seen.contains(&column.field);
+            seen.push(column.field);
+            new
+        });
+        for field in Field::iter() {
+            if !seen.contains(&field) {
+                self.columns.push(Column {
+                    field,
+                    visible: false,
+                    filter: String::new(),
+                });
+            }
+        }
+    }
+
+    pub fn visible(&self) -> impl Iterator<Item = Field> + '_ {
+        self.columns
+            .iter()
+            .filter(|column| column.visible)
+            .map(|column| column.field)
+    }
+
+    // Повторный выбор того же столбца меняет направление
+    pub fn sort_by(&mut self, field: Field) {
+        if self.sort == field {
+            self.descending = !self.descending;
+        } else {
+            self.sort = field;
+            self.descending = false;
+        }
+    }
+
+    // Сдвиг столбца на соседнее место; offset -1 - влево, 1 - вправо
+    pub fn move_column(&mut self, index: usize, offset: isize) {
+        if let Some(target) = index.checked_add_signed(offset)
+            && target < self.columns.len()
+            && index < self.columns.len()
+        {
+            self.columns.swap(index, target);
+        }
+    }
+
+    pub fn is_filtered(&self) -> bool {
+        self.columns
+            .iter()
+            .any(|column| !column.filter.trim().is_empty())
+    }
+
+    pub fn matches(&self, store: &Store, operation: &Operation) -> bool {
+        self.columns
+            .iter()
+            .all(|column| column.field.matches(&column.filter, store, operation))
+    }
+
+    // Идентификаторы операций в порядке показа; query отбирает дополнительно
+    pub fn rows(&self, store: &Store, query: &Query) -> Vec<Uuid> {
+        let mut operations: Vec<&Operation> = store
+            .operations_by_date()
+            .filter(|operation| {
+                self.matches(store, operation) && query.matches_operation(store, operation)
+            })
+            .collect();
+        // Равные значения остаются в порядке дат
+        match self.sort {
+            Field::Date => {}
+            Field::Amount | Field::Balance => {
+                operations.sort_by_cached_key(|operation| self.sort.number(store, operation))
+            }
+            _ => operations
+                .sort_by_cached_key(|operation| self.sort.text(store, operation).to_lowercase()),
+        }
+        if self.descending {
+            operations.reverse();
+        }
+        operations.iter().map(|operation| operation.id).collect()
+    }
+}
+
+// Именованный вид: запрос поиска вместе с настройкой таблицы
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct SavedView {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub query: String,
+    pub table: TableView,
+}
+
+impl SavedView {
+    pub fn rows(&self, store: &Store) -> Result<Vec<Uuid>, QueryError> {
+        Ok(self.table.rows(store, &Query::parse(&self.query)?))
+    }
+
+    // База из операций вида с их счетами и чеками, для экспорта и отчётов
+    // Счёт и чек попадают в базу один раз, сколько бы операций на них ни ссылалось
+    pub fn database(&self, store: &Store) -> Result<Database, QueryError> {
+        let mut db = Database::new();
+        let mut accounts = HashSet::new();
+        let mut receipts = HashSet::new();
+        for id in self.rows(store)? {
+            let operation = store.operation(id).unwrap();
+            if let Some(account) = store.account(operation.account_id)
+                && accounts.insert(account.id)
+            {
+                db.accounts.push(account.clone());
+            }
+            if let Some(receipt) = operation.receipt_id.and_then(|id| store.receipt(id))
+                && receipts.insert(receipt.id)
+            {
+                db.receipts.push(receipt.clone());
+            }
+            db.operations.push(operation.clone());
+        }
+        Ok(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{Account, AccountType};
+    use crate::operation::{FinanseDirection, OperationType};
+    use crate::receipt::Receipt;
+    use chrono::NaiveDate;
+    use rust_decimal::dec;
+
+    #[test]
+    fn database_lists_shared_records_once() {
+        let account = Account {
+            id: Uuid::new_v4(),
+            name: "Main".to_string(),
+            account_type: AccountType::Account,
+            number: String::new(),
+            bik: 0,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Default::default(),
+        };
+        let mut receipt = Receipt::empty_new();
+        receipt.id = Uuid::new_v4();
+        let mut db = Database::new();
+        for (day, summary) in [(1, dec!(70)), (2, dec!(50))] {
+            db.operations.push(Operation {
+                id: Uuid::new_v4(),
+                date_time: NaiveDate::from_ymd_opt(2026, 3, day).unwrap().into(),
+                account_id: account.id,
+                operation_type: OperationType::Buy,
+                summary,
+                direction: FinanseDirection::Credit,
+                receipt_id: Some(receipt.id),
+                reference: None,
+                counterparty: None,
+                note: None,
+                category: None,
+                transfer_id: None,
+            });
+        }
+        db.accounts.push(account);
+        db.receipts.push(receipt);
+        let store = Store::new(db);
+
+        let view = SavedView {
+            id: Uuid::new_v4(),
+            name: "Все".to_string(),
+            query: String::new(),
+            table: TableView::default(),
+        };
+        let exported = view.database(&store).unwrap();
+        assert_eq!(exported.operations.len(), 2);
+        assert_eq!(exported.accounts.len(), 1);
+        assert_eq!(exported.receipts.len(), 1);
+    }
+}
@@ -1,5 +1,5 @@
 use eframe::egui::{self, Ui};
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 use strum::IntoEnumIterator;
 
 // trait Enum: Debug {}
@@ -17,3 +17,18 @@ where
             }
         });
 }
+
+// Варианты подписаны своим Display, а не именем варианта
+pub fn dcbox<T>(ui: &mut Ui, variable: &mut T, label: &str)
+where
+    T: IntoEnumIterator + Display + PartialEq,
+{
+    egui::ComboBox::from_label(label)
+        .selected_text(variable.to_string())
+        .show_ui(ui, |ui| {
+            for i in T::iter() {
+                let text = i.to_string();
+                ui.selectable_value(variable, i, text);
+            }
+        });
+}
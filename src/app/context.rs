@@ -4,22 +4,40 @@ use egui_extras::{Column, Size, StripBuilder, TableBuilder};
 use rust_decimal::Decimal;
 use uuid::Uuid;
 
-use crate::{
+use crate::app::{
+    AccountFields, App, AuditFields, CheckFields, CsvImportFields, DeleteFields, ExportFormat,
+    ExportTable, ImportFields, ImportFormat, OperationFields, PendingImport, ReceiptFields,
+    Selection, SlipFields, Statement, SubjectFields, cbox, dcbox, table,
+};
+use finance_core::{
     account::Account,
-    app::{
-        AccountFields, App, OperationFields, ReceiptFields, Selection, Statement, cbox,
-        table::{self, TableType},
-    },
-    operation::{self, Operation, OperationType},
-    receipt::{self, Receipt},
+    audit::{self, AuditEntry},
+    card::{Card, CardExpiry, mask_pan},
+    database::Database,
+    dedup::{self, Confidence, find_duplicates},
+    exchange::ImportResult,
+    exchange::{client_bank, csv, ledger, ofx, qif, sheet},
+    history::Command,
+    integrity::{self, Fix, Problem, Severity},
+    matching::{slip_account, slip_operation},
+    money::parse_decimal,
+    operation::Operation,
+    receipt::{self, Receipt, Slip},
+    report::{self, Sheet},
+    search::{self, Hit, Query},
+    storage,
+    trash::{self, AccountOperations, Deleted, Item},
+    validation::validate_receipt,
+    view::SavedView,
 };
 
 pub fn main_central_panel(app: &mut App, ui: &mut Ui) {
     ui.heading("My egui Application");
+    search_bar(app, ui);
     //ui.push_id(id_salt, add_contents)
-    table::table(app, TableType::Account, ui);
+    table::accounts(app, ui, 150.0);
     ui.separator();
-    table::table(app, TableType::Operation, ui);
+    table::operations(app, ui);
 }
 
 pub fn main_right_panel(app: &mut App, ui: &mut Ui) {
@@ -38,17 +56,13 @@ pub fn main_right_panel(app: &mut App, ui: &mut Ui) {
         if let Some(selection) = &app.selected {
             match selection {
                 Selection::Account(uuid) => {
-                    let iter = &app.db.accounts.iter().find(|account| account.id == *uuid);
+                    let iter = app.db.account(*uuid);
                     if let Some(element) = iter {
                         ui.label(format!("{}", element.id));
                     }
                 }
                 Selection::Operation(uuid) => {
-                    let iter = &app
-                        .db
-                        .operations
-                        .iter()
-                        .find(|operation| operation.id == *uuid);
+                    let iter = app.db.operation(*uuid);
                     if let Some(element) = iter {
                         ui.label(format!("{}", element.id));
                     }
@@ -60,38 +74,191 @@ pub fn main_right_panel(app: &mut App, ui: &mut Ui) {
         if ui.button("Edit").clicked() {
             match selection {
                 Selection::Account(uuid) => {
-                    let iter = &app
-                        .db
-                        .accounts
-                        .iter()
-                        .find(|account| account.id == *uuid)
-                        .unwrap();
+                    let iter = app.db.account(*uuid).unwrap();
 
                     app.account_fields.name = iter.name.clone();
                     app.account_fields.account_type = iter.account_type.clone();
                     app.account_fields.number = iter.number.clone();
                     app.account_fields.bik = iter.bik.to_string();
+                    app.account_fields.cards = iter.cards.clone();
+                    app.account_fields.currency = iter.currency;
                     app.statement = Statement::EditAccount(*uuid);
                 }
                 Selection::Operation(uuid) => {
-                    let iter = &app
-                        .db
-                        .operations
-                        .iter()
-                        .find(|operation| operation.id == *uuid)
-                        .unwrap();
-
-                    app.operation_fields.date = iter.date_time.date();
-                    app.operation_fields.hour = iter.date_time.time().hour();
-                    app.operation_fields.minute = iter.date_time.time().minute();
-                    app.operation_fields.account_id = iter.account_id;
-                    app.operation_fields.operation_type = iter.operation_type.clone();
-                    app.operation_fields.summary = iter.summary.to_string();
-                    app.operation_fields.direction = iter.direction.clone();
-                    app.statement = Statement::EditOperation(*uuid);
+                    let op_id = *uuid;
+                    open_operation(app, op_id);
+                }
+            }
+        }
+    }
+    if let Some(selection) = &app.selected
+        && ui.button("Delete").clicked()
+    {
+        app.delete_fields = DeleteFields::new();
+        match selection {
+            Selection::Account(uuid) => {
+                let acc_id = *uuid;
+                if app.db.account_operations(acc_id).next().is_none() {
+                    match trash::delete_account(&mut app.db, acc_id, AccountOperations::Block) {
+                        Ok(_) => {
+                            app.db.commit();
+                            app.selected = None;
+                        }
+                        Err(error) => app.delete_fields.message = Some(error.to_string()),
+                    }
+                } else {
+                    app.statement = Statement::DeleteAccount(acc_id);
                 }
             }
+            Selection::Operation(uuid) => {
+                let op_id = *uuid;
+                let with_receipt = app
+                    .db
+                    .operation(op_id)
+                    .is_some_and(|operation| operation.receipt_id.is_some());
+                if with_receipt {
+                    app.statement = Statement::DeleteOperation(op_id);
+                } else {
+                    match trash::delete_operation(&mut app.db, op_id, false) {
+                        Ok(_) => {
+                            app.db.commit();
+                            app.selected = None;
+                        }
+                        Err(error) => app.delete_fields.message = Some(error.to_string()),
+                    }
+                }
+            }
+        }
+    }
+    // Ошибка удаления без окна подтверждения
+    if matches!(app.statement, Statement::Common)
+        && let Some(message) = &app.delete_fields.message
+    {
+        ui.colored_label(egui::Color32::RED, message);
+    }
+    ui.separator();
+    views(app, ui);
+}
+
+// Сохранённые виды: щелчок показывает вид в таблице операций, текущие запрос
+// поиска и настройка таблицы сохраняются под введённым именем
+fn views(app: &mut App, ui: &mut Ui) {
+    ui.heading("Views");
+    let mut apply: Option<Option<Uuid>> = None;
+    let mut delete: Option<Uuid> = None;
+    let active = app.operations_view.view;
+    if ui
+        .selectable_label(active.is_none(), "All operations")
+        .clicked()
+    {
+        apply = Some(None);
+    }
+    for view in app.db.views.iter() {
+        ui.horizontal(|ui| {
+            if ui.small_button("✖").clicked() {
+                delete = Some(view.id);
+            }
+            if ui
+                .selectable_label(active == Some(view.id), &view.name)
+                .clicked()
+            {
+                apply = Some(Some(view.id));
+            }
+        });
+    }
+    let fields = &mut app.view_fields;
+    ui.add(egui::TextEdit::singleline(&mut fields.name).hint_text("View name"));
+    if ui.button("Save view").clicked() && !fields.name.trim().is_empty() {
+        let text = app.search_fields.text.trim().to_string();
+        match Query::parse(&text) {
+            Ok(_) => {
+                let name = fields.name.trim().to_string();
+                let table = app.settings.operations_table.clone();
+                let id = app
+                    .db
+                    .views
+                    .iter()
+                    .find(|view| view.name == name)
+                    .map(|view| view.id)
+                    .unwrap_or_else(Uuid::new_v4);
+                app.db.upsert_view(SavedView {
+                    id,
+                    name,
+                    query: text,
+                    table,
+                });
+                app.db.commit();
+                app.operations_view.view = Some(id);
+                fields.name.clear();
+                fields.error = None;
+            }
+            Err(error) => fields.error = Some(error.to_string()),
+        }
+    }
+    if let Some(error) = &fields.error {
+        ui.colored_label(egui::Color32::RED, error);
+    }
+    if let Some(id) = delete {
+        app.db.remove_view(id);
+        app.db.commit();
+        if app.operations_view.view == Some(id) {
+            app.operations_view.view = None;
+        }
+    }
+    match apply {
+        Some(Some(id)) => {
+            let view = app.db.views.iter().find(|view| view.id == id).unwrap();
+            app.settings.operations_table = view.table.clone();
+            app.settings.operations_table.normalize();
+            app.search_fields.text = view.query.clone();
+            app.operations_view.view = Some(id);
+            app.settings_changed();
         }
+        Some(None) => app.operations_view.view = None,
+        None => {}
+    }
+}
+
+// Открывает окно операции с её полями
+pub fn open_operation(app: &mut App, op_id: Uuid) {
+    let iter = app.db.operation(op_id).unwrap();
+
+    app.operation_fields.date = iter.date_time.date();
+    app.operation_fields.hour = iter.date_time.time().hour();
+    app.operation_fields.minute = iter.date_time.time().minute();
+    app.operation_fields.account_id = iter.account_id;
+    app.operation_fields.operation_type = iter.operation_type.clone();
+    app.operation_fields.summary = iter.summary.to_string();
+    app.operation_fields.direction = iter.direction.clone();
+    app.operation_fields.receipt = iter.receipt_id;
+    app.operation_fields.reference = iter.reference.clone().unwrap_or_default();
+    app.operation_fields.counterparty = iter.counterparty.clone().unwrap_or_default();
+    app.operation_fields.note = iter.note.clone().unwrap_or_default();
+    app.operation_fields.category = iter.category.clone().unwrap_or_default();
+    app.statement = Statement::EditOperation(op_id);
+}
+
+// Чек без операции открывается как чек новой операции
+pub fn open_free_receipt(app: &mut App, rec_id: Uuid) {
+    app.operation_fields = OperationFields::new();
+    app.receipt_fields = app
+        .db
+        .receipt(rec_id)
+        .map(ReceiptFields::from_receipt)
+        .unwrap_or_else(ReceiptFields::new);
+    app.statement = Statement::EditReceipt(rec_id, Uuid::new_v4(), true);
+}
+
+// Открывает окно чека операции; после него открывается окно самой операции
+pub fn open_receipt(app: &mut App, op_id: Uuid) {
+    open_operation(app, op_id);
+    if let Some(rec_id) = app.operation_fields.receipt {
+        app.receipt_fields = app
+            .db
+            .receipt(rec_id)
+            .map(ReceiptFields::from_receipt)
+            .unwrap_or_else(ReceiptFields::new);
+        app.statement = Statement::EditReceipt(rec_id, op_id, false);
     }
 }
 
@@ -105,8 +272,75 @@ pub fn main_bottom_panel(app: &mut App, ui: &mut Ui) {
             app.statement = Statement::EditOperation(Uuid::new_v4());
             app.operation_fields = OperationFields::new();
         }
-        if ui.button("Save").clicked() {
-            app.db.save(&app.file);
+        // Базу, которую не удалось прочитать, нельзя сохранить поверх файла
+        if ui
+            .add_enabled(app.load_error.is_none(), egui::Button::new("Save"))
+            .clicked()
+        {
+            let keep_history = app.settings.keep_history;
+            let result = storage::open(&app.file).and_then(|mut storage| {
+                if keep_history {
+                    storage.save(&app.db)
+                } else {
+                    app.db.without_history(|db| storage.save(db))
+                }
+            });
+            app.save_message = Some(match result {
+                Ok(()) => format!("Saved to {}", app.file),
+                Err(error) => error.to_string(),
+            });
+        }
+        // Как и сочетания клавиш, отмена недоступна при открытом окне правки
+        let common = matches!(app.statement, Statement::Common);
+        if ui
+            .add_enabled(
+                common && !app.db.history.done.is_empty(),
+                egui::Button::new("Undo"),
+            )
+            .clicked()
+        {
+            undo(app, 1);
+        }
+        if ui
+            .add_enabled(
+                common && !app.db.history.undone.is_empty(),
+                egui::Button::new("Redo"),
+            )
+            .clicked()
+        {
+            redo(app, 1);
+        }
+        if ui.button("History").clicked() {
+            app.statement = Statement::History;
+        }
+        if ui.button("Audit log").clicked() {
+            app.audit_fields = AuditFields::new();
+            app.statement = Statement::Audit;
+        }
+        if ui.button("Check").clicked() {
+            app.check_fields = CheckFields::new();
+            app.statement = Statement::Check;
+        }
+        if ui.button("Trash").clicked() {
+            app.delete_fields = DeleteFields::new();
+            app.statement = Statement::Trash;
+        }
+        if ui.button("Import").clicked() {
+            app.import_fields = ImportFields::new();
+            app.statement = Statement::Import;
+        }
+        if ui.button("Import CSV").clicked() {
+            app.csv_import_fields = CsvImportFields::new();
+            app.statement = Statement::CsvImport;
+        }
+        if let Some(error) = &app.load_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        if let Some(error) = &app.settings_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        if let Some(message) = &app.save_message {
+            ui.label(message);
         }
     }
 }
@@ -127,27 +361,27 @@ pub fn account(app: &mut App, acc_id: Uuid, ctx: &egui::Context, class: egui::Vi
 
         ui.add(egui::TextEdit::singleline(&mut app.account_fields.number).char_limit(30));
         ui.add(egui::TextEdit::singleline(&mut app.account_fields.bik).char_limit(9));
+        cbox(ui, &mut app.account_fields.currency, "Currency");
+        if app.account_fields.account_type.has_cards() {
+            cards(ui, &mut app.account_fields);
+        }
         if ui.button("Apply").clicked() {
-            let iter = app
+            let sum = app
                 .db
-                .accounts
-                .iter_mut()
-                .find(|account| account.id == acc_id);
-            if let Some(element) = iter {
-                element.account_type = app.account_fields.account_type.clone();
-                element.name = app.account_fields.name.clone();
-                element.number = app.account_fields.number.clone();
-                element.bik = app.account_fields.bik.parse::<u32>().unwrap();
-            } else {
-                app.db.accounts.push(Account {
-                    id: acc_id,
-                    name: app.account_fields.name.clone(),
-                    account_type: app.account_fields.account_type.clone(),
-                    number: app.account_fields.number.clone(),
-                    bik: app.account_fields.bik.parse::<u32>().unwrap(),
-                    sum: 0,
-                });
-            }
+                .account(acc_id)
+                .map(|account| account.sum)
+                .unwrap_or(0);
+            app.db.upsert_account(Account {
+                id: acc_id,
+                name: app.account_fields.name.clone(),
+                account_type: app.account_fields.account_type.clone(),
+                number: app.account_fields.number.clone(),
+                bik: app.account_fields.bik.parse::<u32>().unwrap(),
+                sum,
+                cards: app.account_fields.cards.clone(),
+                currency: app.account_fields.currency,
+            });
+            app.db.commit();
             close_request = true;
         }
     });
@@ -160,6 +394,58 @@ pub fn account(app: &mut App, acc_id: Uuid, ctx: &egui::Context, class: egui::Vi
     }
 }
 
+fn cards(ui: &mut Ui, fields: &mut AccountFields) {
+    ui.separator();
+    ui.label("Cards");
+    let mut removed = None;
+    for (i, card) in fields.cards.iter().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(format!("{} {}", card.payment_system, card.masked_pan));
+            if let Some(expiry) = card.expiry {
+                ui.label(expiry.to_string());
+            }
+            if let Some(holder) = &card.holder {
+                ui.label(holder);
+            }
+            if ui.button("Remove").clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = removed {
+        fields.cards.remove(i);
+    }
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::TextEdit::singleline(&mut fields.card_pan)
+                .hint_text("Card number")
+                .char_limit(23),
+        );
+        ui.add(
+            egui::TextEdit::singleline(&mut fields.card_expiry)
+                .hint_text("MM/YY")
+                .char_limit(7),
+        );
+        ui.add(egui::TextEdit::singleline(&mut fields.card_holder).hint_text("Holder"));
+        if ui.button("Add card").clicked() {
+            let expiry = CardExpiry::parse(&fields.card_expiry);
+            match Card::new(&fields.card_pan, expiry, optional_text(&fields.card_holder)) {
+                Ok(card) => {
+                    fields.cards.push(card);
+                    fields.card_pan = "".to_string();
+                    fields.card_expiry = "".to_string();
+                    fields.card_holder = "".to_string();
+                    fields.card_error = None;
+                }
+                Err(error) => fields.card_error = Some(error.to_string()),
+            }
+        }
+    });
+    if let Some(error) = &fields.card_error {
+        ui.colored_label(egui::Color32::RED, error);
+    }
+}
+
 pub fn operation(app: &mut App, op_id: Uuid, ctx: &egui::Context, class: egui::ViewportClass) {
     assert!(
         class == egui::ViewportClass::Immediate,
@@ -204,53 +490,74 @@ pub fn operation(app: &mut App, op_id: Uuid, ctx: &egui::Context, class: egui::V
         ui.label("Direction");
         cbox(ui, &mut app.operation_fields.direction, "Select direction!");
 
+        ui.label("Reference");
+        ui.add(egui::TextEdit::singleline(
+            &mut app.operation_fields.reference,
+        ));
+        ui.label("Counterparty");
+        ui.add(egui::TextEdit::singleline(
+            &mut app.operation_fields.counterparty,
+        ));
+        ui.label("Category");
+        ui.add(egui::TextEdit::singleline(
+            &mut app.operation_fields.category,
+        ));
+        ui.label("Note");
+        ui.add(egui::TextEdit::multiline(&mut app.operation_fields.note));
+
         if ui.button("Receipt").clicked() {
             app.receipt_fields = ReceiptFields::new();
-            let iter = app
-                .db
-                .operations
-                .iter_mut()
-                .find(|operation| operation.id == op_id);
-            if let None = iter {
-                app.statement = Statement::EditReceipt(Uuid::new_v4(), op_id, true)
-            } else {
-                if let Some(identificator) = app.operation_fields.receipt {
-                    app.statement = Statement::EditReceipt(identificator, op_id, false);
-                } else {
-                    app.statement = Statement::EditReceipt(Uuid::new_v4(), op_id, false)
+            let is_new = app.db.operation(op_id).is_none();
+            if let Some(identificator) = app.operation_fields.receipt {
+                let iter = app.db.receipt(identificator);
+                if let Some(element) = iter {
+                    app.receipt_fields = ReceiptFields::from_receipt(element);
                 }
+                app.statement = Statement::EditReceipt(identificator, op_id, is_new);
+            } else {
+                app.statement = Statement::EditReceipt(Uuid::new_v4(), op_id, is_new)
             }
         }
 
-        if ui.button("Apply").clicked() {
-            let iter = app
+        let summary = parse_decimal(&app.operation_fields.summary);
+        if summary.is_none() {
+            ui.separator();
+            ui.colored_label(
+                egui::Color32::RED,
+                "Сумма не распознана: введите число, например 1 234,56",
+            );
+        }
+
+        if ui
+            .add_enabled(summary.is_some(), egui::Button::new("Apply"))
+            .clicked()
+            && let Some(summary) = summary
+        {
+            let transfer_id = app
                 .db
-                .operations
-                .iter_mut()
-                .find(|operation| operation.id == op_id);
+                .operation(op_id)
+                .and_then(|operation| operation.transfer_id);
             let time = chrono::NaiveTime::from_hms_opt(
                 app.operation_fields.hour,
                 app.operation_fields.minute,
                 0,
             )
             .unwrap();
-            if let Some(element) = iter {
-                element.date_time = chrono::NaiveDateTime::new(app.operation_fields.date, time);
-                element.account_id = app.operation_fields.account_id;
-                element.operation_type = app.operation_fields.operation_type.clone();
-                element.summary = app.operation_fields.summary.parse::<usize>().unwrap();
-                element.direction = app.operation_fields.direction.clone();
-            } else {
-                app.db.operations.push(Operation {
-                    id: op_id,
-                    date_time: chrono::NaiveDateTime::new(app.operation_fields.date, time),
-                    account_id: app.operation_fields.account_id,
-                    operation_type: app.operation_fields.operation_type.clone(),
-                    direction: app.operation_fields.direction.clone(),
-                    receipt_id: None,
-                    summary: app.operation_fields.summary.parse::<usize>().unwrap(),
-                });
-            }
+            app.db.upsert_operation(Operation {
+                id: op_id,
+                date_time: chrono::NaiveDateTime::new(app.operation_fields.date, time),
+                account_id: app.operation_fields.account_id,
+                operation_type: app.operation_fields.operation_type.clone(),
+                direction: app.operation_fields.direction.clone(),
+                receipt_id: app.operation_fields.receipt,
+                reference: optional_text(&app.operation_fields.reference),
+                counterparty: optional_text(&app.operation_fields.counterparty),
+                note: optional_text(&app.operation_fields.note),
+                category: optional_text(&app.operation_fields.category),
+                transfer_id,
+                summary,
+            });
+            app.db.commit();
             close_request = true;
         }
     });
@@ -317,6 +624,9 @@ pub fn receipt(
                         .column(Column::auto())
                         .column(Column::auto())
                         .column(Column::auto())
+                        .column(Column::auto())
+                        .column(Column::auto())
+                        .column(Column::auto())
                         .min_scrolled_height(0.0)
                         .max_scroll_height(500.0)
                         .sense(egui::Sense::click());
@@ -346,6 +656,15 @@ pub fn receipt(
                             header.col(|ui| {
                                 ui.strong("Vat");
                             });
+                            header.col(|ui| {
+                                ui.strong("Payment method");
+                            });
+                            header.col(|ui| {
+                                ui.strong("Subject type");
+                            });
+                            header.col(|ui| {
+                                ui.strong("Marking code");
+                            });
                         })
                         .body(|mut body| {
                             // let contents =
@@ -354,7 +673,7 @@ pub fn receipt(
                             //      response: &mut Option<Response>| {
                             //         App::response_compare(ui.label(text), response);
                             //     };
-                            for (i, element) in app.receipt_fields.subjects.iter_mut().enumerate() {
+                            for (i, fields) in app.receipt_fields.subjects.iter_mut().enumerate() {
                                 body.row(30.0, |mut row| {
                                     //let mut inner_response: Option<Response> = None;
                                     row.col(|ui| {
@@ -362,41 +681,108 @@ pub fn receipt(
                                     });
 
                                     row.col(|ui| {
-                                        ui.add(egui::TextEdit::singleline(&mut element.name));
+                                        ui.add(egui::TextEdit::singleline(
+                                            &mut fields.subject.name,
+                                        ));
                                     });
 
                                     row.col(|ui| {
-                                        let mut count = element.count.to_string();
-                                        if ui.add(egui::TextEdit::singleline(&mut count)).changed()
+                                        if ui
+                                            .add(egui::TextEdit::singleline(&mut fields.count))
+                                            .changed()
+                                            && let Some(count) = parse_decimal(&fields.count)
                                         {
-                                            element.count = count.parse().unwrap();
+                                            fields.subject.set_count(count);
+                                            fields.subject.summary =
+                                                fields.subject.computed_summary();
+                                            fields.summary = fields.subject.summary.to_string();
                                         };
                                     });
 
                                     row.col(|ui| {
-                                        cbox(ui, &mut element.unit_type, "Select unit type!");
+                                        let unit = fields.subject.unit_type;
+                                        dcbox(
+                                            ui,
+                                            &mut fields.subject.unit_type,
+                                            "Select unit type!",
+                                        );
+                                        // Количество берётся из введённого текста: прежняя
+                                        // единица могла его округлить
+                                        if fields.subject.unit_type != unit
+                                            && let Some(count) = parse_decimal(&fields.count)
+                                        {
+                                            fields.subject.set_count(count);
+                                            fields.subject.summary =
+                                                fields.subject.computed_summary();
+                                            fields.summary = fields.subject.summary.to_string();
+                                        }
                                     });
 
                                     row.col(|ui| {
-                                        ui.add(egui::TextEdit::singleline(
-                                            &mut element.price.to_string(),
-                                        ));
+                                        if ui
+                                            .add(egui::TextEdit::singleline(&mut fields.price))
+                                            .changed()
+                                            && let Some(price) = parse_decimal(&fields.price)
+                                        {
+                                            fields.subject.price = price;
+                                            fields.subject.summary =
+                                                fields.subject.computed_summary();
+                                            fields.summary = fields.subject.summary.to_string();
+                                        };
                                     });
 
                                     row.col(|ui| {
-                                        ui.add(egui::TextEdit::singleline(
-                                            &mut element.summary.to_string(),
-                                        ));
+                                        if ui
+                                            .add(egui::TextEdit::singleline(&mut fields.summary))
+                                            .changed()
+                                            && let Some(summary) = parse_decimal(&fields.summary)
+                                        {
+                                            fields.subject.summary = summary;
+                                        };
+                                        if !fields.subject.summary_matches() {
+                                            ui.colored_label(egui::Color32::RED, "≠")
+                                                .on_hover_text(format!(
+                                                    "price × count = {}",
+                                                    fields.subject.computed_summary()
+                                                ));
+                                        }
                                     });
 
                                     row.col(|ui| {
-                                        cbox(ui, &mut element.vat_type, "Select Vat type!");
+                                        cbox(ui, &mut fields.subject.vat_type, "Select Vat type!");
                                     });
 
                                     row.col(|ui| {
-                                        ui.add(egui::TextEdit::singleline(
-                                            &mut element.vat.to_string(),
-                                        ));
+                                        fields.subject.vat = fields.subject.computed_vat();
+                                        ui.label(fields.subject.vat.to_string());
+                                    });
+
+                                    row.col(|ui| {
+                                        cbox(
+                                            ui,
+                                            &mut fields.subject.payment_method,
+                                            "Select payment method!",
+                                        );
+                                    });
+
+                                    row.col(|ui| {
+                                        cbox(
+                                            ui,
+                                            &mut fields.subject.subject_type,
+                                            "Select subject type!",
+                                        );
+                                    });
+
+                                    row.col(|ui| {
+                                        let mut marking_code =
+                                            fields.subject.marking_code.clone().unwrap_or_default();
+                                        if ui
+                                            .add(egui::TextEdit::singleline(&mut marking_code))
+                                            .changed()
+                                        {
+                                            fields.subject.marking_code =
+                                                optional_text(&marking_code);
+                                        };
                                     });
                                 });
                             }
@@ -405,7 +791,9 @@ pub fn receipt(
             });
 
         if ui.button("Add row").clicked() {
-            &mut app.receipt_fields.subjects.push(receipt::Subject::empty());
+            app.receipt_fields
+                .subjects
+                .push(SubjectFields::new(receipt::Subject::empty()));
         }
 
         //
@@ -426,186 +814,1463 @@ pub fn receipt(
         ui.label("in_kind");
         ui.add(egui::TextEdit::singleline(&mut app.receipt_fields.in_kind));
         ui.label("vat");
-        ui.add(egui::TextEdit::singleline(&mut app.receipt_fields.vat));
+        let subjects: Vec<receipt::Subject> = app
+            .receipt_fields
+            .subjects
+            .iter()
+            .map(|fields| fields.subject.clone())
+            .collect();
+        let totals = receipt::vat_totals(&subjects);
+        let computed_vat: Decimal = totals.iter().map(|total| total.vat).sum();
+        for total in totals.iter() {
+            ui.label(format!(
+                "{:?}: {} / {}",
+                total.vat_type, total.summary, total.vat
+            ));
+        }
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut app.receipt_fields.vat));
+            let entered_vat = parse_decimal(&app.receipt_fields.vat).unwrap_or(Decimal::ZERO);
+            if entered_vat != computed_vat {
+                ui.colored_label(egui::Color32::RED, "≠")
+                    .on_hover_text(format!("computed VAT = {}", computed_vat));
+                if ui.button("Fill").clicked() {
+                    app.receipt_fields.vat = computed_vat.to_string();
+                }
+            }
+        });
         ui.label("url");
         ui.add(egui::TextEdit::singleline(&mut app.receipt_fields.url));
 
-        if ui.button("Apply").clicked() {
-            let iter = app
-                .db
-                .receipts
-                .iter_mut()
-                .find(|receipt| receipt.id == rec_id);
+        ui.collapsing("Fiscal data", |ui| {
+            ui.label("FN number");
+            ui.add(egui::TextEdit::singleline(&mut app.receipt_fields.fn_number).char_limit(16));
+            ui.label("FD number");
+            ui.add(egui::TextEdit::singleline(&mut app.receipt_fields.fd_number).char_limit(10));
+            ui.label("Fiscal sign");
+            ui.add(egui::TextEdit::singleline(&mut app.receipt_fields.fiscal_sign).char_limit(10));
+            ui.label("Shift number");
+            ui.add(egui::TextEdit::singleline(
+                &mut app.receipt_fields.shift_number,
+            ));
+            ui.label("Seller INN");
+            ui.add(egui::TextEdit::singleline(&mut app.receipt_fields.seller_inn).char_limit(12));
+            ui.label("Taxation");
+            cbox(
+                ui,
+                &mut app.receipt_fields.taxation_type,
+                "Select taxation type!",
+            );
+            ui.label("Cashier");
+            ui.add(egui::TextEdit::singleline(&mut app.receipt_fields.cashier));
+        });
+
+        let mut has_slip = app.receipt_fields.slip.is_some();
+        if ui.checkbox(&mut has_slip, "Slip").changed() {
+            app.receipt_fields.slip = has_slip.then(SlipFields::new);
+        }
+        if let Some(slip_fields) = &mut app.receipt_fields.slip {
+            slip(ui, slip_fields);
+            let element = slip_from_fields(slip_fields);
+            ui.label(format!("Card: {}", element.masked_card()));
+            if let Some(acc_id) = slip_account(&app.db, &element) {
+                let name = app
+                    .db
+                    .account(acc_id)
+                    .map(|account| account.name.clone())
+                    .unwrap_or_default();
+                ui.horizontal(|ui| {
+                    ui.label(format!("Account: {}", name));
+                    if app.operation_fields.account_id != acc_id && ui.button("Use").clicked() {
+                        app.operation_fields.account_id = acc_id;
+                    }
+                });
+            }
+            if let Some(matched_id) = slip_operation(&app.db, &element) {
+                if matched_id == op_id {
+                    ui.label("Operation matches the slip");
+                } else {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("Slip matches another operation {}", matched_id),
+                    );
+                }
+            }
+            if app.operation_fields.reference.is_empty() && !element.auth_code.is_empty() {
+                app.operation_fields.reference = element.auth_code.clone();
+            }
+        }
+
+        let mut preview = Receipt::empty_new();
+        receipt_from_fields(&mut preview, &app.receipt_fields, NaiveDateTime::default());
+        let operation = app.db.operation(op_id);
+        let violations = validate_receipt(&preview, operation);
+        if !violations.is_empty() {
+            ui.separator();
+            for violation in violations.iter() {
+                ui.colored_label(egui::Color32::RED, violation.to_string());
+            }
+        }
+
+        let errors = receipt_errors(&app.receipt_fields);
+        if !errors.is_empty() {
+            ui.separator();
+            for error in errors.iter() {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+        }
+
+        if ui
+            .add_enabled(errors.is_empty(), egui::Button::new("Apply"))
+            .clicked()
+        {
             let time = chrono::NaiveTime::from_hms_opt(
-                app.operation_fields.hour,
-                app.operation_fields.minute,
+                app.receipt_fields.hour,
+                app.receipt_fields.minute,
                 0,
             )
             .unwrap();
-            if let Some(element) = iter {
-                element.date_time = chrono::NaiveDateTime::new(app.operation_fields.date, time);
-                element.calculation_type = app.receipt_fields.calculation_type;
-                if app.receipt_fields.address == "".to_string() {
-                    element.address = None;
-                } else {
-                    element.address = Some(app.receipt_fields.address.clone());
-                }
-                if app.receipt_fields.place == "".to_string() {
-                    element.place = None;
-                } else {
-                    element.place = Some(app.receipt_fields.place.clone());
-                }
-                element.subjects = Vec::new();
-                for j in 0..app.receipt_fields.subjects.len() {
-                    element
-                        .subjects
-                        .push(app.receipt_fields.subjects[j].clone());
-                }
-                element.summary =
-                    Decimal::from_str_exact(&app.receipt_fields.summary.clone()).unwrap();
-                if app.receipt_fields.cash == "" || app.receipt_fields.cash == "0" {
-                    element.cash = None;
-                } else {
-                    element.cash =
-                        Some(Decimal::from_str_exact(&app.receipt_fields.cash.clone()).unwrap());
-                }
-                if app.receipt_fields.cashless == "" || app.receipt_fields.cashless == "0" {
-                    element.cashless = None;
-                } else {
-                    element.cashless = Some(
-                        Decimal::from_str_exact(&app.receipt_fields.cashless.clone()).unwrap(),
-                    );
-                }
-                if app.receipt_fields.prepayment == "" || app.receipt_fields.prepayment == "0" {
-                    element.prepayment = None;
-                } else {
-                    element.prepayment = Some(
-                        Decimal::from_str_exact(&app.receipt_fields.prepayment.clone()).unwrap(),
-                    );
-                }
-                if app.receipt_fields.postpayment == "" || app.receipt_fields.postpayment == "0" {
-                    element.postpayment = None;
-                } else {
-                    element.postpayment = Some(
-                        Decimal::from_str_exact(&app.receipt_fields.postpayment.clone()).unwrap(),
-                    );
-                }
-                if app.receipt_fields.in_kind == "" || app.receipt_fields.in_kind == "0" {
-                    element.in_kind = None;
-                } else {
-                    element.in_kind =
-                        Some(Decimal::from_str_exact(&app.receipt_fields.in_kind.clone()).unwrap());
-                }
-                if app.receipt_fields.vat == "" || app.receipt_fields.vat == "0" {
-                    element.vat = None;
-                } else {
-                    element.vat =
-                        Some(Decimal::from_str_exact(&app.receipt_fields.vat.clone()).unwrap());
-                }
-                if app.receipt_fields.url == "".to_string() {
-                    element.url = None;
-                } else {
-                    element.url = Some(app.receipt_fields.url.clone());
-                }
-            } else {
+            let date_time = chrono::NaiveDateTime::new(app.receipt_fields.date, time);
+            let mut element = app.db.receipt(rec_id).cloned().unwrap_or_else(|| {
                 let mut element = Receipt::empty_new();
                 element.id = rec_id;
-                element.date_time = chrono::NaiveDateTime::new(app.operation_fields.date, time);
-                element.calculation_type = app.receipt_fields.calculation_type;
-                if app.receipt_fields.address == "".to_string() {
-                    element.address = None;
-                } else {
-                    element.address = Some(app.receipt_fields.address.clone());
-                }
-                if app.receipt_fields.place == "".to_string() {
-                    element.place = None;
-                } else {
-                    element.place = Some(app.receipt_fields.place.clone());
-                }
-                element.subjects = Vec::new();
-                for j in 0..app.receipt_fields.subjects.len() {
-                    element
-                        .subjects
-                        .push(app.receipt_fields.subjects[j].clone());
-                }
-                element.summary =
-                    Decimal::from_str_exact(&app.receipt_fields.summary.clone()).unwrap();
-                if app.receipt_fields.cash == "" || app.receipt_fields.cash == "0" {
-                    element.cash = None;
-                } else {
-                    element.cash =
-                        Some(Decimal::from_str_exact(&app.receipt_fields.cash.clone()).unwrap());
-                }
-                if app.receipt_fields.cashless == "" || app.receipt_fields.cashless == "0" {
-                    element.cashless = None;
-                } else {
-                    element.cashless = Some(
-                        Decimal::from_str_exact(&app.receipt_fields.cashless.clone()).unwrap(),
-                    );
-                }
-                if app.receipt_fields.prepayment == "" || app.receipt_fields.prepayment == "0" {
-                    element.prepayment = None;
-                } else {
-                    element.prepayment = Some(
-                        Decimal::from_str_exact(&app.receipt_fields.prepayment.clone()).unwrap(),
-                    );
-                }
-                if app.receipt_fields.postpayment == "" || app.receipt_fields.postpayment == "0" {
-                    element.postpayment = None;
-                } else {
-                    element.postpayment = Some(
-                        Decimal::from_str_exact(&app.receipt_fields.postpayment.clone()).unwrap(),
-                    );
-                }
-                if app.receipt_fields.in_kind == "" || app.receipt_fields.in_kind == "0" {
-                    element.in_kind = None;
-                } else {
-                    element.in_kind =
-                        Some(Decimal::from_str_exact(&app.receipt_fields.in_kind.clone()).unwrap());
-                }
-                if app.receipt_fields.vat == "" || app.receipt_fields.vat == "0" {
-                    element.vat = None;
-                } else {
-                    element.vat =
-                        Some(Decimal::from_str_exact(&app.receipt_fields.vat.clone()).unwrap());
-                }
-                if app.receipt_fields.url == "".to_string() {
-                    element.url = None;
-                } else {
-                    element.url = Some(app.receipt_fields.url.clone());
-                }
-                app.db.receipts.push(element);
-            }
+                element
+            });
+            receipt_from_fields(&mut element, &app.receipt_fields, date_time);
+            app.db.upsert_receipt(element);
+            app.db.commit();
             if signal {
                 app.operation_fields.date = app.receipt_fields.date;
                 app.operation_fields.hour = app.receipt_fields.hour;
                 app.operation_fields.minute = app.receipt_fields.minute;
-                match app.receipt_fields.calculation_type {
-                    receipt::CalculationType::Inbound => {
-                        app.operation_fields.direction = operation::FinanseDirection::Credit;
-                        app.operation_fields.operation_type = OperationType::Buy;
-                    }
-                    receipt::CalculationType::Outbound => {
-                        app.operation_fields.direction = operation::FinanseDirection::Debet;
-                        app.operation_fields.operation_type = OperationType::Sell;
-                    }
-                    receipt::CalculationType::InboundReturn => {
-                        app.operation_fields.direction = operation::FinanseDirection::Debet;
-                        app.operation_fields.operation_type = OperationType::ReturnBuy;
-                    }
-                    receipt::CalculationType::OutboundReturn => {
-                        app.operation_fields.direction = operation::FinanseDirection::Credit;
-                        app.operation_fields.operation_type = OperationType::ReturnSell;
-                    }
-                }
+                app.operation_fields.direction = app.receipt_fields.calculation_type.direction();
+                app.operation_fields.operation_type =
+                    app.receipt_fields.calculation_type.operation_type();
                 app.operation_fields.summary = app.receipt_fields.summary.clone();
-                app.operation_fields.receipt = Some(rec_id);
             }
+            app.operation_fields.receipt = Some(rec_id);
             close_request = true;
         }
+        if app.db.receipt(rec_id).is_some() && ui.button("Delete receipt").clicked() {
+            match trash::delete_receipt(&mut app.db, rec_id) {
+                Ok(_) => {
+                    app.db.commit();
+                    app.operation_fields.receipt = None;
+                    close_request = true;
+                }
+                Err(error) => app.receipt_fields.message = Some(error.to_string()),
+            }
+        }
+        if let Some(message) = &app.receipt_fields.message {
+            ui.colored_label(egui::Color32::RED, message);
+        }
     });
     if ctx.input(|i| i.viewport().close_requested()) || close_request {
         app.receipt_fields = ReceiptFields::new();
         app.statement = Statement::EditOperation(op_id);
     }
 }
+
+fn optional_text(text: &str) -> Option<String> {
+    if text.trim().is_empty() {
+        None
+    } else {
+        Some(text.trim().to_string())
+    }
+}
+
+fn optional_decimal(text: &str) -> Option<Decimal> {
+    parse_decimal(text).filter(|value| !value.is_zero())
+}
+
+// Поля чека, которые не удалось разобрать; пока они есть, чек не сохраняется
+fn receipt_errors(fields: &ReceiptFields) -> Vec<String> {
+    let mut errors = Vec::new();
+    if parse_decimal(&fields.summary).is_none() {
+        errors.push("Сумма чека не распознана: введите число, например 1 234,56".to_string());
+    }
+    for (name, value) in [
+        ("cash", &fields.cash),
+        ("cashless", &fields.cashless),
+        ("prepayment", &fields.prepayment),
+        ("postpayment", &fields.postpayment),
+        ("in_kind", &fields.in_kind),
+        ("vat", &fields.vat),
+    ] {
+        if !value.trim().is_empty() && parse_decimal(value).is_none() {
+            errors.push(format!("Поле {} не распознано: введите число", name));
+        }
+    }
+    for (name, value) in [
+        ("FD number", &fields.fd_number),
+        ("Fiscal sign", &fields.fiscal_sign),
+        ("Shift number", &fields.shift_number),
+    ] {
+        if !value.trim().is_empty() && value.trim().parse::<u32>().is_err() {
+            errors.push(format!(
+                "Поле {} не распознано: введите целое число до {}",
+                name,
+                u32::MAX
+            ));
+        }
+    }
+    errors
+}
+
+fn receipt_from_fields(element: &mut Receipt, fields: &ReceiptFields, date_time: NaiveDateTime) {
+    element.date_time = date_time;
+    element.calculation_type = fields.calculation_type;
+    element.address = optional_text(&fields.address);
+    element.place = optional_text(&fields.place);
+    element.subjects = fields
+        .subjects
+        .iter()
+        .map(|subject| subject.subject.clone())
+        .collect();
+    element.summary = parse_decimal(&fields.summary).unwrap_or(Decimal::ZERO);
+    element.cash = optional_decimal(&fields.cash);
+    element.cashless = optional_decimal(&fields.cashless);
+    element.prepayment = optional_decimal(&fields.prepayment);
+    element.postpayment = optional_decimal(&fields.postpayment);
+    element.in_kind = optional_decimal(&fields.in_kind);
+    element.vat = optional_decimal(&fields.vat);
+    element.url = optional_text(&fields.url);
+    element.fn_number = optional_text(&fields.fn_number);
+    element.fd_number = fields.fd_number.trim().parse().ok();
+    element.fiscal_sign = fields.fiscal_sign.trim().parse().ok();
+    element.shift_number = fields.shift_number.trim().parse().ok();
+    element.seller_inn = optional_text(&fields.seller_inn);
+    element.taxation_type = fields.taxation_type;
+    element.cashier = optional_text(&fields.cashier);
+    element.slip = fields.slip.as_ref().map(slip_from_fields);
+}
+
+fn slip(ui: &mut Ui, fields: &mut SlipFields) {
+    ui.label("Terminal");
+    ui.add(egui::TextEdit::singleline(&mut fields.terminal_id));
+    cbox(ui, &mut fields.op_type, "Select slip operation!");
+    ui.horizontal(|ui| {
+        ui.add(egui_extras::DatePickerButton::new(&mut fields.date).id_salt("slip_date"));
+        ui.add(
+            egui::DragValue::new(&mut fields.hour)
+                .speed(1)
+                .range(0..=23),
+        );
+        ui.add(
+            egui::DragValue::new(&mut fields.minute)
+                .speed(1)
+                .range(0..=59),
+        );
+    });
+    ui.label("Summ");
+    ui.add(egui::TextEdit::singleline(&mut fields.summary));
+    cbox(ui, &mut fields.currency, "Select currency!");
+    ui.label("Commission");
+    ui.add(egui::TextEdit::singleline(&mut fields.comm_summary));
+    ui.label("Auth code");
+    ui.add(egui::TextEdit::singleline(&mut fields.auth_code).char_limit(6));
+    ui.label("Card");
+    ui.add(egui::TextEdit::singleline(&mut fields.card).char_limit(19));
+    ui.label("Payment system");
+    ui.add(egui::TextEdit::singleline(&mut fields.payment_system));
+    ui.label("Adress");
+    ui.add(egui::TextEdit::singleline(&mut fields.address));
+    ui.label("Point name");
+    ui.add(egui::TextEdit::singleline(&mut fields.place));
+    ui.label("Document");
+    ui.add(egui::TextEdit::singleline(&mut fields.doc_id));
+}
+
+fn slip_from_fields(fields: &SlipFields) -> Slip {
+    let time = chrono::NaiveTime::from_hms_opt(fields.hour, fields.minute, 0).unwrap();
+    Slip {
+        id: fields.terminal_id.trim().parse().unwrap_or_default(),
+        op_type: fields.op_type,
+        date_time: chrono::NaiveDateTime::new(fields.date, time),
+        summary: parse_decimal(&fields.summary).unwrap_or(Decimal::ZERO),
+        currency: fields.currency,
+        comm_summary: optional_decimal(&fields.comm_summary),
+        auth_code: fields.auth_code.trim().to_string(),
+        card: mask_pan(fields.card.trim()),
+        address: optional_text(&fields.address),
+        place: optional_text(&fields.place),
+        payment_system: optional_text(&fields.payment_system),
+        doc_id: fields.doc_id.trim().parse().ok(),
+    }
+}
+
+pub fn check(app: &mut App, ctx: &egui::Context, class: egui::ViewportClass) {
+    assert!(
+        class == egui::ViewportClass::Immediate,
+        "This egui backend doesn't support multiple viewports"
+    );
+    let mut close_request: bool = false;
+    // Проверка повторяется только после изменения базы
+    if app.check_fields.revision != Some(app.db.revision()) {
+        app.check_fields.problems = integrity::check(&app.db);
+        app.check_fields.revision = Some(app.db.revision());
+    }
+    let mut repair: Option<(Problem, Fix)> = None;
+    let mut repair_all: bool = false;
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let fields = &mut app.check_fields;
+        if fields.problems.is_empty() {
+            ui.label("No problems found");
+        } else {
+            let count = |severity: Severity| {
+                fields
+                    .problems
+                    .iter()
+                    .filter(|problem| problem.severity() == severity)
+                    .count()
+            };
+            ui.label(format!(
+                "Errors: {}, warnings: {}, notes: {}",
+                count(Severity::Error),
+                count(Severity::Warning),
+                count(Severity::Info)
+            ));
+        }
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - 60.0)
+            .show(ui, |ui| {
+                for (index, problem) in fields.problems.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let color = match problem.severity() {
+                            Severity::Error => egui::Color32::RED,
+                            Severity::Warning => egui::Color32::ORANGE,
+                            Severity::Info => egui::Color32::GRAY,
+                        };
+                        ui.colored_label(color, problem.severity().to_string());
+                        ui.label(problem.record().to_string());
+                        ui.label(problem.to_string());
+                        if let Problem::UnknownAccount { .. } = problem {
+                            ui.push_id(index, |ui| {
+                                cbox(ui, &mut fields.account_type, "Type");
+                                cbox(ui, &mut fields.currency, "Currency");
+                            });
+                            let fix =
+                                Fix::CreateAccount(fields.account_type.clone(), fields.currency);
+                            if ui.button(fix_title(&fix)).clicked() {
+                                repair = Some((problem.clone(), fix));
+                            }
+                            egui::ComboBox::from_id_salt(("reassign", index))
+                                .selected_text(
+                                    app.db
+                                        .account(fields.target)
+                                        .map(|account| account.name.clone())
+                                        .unwrap_or_default(),
+                                )
+                                .show_ui(ui, |ui| {
+                                    for account in app.db.accounts.iter() {
+                                        ui.selectable_value(
+                                            &mut fields.target,
+                                            account.id,
+                                            &account.name,
+                                        );
+                                    }
+                                });
+                            if ui.button("Move to account").clicked() {
+                                repair = Some((problem.clone(), Fix::Reassign(fields.target)));
+                            }
+                        } else {
+                            for fix in problem.fixes() {
+                                if ui.button(fix_title(&fix)).clicked() {
+                                    repair = Some((problem.clone(), fix));
+                                }
+                            }
+                        }
+                    });
+                }
+            });
+        if let Some(message) = &fields.message {
+            ui.label(message);
+        }
+        ui.horizontal(|ui| {
+            if fields
+                .problems
+                .iter()
+                .any(|problem| problem.recommended().is_some())
+                && ui.button("Repair all").clicked()
+            {
+                repair_all = true;
+            }
+            if ui.button("Close").clicked() {
+                close_request = true;
+            }
+        });
+    });
+    // Исправления идут через историю: их можно отменить, они попадают в журнал
+    if let Some((problem, fix)) = repair {
+        let mut result = Ok(());
+        app.db
+            .update(|db| result = integrity::repair(db, &problem, fix));
+        app.check_fields.message = result.err().map(|error| error.to_string());
+    }
+    // Операциям с несуществующим счётом создаётся счёт выбранных типа и валюты
+    if repair_all {
+        let fix = Fix::CreateAccount(
+            app.check_fields.account_type.clone(),
+            app.check_fields.currency,
+        );
+        let mut count = 0;
+        app.db.update(|db| count = integrity::repair_all(db, &fix));
+        app.check_fields.message = Some(format!("Fixed: {}", count));
+    }
+    app.db.commit();
+    if ctx.input(|i| i.viewport().close_requested()) || close_request {
+        app.statement = Statement::Common;
+    }
+}
+
+fn fix_title(fix: &Fix) -> &'static str {
+    match fix {
+        Fix::CreateAccount(..) => "Create account",
+        Fix::Reassign(_) => "Move to account",
+        Fix::UnlinkReceipt => "Unlink receipt",
+        Fix::UnlinkTransfer => "Unlink transfer",
+        Fix::Deduplicate => "New ids for copies",
+        Fix::Trash => "Move to trash",
+    }
+}
+
+fn export_sheets(app: &App, db: &Database) -> Vec<Sheet> {
+    let year = app.import_fields.report_year;
+    match app.import_fields.export_table {
+        ExportTable::All => vec![
+            report::all_operations(db),
+            report::receipts(db),
+            report::subjects(db),
+            report::monthly(db, year),
+        ],
+        ExportTable::Operations => vec![report::all_operations(db)],
+        ExportTable::Receipts => vec![report::receipts(db)],
+        ExportTable::Subjects => vec![report::subjects(db)],
+        ExportTable::Monthly => vec![report::monthly(db, year)],
+    }
+}
+
+pub fn import(app: &mut App, ctx: &egui::Context, class: egui::ViewportClass) {
+    assert!(
+        class == egui::ViewportClass::Immediate,
+        "This egui backend doesn't support multiple viewports"
+    );
+    let mut close_request: bool = false;
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.label("Import file");
+        ui.add(egui::TextEdit::singleline(&mut app.import_fields.path));
+        cbox(ui, &mut app.import_fields.format, "Format");
+        if ui.button("Import").clicked() {
+            let result = match app.import_fields.format {
+                ImportFormat::ClientBank => client_bank::read(&app.import_fields.path)
+                    .map(|statement| client_bank::import(&app.db, &statement)),
+                ImportFormat::Ofx => ofx::read(&app.db, &app.import_fields.path),
+                ImportFormat::Qif => qif::read(&app.db, &app.import_fields.path),
+            };
+            match result {
+                Ok(result) => {
+                    let mut message = format!(
+                        "Read: {}, new accounts: {}, skipped (no account or date): {}",
+                        result.operations.len(),
+                        result.accounts.len(),
+                        result.unmatched
+                    );
+                    if !result.unknown_accounts.is_empty() {
+                        message += &format!(
+                            ", accounts not in the database: {}",
+                            result.unknown_accounts.join(", ")
+                        );
+                    }
+                    app.import_fields.message = Some(start_import(app, result, message));
+                }
+                Err(error) => app.import_fields.message = Some(error.to_string()),
+            }
+        }
+
+        ui.separator();
+        ui.label("Export file");
+        ui.add(egui::TextEdit::singleline(
+            &mut app.import_fields.export_path,
+        ));
+        cbox(ui, &mut app.import_fields.export_format, "Export format");
+        egui::ComboBox::from_label("Source")
+            .selected_text(
+                app.import_fields
+                    .export_view
+                    .and_then(|id| app.db.views.iter().find(|view| view.id == id))
+                    .map(|view| view.name.clone())
+                    .unwrap_or("All operations".to_string()),
+            )
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut app.import_fields.export_view, None, "All operations");
+                for view in app.db.views.iter() {
+                    ui.selectable_value(
+                        &mut app.import_fields.export_view,
+                        Some(view.id),
+                        &view.name,
+                    );
+                }
+            });
+        if matches!(
+            app.import_fields.export_format,
+            ExportFormat::Csv | ExportFormat::Xlsx
+        ) {
+            cbox(ui, &mut app.import_fields.export_table, "Table");
+            ui.horizontal(|ui| {
+                ui.label("Report year");
+                ui.add(egui::DragValue::new(&mut app.import_fields.report_year).speed(1));
+            });
+        }
+        if ui.button("Export").clicked() {
+            let path = &app.import_fields.export_path;
+            let source = match app.import_fields.export_view {
+                Some(id) => app
+                    .db
+                    .views
+                    .iter()
+                    .find(|view| view.id == id)
+                    .map(|view| view.database(&app.db).map_err(|error| error.to_string())),
+                None => None,
+            }
+            .transpose();
+            app.import_fields.message = Some(match source {
+                Ok(source) => {
+                    let db: &Database = source.as_ref().unwrap_or(&app.db);
+                    let result = match app.import_fields.export_format {
+                        ExportFormat::Ofx1 => ofx::write(db, path, ofx::Version::Sgml),
+                        ExportFormat::Ofx2 => ofx::write(db, path, ofx::Version::Xml),
+                        ExportFormat::Qif => qif::write(db, path),
+                        ExportFormat::Ledger => ledger::write(db, path, ledger::Dialect::Ledger),
+                        ExportFormat::Beancount => {
+                            ledger::write(db, path, ledger::Dialect::Beancount)
+                        }
+                        ExportFormat::Csv => sheet::write_csv(&export_sheets(app, db), path, ';'),
+                        ExportFormat::Xlsx => sheet::write_xlsx(&export_sheets(app, db), path),
+                    };
+                    match result {
+                        Ok(()) => format!("Exported to {}", path),
+                        Err(error) => error.to_string(),
+                    }
+                }
+                Err(error) => error,
+            });
+        }
+        if let Some(message) = &app.import_fields.message {
+            ui.label(message);
+        }
+        if ui.button("Close").clicked() {
+            close_request = true;
+        }
+    });
+    if ctx.input(|i| i.viewport().close_requested()) || close_request {
+        app.statement = Statement::Common;
+    }
+}
+
+// Без похожих операций импорт сразу записывается в базу, иначе открывается
+// окно выбора решения по каждому кандидату
+fn start_import(app: &mut App, result: ImportResult, message: String) -> String {
+    let candidates = find_duplicates(&app.db.operations, &result.operations);
+    if candidates.is_empty() {
+        dedup::commit(&mut app.db, result, &[], &[]);
+        app.db.commit();
+        return format!("{}, imported", message);
+    }
+    let resolutions = candidates
+        .iter()
+        .map(|candidate| candidate.default_resolution())
+        .collect();
+    let count = candidates.len();
+    app.pending_import = Some(PendingImport {
+        result,
+        candidates,
+        resolutions,
+    });
+    app.statement = Statement::ReviewImport;
+    format!("{}, possible duplicates: {}", message, count)
+}
+
+fn operation_label(app: &App, operation: &Operation) -> String {
+    let account = app
+        .db
+        .account(operation.account_id)
+        .map(|account| account.name.as_str())
+        .unwrap_or("?");
+    format!(
+        "{} {} {:?} {} {} {}",
+        operation.date_time.format("%d.%m.%Y"),
+        account,
+        operation.direction,
+        operation.summary,
+        operation.counterparty.as_deref().unwrap_or(""),
+        operation.reference.as_deref().unwrap_or("")
+    )
+}
+
+pub fn review_import(app: &mut App, ctx: &egui::Context, class: egui::ViewportClass) {
+    assert!(
+        class == egui::ViewportClass::Immediate,
+        "This egui backend doesn't support multiple viewports"
+    );
+    let mut close_request: bool = false;
+    let mut apply = false;
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let Some(pending) = app.pending_import.as_ref() else {
+            close_request = true;
+            return;
+        };
+        let mut rows = Vec::new();
+        for candidate in pending.candidates.iter() {
+            let incoming = &pending.result.operations[candidate.incoming];
+            let existing = app.db.operation(candidate.existing).or_else(|| {
+                pending
+                    .result
+                    .operations
+                    .iter()
+                    .find(|operation| operation.id == candidate.existing)
+            });
+            rows.push((
+                operation_label(app, incoming),
+                existing
+                    .map(|operation| operation_label(app, operation))
+                    .unwrap_or_default(),
+            ));
+        }
+        let pending = app.pending_import.as_mut().unwrap();
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - 30.0)
+            .show(ui, |ui| {
+                egui::Grid::new("duplicates").striped(true).show(ui, |ui| {
+                    ui.label("Incoming");
+                    ui.label("Existing");
+                    ui.label("Confidence");
+                    ui.label("Resolution");
+                    ui.end_row();
+                    for (i, (incoming, existing)) in rows.iter().enumerate() {
+                        ui.label(incoming);
+                        ui.label(existing);
+                        match pending.candidates[i].confidence {
+                            Confidence::Certain => ui.colored_label(egui::Color32::RED, "Certain"),
+                            Confidence::Likely => ui.colored_label(egui::Color32::YELLOW, "Likely"),
+                            Confidence::Possible => ui.label("Possible"),
+                        };
+                        ui.push_id(i, |ui| cbox(ui, &mut pending.resolutions[i], ""));
+                        ui.end_row();
+                    }
+                });
+            });
+        ui.horizontal(|ui| {
+            if ui.button("Apply").clicked() {
+                apply = true;
+                close_request = true;
+            }
+            if ui.button("Cancel").clicked() {
+                close_request = true;
+            }
+        });
+    });
+    if ctx.input(|i| i.viewport().close_requested()) || close_request {
+        if let Some(pending) = app.pending_import.take()
+            && apply
+        {
+            dedup::commit(
+                &mut app.db,
+                pending.result,
+                &pending.candidates,
+                &pending.resolutions,
+            );
+            app.db.commit();
+        }
+        app.statement = Statement::Common;
+    }
+}
+
+pub fn csv_import(app: &mut App, ctx: &egui::Context, class: egui::ViewportClass) {
+    assert!(
+        class == egui::ViewportClass::Immediate,
+        "This egui backend doesn't support multiple viewports"
+    );
+    let mut close_request: bool = false;
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let fields = &mut app.csv_import_fields;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Profile")
+                .selected_text(fields.profile.name.clone())
+                .show_ui(ui, |ui| {
+                    for profile in app.db.csv_profiles.iter() {
+                        if ui.selectable_label(false, &profile.name).clicked() {
+                            fields.profile = profile.clone();
+                            // Колонок в профиле может быть меньше, чем в загруженном файле
+                            let width = fields.rows.iter().map(|row| row.len()).max().unwrap_or(0);
+                            if fields.profile.columns.len() < width {
+                                fields.profile.columns.resize(width, csv::ColumnRole::Skip);
+                            }
+                        }
+                    }
+                });
+            ui.add(egui::TextEdit::singleline(&mut fields.profile.name).hint_text("Bank"));
+            if ui.button("Save profile").clicked() && !fields.profile.name.is_empty() {
+                app.db.upsert_csv_profile(fields.profile.clone());
+                app.db.commit();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.add(egui::TextEdit::singleline(&mut fields.path));
+            cbox(ui, &mut fields.profile.encoding, "Encoding");
+            egui::ComboBox::from_label("Delimiter")
+                .selected_text(format!("{:?}", fields.profile.delimiter))
+                .show_ui(ui, |ui| {
+                    for delimiter in [';', ',', '\t', '|'] {
+                        ui.selectable_value(
+                            &mut fields.profile.delimiter,
+                            delimiter,
+                            format!("{:?}", delimiter),
+                        );
+                    }
+                });
+            if ui.button("Load").clicked() {
+                match csv::read(&fields.path, &fields.profile) {
+                    Ok(rows) => {
+                        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+                        fields.profile.columns.resize(width, csv::ColumnRole::Skip);
+                        fields.rows = rows;
+                        fields.message = None;
+                    }
+                    Err(error) => fields.message = Some(error.to_string()),
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Header rows");
+            ui.add(egui::DragValue::new(&mut fields.profile.skip_rows).range(0..=20));
+            ui.label("Date format");
+            ui.add(
+                egui::TextEdit::singleline(&mut fields.profile.date_format).desired_width(140.0),
+            );
+            ui.label("Income marker");
+            ui.add(
+                egui::TextEdit::singleline(&mut fields.profile.debet_marker).desired_width(100.0),
+            );
+        });
+
+        egui::ComboBox::from_label("Account")
+            .selected_text(
+                app.db
+                    .account(fields.account_id)
+                    .map(|account| account.name.clone())
+                    .unwrap_or_default(),
+            )
+            .show_ui(ui, |ui| {
+                for element in app.db.accounts.iter() {
+                    ui.selectable_value(&mut fields.account_id, element.id, &element.name);
+                }
+            });
+
+        StripBuilder::new(ui)
+            .size(Size::exact(350.0))
+            .vertical(|mut strip| {
+                strip.cell(|ui| {
+                    egui::ScrollArea::horizontal().show(ui, |ui| {
+                        let mut table = TableBuilder::new(ui)
+                            .striped(true)
+                            .id_salt("csv_preview_table")
+                            .cell_layout(egui::Layout::left_to_right(egui::Align::Center));
+                        for _ in 0..fields.profile.columns.len() {
+                            table = table.column(Column::auto().resizable(true));
+                        }
+                        table
+                            .header(30.0, |mut header| {
+                                for (i, role) in fields.profile.columns.iter_mut().enumerate() {
+                                    header.col(|ui| {
+                                        cbox(ui, role, &format!("#{}", i + 1));
+                                    });
+                                }
+                            })
+                            .body(|mut body| {
+                                for (i, element) in fields.rows.iter().take(20).enumerate() {
+                                    body.row(20.0, |mut row| {
+                                        let width = fields.profile.columns.len();
+                                        for value in element.iter().take(width) {
+                                            row.col(|ui| {
+                                                if i < fields.profile.skip_rows {
+                                                    ui.weak(value);
+                                                } else {
+                                                    ui.label(value);
+                                                }
+                                            });
+                                        }
+                                    });
+                                }
+                            });
+                    });
+                });
+            });
+
+        let selected = app.db.account(fields.account_id).is_some();
+        if ui
+            .add_enabled(selected, egui::Button::new("Import"))
+            .clicked()
+        {
+            let result = csv::import(&app.db, &fields.profile, &fields.rows, fields.account_id);
+            let message = match result {
+                Ok(result) => {
+                    let message = format!("Read: {}", result.operations.len());
+                    start_import(app, result, message)
+                }
+                Err(error) => error.to_string(),
+            };
+            app.csv_import_fields.message = Some(message);
+        }
+        if let Some(message) = &app.csv_import_fields.message {
+            ui.label(message);
+        }
+        if ui.button("Close").clicked() {
+            close_request = true;
+        }
+    });
+    if ctx.input(|i| i.viewport().close_requested()) || close_request {
+        app.statement = Statement::Common;
+    }
+}
+
+fn search_bar(app: &mut App, ui: &mut Ui) {
+    let fields = &mut app.search_fields;
+    let mut run = false;
+    ui.horizontal(|ui| {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut fields.text)
+                .hint_text("place:Пятёрочка amount>1000 date:2026-03 item:молоко type:Buy")
+                .desired_width(400.0),
+        );
+        run = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        run |= ui.button("Search").clicked();
+        if let Some(error) = &fields.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+    });
+    if run {
+        match Query::parse(&fields.text) {
+            Ok(query) => {
+                fields.hits = search::search(&app.db, &query);
+                fields.revision = Some(app.db.revision());
+                fields.error = None;
+                app.statement = Statement::Search;
+            }
+            Err(error) => fields.error = Some(error.to_string()),
+        }
+    }
+}
+
+fn hit_label(app: &App, query: &Query, hit: Hit) -> Option<String> {
+    let mut label = match hit {
+        Hit::Operation(id) => {
+            let operation = app.db.operation(id)?;
+            let place = operation
+                .receipt_id
+                .and_then(|id| app.db.receipt(id))
+                .and_then(|receipt| receipt.place.clone())
+                .or(operation.counterparty.clone())
+                .unwrap_or_default();
+            format!("{} {}", operation_label(app, operation), place)
+        }
+        Hit::Receipt(id) => {
+            let receipt = app.db.receipt(id)?;
+            format!(
+                "{} {} {} (receipt without operation)",
+                receipt.date_time.format("%d.%m.%Y"),
+                receipt.summary,
+                receipt.place.clone().unwrap_or_default()
+            )
+        }
+    };
+    let items = search::matched_items(&app.db, query, hit);
+    if !items.is_empty() {
+        label.push_str(&format!(": {}", items.join(", ")));
+    }
+    Some(label)
+}
+
+// Результаты поиска, новые первыми; Open открывает операцию или чек
+pub fn search(app: &mut App, ctx: &egui::Context, class: egui::ViewportClass) {
+    assert!(
+        class == egui::ViewportClass::Immediate,
+        "This egui backend doesn't support multiple viewports"
+    );
+    let mut close_request: bool = false;
+    let mut open: Option<Hit> = None;
+    let query = Query::parse(&app.search_fields.text).unwrap_or_default();
+    // Главное окно остаётся доступным: после правки или отмены поиск повторяется
+    if app.search_fields.revision != Some(app.db.revision()) {
+        app.search_fields.hits = search::search(&app.db, &query);
+        app.search_fields.revision = Some(app.db.revision());
+    }
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.label(format!(
+            "{}: {} found",
+            app.search_fields.text,
+            app.search_fields.hits.len()
+        ));
+        let hits = &app.search_fields.hits;
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - 30.0)
+            .show_rows(ui, 20.0, hits.len(), |ui, range| {
+                for hit in hits[range].iter() {
+                    let Some(label) = hit_label(app, &query, *hit) else {
+                        continue;
+                    };
+                    ui.horizontal(|ui| {
+                        if ui.button("Open").clicked() {
+                            open = Some(*hit);
+                        }
+                        ui.label(label);
+                    });
+                }
+            });
+        if ui.button("Close").clicked() {
+            close_request = true;
+        }
+    });
+    match open {
+        Some(Hit::Operation(op_id)) => open_operation(app, op_id),
+        Some(Hit::Receipt(rec_id)) => open_free_receipt(app, rec_id),
+        None => {
+            if ctx.input(|i| i.viewport().close_requested()) || close_request {
+                app.statement = Statement::Common;
+            }
+        }
+    }
+}
+
+// Счёт с операциями: перенос операций на другой счёт или удаление вместе с ними
+pub fn delete_account(
+    app: &mut App,
+    acc_id: Uuid,
+    ctx: &egui::Context,
+    class: egui::ViewportClass,
+) {
+    assert!(
+        class == egui::ViewportClass::Immediate,
+        "This egui backend doesn't support multiple viewports"
+    );
+    // Счёт мог исчезнуть после отмены или повтора в главном окне
+    let Some(name) = app.db.account(acc_id).map(|account| account.name.clone()) else {
+        app.delete_fields = DeleteFields::new();
+        app.statement = Statement::Common;
+        return;
+    };
+    let mut close_request: bool = false;
+    let mut mode: Option<AccountOperations> = None;
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let count = app.db.account_operations(acc_id).count();
+        ui.label(format!("Account '{}' has {} operations", name, count));
+        let fields = &mut app.delete_fields;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("reassign account")
+                .selected_text(
+                    app.db
+                        .account(fields.target)
+                        .map(|account| account.name.clone())
+                        .unwrap_or_default(),
+                )
+                .show_ui(ui, |ui| {
+                    for account in app.db.accounts.iter().filter(|a| a.id != acc_id) {
+                        ui.selectable_value(&mut fields.target, account.id, &account.name);
+                    }
+                });
+            if ui.button("Move operations and delete").clicked() {
+                mode = Some(AccountOperations::Reassign(fields.target));
+            }
+        });
+        if ui.button("Delete with operations and receipts").clicked() {
+            mode = Some(AccountOperations::Cascade);
+        }
+        if let Some(message) = &fields.message {
+            ui.colored_label(egui::Color32::RED, message);
+        }
+        if ui.button("Cancel").clicked() {
+            close_request = true;
+        }
+    });
+    if let Some(mode) = mode {
+        match trash::delete_account(&mut app.db, acc_id, mode) {
+            Ok(_) => {
+                app.db.commit();
+                app.selected = None;
+                close_request = true;
+            }
+            Err(error) => app.delete_fields.message = Some(error.to_string()),
+        }
+    }
+    if ctx.input(|i| i.viewport().close_requested()) || close_request {
+        app.delete_fields = DeleteFields::new();
+        app.statement = Statement::Common;
+    }
+}
+
+// Операция с чеком: удалить чек вместе с ней или оставить его без операции
+pub fn delete_operation(
+    app: &mut App,
+    op_id: Uuid,
+    ctx: &egui::Context,
+    class: egui::ViewportClass,
+) {
+    assert!(
+        class == egui::ViewportClass::Immediate,
+        "This egui backend doesn't support multiple viewports"
+    );
+    // Операция могла исчезнуть после отмены или повтора в главном окне
+    let Some(operation) = app.db.operation(op_id) else {
+        app.delete_fields = DeleteFields::new();
+        app.statement = Statement::Common;
+        return;
+    };
+    let label = operation_label(app, operation);
+    let mut close_request: bool = false;
+    let mut with_receipt: Option<bool> = None;
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.label(label);
+        ui.label("The operation has a receipt");
+        if ui.button("Delete with receipt").clicked() {
+            with_receipt = Some(true);
+        }
+        if ui.button("Keep receipt").clicked() {
+            with_receipt = Some(false);
+        }
+        if let Some(message) = &app.delete_fields.message {
+            ui.colored_label(egui::Color32::RED, message);
+        }
+        if ui.button("Cancel").clicked() {
+            close_request = true;
+        }
+    });
+    if let Some(with_receipt) = with_receipt {
+        match trash::delete_operation(&mut app.db, op_id, with_receipt) {
+            Ok(_) => {
+                app.db.commit();
+                app.selected = None;
+                close_request = true;
+            }
+            Err(error) => app.delete_fields.message = Some(error.to_string()),
+        }
+    }
+    if ctx.input(|i| i.viewport().close_requested()) || close_request {
+        app.delete_fields = DeleteFields::new();
+        app.statement = Statement::Common;
+    }
+}
+
+fn deleted_label(group: &Deleted) -> String {
+    let mut accounts = Vec::new();
+    let mut operations = 0;
+    let mut receipts = 0;
+    for item in group.items.iter() {
+        match item {
+            Item::Account(account) => accounts.push(account.name.clone()),
+            Item::Operation(_) => operations += 1,
+            Item::Receipt(_) => receipts += 1,
+        }
+    }
+    let mut parts = Vec::new();
+    if !accounts.is_empty() {
+        parts.push(format!("account {}", accounts.join(", ")));
+    }
+    if operations > 0 {
+        parts.push(format!("operations: {}", operations));
+    }
+    if receipts > 0 {
+        parts.push(format!("receipts: {}", receipts));
+    }
+    format!(
+        "{} {}",
+        group.date_time.format("%d.%m.%Y %H:%M"),
+        parts.join(", ")
+    )
+}
+
+// Корзина и чеки без операций
+pub fn trash(app: &mut App, ctx: &egui::Context, class: egui::ViewportClass) {
+    assert!(
+        class == egui::ViewportClass::Immediate,
+        "This egui backend doesn't support multiple viewports"
+    );
+    let mut close_request: bool = false;
+    let mut restore: Option<Uuid> = None;
+    let mut purge: Option<Option<Uuid>> = None;
+    let mut open: Option<Uuid> = None;
+    let mut delete: Option<Uuid> = None;
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let orphans = trash::orphan_receipts(&app.db);
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - 60.0)
+            .show(ui, |ui| {
+                ui.heading("Deleted");
+                if app.db.trash.is_empty() {
+                    ui.label("Trash is empty");
+                }
+                for group in app.db.trash.iter().rev() {
+                    ui.horizontal(|ui| {
+                        if ui.button("Restore").clicked() {
+                            restore = Some(group.id);
+                        }
+                        if ui.button("Delete forever").clicked() {
+                            purge = Some(Some(group.id));
+                        }
+                        ui.label(deleted_label(group));
+                    });
+                }
+                ui.separator();
+                ui.heading("Receipts without operation");
+                for rec_id in orphans.iter() {
+                    let receipt = app.db.receipt(*rec_id).unwrap();
+                    ui.horizontal(|ui| {
+                        if ui.button("Open").clicked() {
+                            open = Some(*rec_id);
+                        }
+                        if ui.button("Delete").clicked() {
+                            delete = Some(*rec_id);
+                        }
+                        ui.label(format!(
+                            "{} {} {}",
+                            receipt.date_time.format("%d.%m.%Y"),
+                            receipt.summary,
+                            receipt.place.clone().unwrap_or_default()
+                        ));
+                    });
+                }
+            });
+        if let Some(message) = &app.delete_fields.message {
+            ui.colored_label(egui::Color32::RED, message);
+        }
+        ui.horizontal(|ui| {
+            if !app.db.trash.is_empty() && ui.button("Empty trash").clicked() {
+                purge = Some(None);
+            }
+            if ui.button("Close").clicked() {
+                close_request = true;
+            }
+        });
+    });
+    if let Some(id) = restore {
+        app.delete_fields.message = trash::restore(&mut app.db, id)
+            .err()
+            .map(|error| error.to_string());
+    }
+    if let Some(id) = purge {
+        trash::purge(&mut app.db, id);
+    }
+    if let Some(rec_id) = delete
+        && let Err(error) = trash::delete_receipt(&mut app.db, rec_id)
+    {
+        app.delete_fields.message = Some(error.to_string());
+    }
+    app.db.commit();
+    if let Some(rec_id) = open {
+        open_free_receipt(app, rec_id);
+    } else if ctx.input(|i| i.viewport().close_requested()) || close_request {
+        app.delete_fields = DeleteFields::new();
+        app.statement = Statement::Common;
+    }
+}
+
+// Выделение снимается, если запись исчезла после отмены или повтора
+fn forget_missing(app: &mut App) {
+    let exists = match app.selected {
+        Some(Selection::Account(id)) => app.db.account(id).is_some(),
+        Some(Selection::Operation(id)) => app.db.operation(id).is_some(),
+        None => true,
+    };
+    if !exists {
+        app.selected = None;
+    }
+}
+
+fn undo(app: &mut App, count: usize) {
+    for _ in 0..count {
+        app.db.undo();
+    }
+    forget_missing(app);
+}
+
+fn redo(app: &mut App, count: usize) {
+    for _ in 0..count {
+        app.db.redo();
+    }
+    forget_missing(app);
+}
+
+// Ctrl+Z - отмена, Ctrl+Shift+Z - повтор. В текстовом поле они остаются за полем.
+pub fn shortcuts(app: &mut App, ctx: &egui::Context) {
+    if ctx.memory(|memory| memory.focused().is_some()) {
+        return;
+    }
+    let redo_shortcut = egui::KeyboardShortcut::new(
+        egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+        egui::Key::Z,
+    );
+    let undo_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+    // Ctrl+Z совпадает и с нажатием вместе с Shift, поэтому повтор проверяется первым
+    if ctx.input_mut(|i| i.consume_shortcut(&redo_shortcut)) {
+        redo(app, 1);
+    } else if ctx.input_mut(|i| i.consume_shortcut(&undo_shortcut)) {
+        undo(app, 1);
+    }
+}
+
+fn command_label(command: &Command) -> String {
+    format!(
+        "{} {}",
+        command.date_time.format("%d.%m.%Y %H:%M:%S"),
+        command.title
+    )
+}
+
+// Список команд: выполненные сверху вниз, под ними отменённые. Щелчок по
+// команде отменяет или повторяет всё до неё включительно.
+pub fn history(app: &mut App, ctx: &egui::Context, class: egui::ViewportClass) {
+    assert!(
+        class == egui::ViewportClass::Immediate,
+        "This egui backend doesn't support multiple viewports"
+    );
+    let mut close_request: bool = false;
+    let mut undo_count: usize = 0;
+    let mut redo_count: usize = 0;
+    let mut keep_history_changed = false;
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let history = &app.db.history;
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!history.done.is_empty(), egui::Button::new("Undo"))
+                .clicked()
+            {
+                undo_count = 1;
+            }
+            if ui
+                .add_enabled(!history.undone.is_empty(), egui::Button::new("Redo"))
+                .clicked()
+            {
+                redo_count = 1;
+            }
+        });
+        keep_history_changed = ui
+            .checkbox(&mut app.settings.keep_history, "Save history with the file")
+            .changed();
+        ui.separator();
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - 30.0)
+            .show(ui, |ui| {
+                if ui
+                    .selectable_label(history.done.is_empty(), "Initial state")
+                    .clicked()
+                {
+                    undo_count = history.done.len();
+                }
+                let done = history.done.len();
+                for (index, command) in history.done.iter().enumerate() {
+                    if ui
+                        .selectable_label(index + 1 == done, command_label(command))
+                        .clicked()
+                    {
+                        undo_count = done - index - 1;
+                    }
+                }
+                for (index, command) in history.undone.iter().rev().enumerate() {
+                    let text = egui::RichText::new(command_label(command)).weak();
+                    if ui.selectable_label(false, text).clicked() {
+                        redo_count = index + 1;
+                    }
+                }
+            });
+        if ui.button("Close").clicked() {
+            close_request = true;
+        }
+    });
+    if keep_history_changed {
+        app.settings_changed();
+    }
+    undo(app, undo_count);
+    redo(app, redo_count);
+    if ctx.input(|i| i.viewport().close_requested()) || close_request {
+        app.statement = Statement::Common;
+    }
+}
+
+// Состояние записи в журнале с отступами для чтения
+fn pretty_json(text: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(text)
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .unwrap_or_else(|_| text.to_string())
+}
+
+// Состояние записи в журнале или ошибка его преобразования в JSON
+fn json_state(ui: &mut Ui, state: Result<String, serde_json::Error>) {
+    match state {
+        Ok(text) => {
+            ui.monospace(pretty_json(&text));
+        }
+        Err(error) => {
+            ui.colored_label(egui::Color32::RED, error.to_string());
+        }
+    }
+}
+
+// Журнал изменений: новые записи сверху, отбор, подробности выбранной записи и
+// выгрузка отобранного
+pub fn audit(app: &mut App, ctx: &egui::Context, class: egui::ViewportClass) {
+    assert!(
+        class == egui::ViewportClass::Immediate,
+        "This egui backend doesn't support multiple viewports"
+    );
+    let mut close_request: bool = false;
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Your name");
+            let hint = app.settings.user_name();
+            if ui
+                .add(egui::TextEdit::singleline(&mut app.settings.user).hint_text(hint))
+                .changed()
+            {
+                app.settings_changed();
+                app.db.set_user(&app.settings.user_name());
+            }
+        });
+        let fields = &mut app.audit_fields;
+        ui.horizontal(|ui| {
+            let entity_title = |entity: &str| match entity {
+                "" => "All records".to_string(),
+                entity => entity.to_string(),
+            };
+            egui::ComboBox::from_id_salt("audit entity")
+                .selected_text(entity_title(&fields.filter.entity))
+                .show_ui(ui, |ui| {
+                    for entity in ["", "account", "operation", "receipt"] {
+                        ui.selectable_value(
+                            &mut fields.filter.entity,
+                            entity.to_string(),
+                            entity_title(entity),
+                        );
+                    }
+                });
+            ui.label("User");
+            ui.add(egui::TextEdit::singleline(&mut fields.filter.user).desired_width(120.0));
+            ui.label("Text");
+            ui.add(
+                egui::TextEdit::singleline(&mut fields.filter.text)
+                    .hint_text("command, id or date"),
+            );
+        });
+        let entries: Vec<&AuditEntry> = app
+            .db
+            .audit
+            .iter()
+            .rev()
+            .filter(|entry| fields.filter.matches(entry))
+            .collect();
+        ui.label(format!("{} / {}", entries.len(), app.db.audit.len()));
+
+        StripBuilder::new(ui)
+            .size(Size::exact(250.0))
+            .vertical(|mut strip| {
+                strip.cell(|ui| {
+                    TableBuilder::new(ui)
+                        .id_salt("audit_table")
+                        .striped(true)
+                        .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+                        .column(Column::initial(130.0))
+                        .column(Column::initial(120.0))
+                        .column(Column::initial(60.0))
+                        .column(Column::initial(80.0))
+                        .column(Column::remainder().clip(true))
+                        .min_scrolled_height(0.0)
+                        .max_scroll_height(250.0)
+                        .sense(egui::Sense::click())
+                        .header(20.0, |mut header| {
+                            for title in ["Date", "User", "Action", "Record", "Command"] {
+                                header.col(|ui| {
+                                    ui.strong(title);
+                                });
+                            }
+                        })
+                        .body(|body| {
+                            body.rows(20.0, entries.len(), |mut row| {
+                                let entry = entries[row.index()];
+                                row.set_selected(fields.selected == Some(entry.id));
+                                row.col(|ui| {
+                                    ui.label(
+                                        entry.date_time.format("%d.%m.%Y %H:%M:%S").to_string(),
+                                    );
+                                });
+                                row.col(|ui| {
+                                    ui.label(&entry.user);
+                                });
+                                row.col(|ui| {
+                                    ui.label(entry.change.action());
+                                });
+                                row.col(|ui| {
+                                    ui.label(entry.change.entity());
+                                });
+                                row.col(|ui| {
+                                    ui.label(&entry.command);
+                                });
+                                if row.response().clicked() {
+                                    fields.selected = Some(entry.id);
+                                }
+                            });
+                        });
+                });
+            });
+
+        ui.separator();
+        if let Some(entry) = entries
+            .iter()
+            .find(|entry| Some(entry.id) == fields.selected)
+        {
+            if let Some(id) = entry.change.id() {
+                ui.label(format!("{} {}", entry.change.entity(), id));
+            }
+            egui::ScrollArea::vertical()
+                .id_salt("audit_details")
+                .max_height(ui.available_height() - 60.0)
+                .show(ui, |ui| {
+                    ui.columns(2, |columns| {
+                        columns[0].strong("Before");
+                        json_state(&mut columns[0], entry.before());
+                        columns[1].strong("After");
+                        json_state(&mut columns[1], entry.after());
+                    });
+                });
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut fields.path));
+            if ui.button("Export").clicked() {
+                fields.message = Some(match audit::sheet(&entries) {
+                    Ok(audit) => {
+                        let sheets = [audit];
+                        let result = if fields.path.to_lowercase().ends_with(".xlsx") {
+                            sheet::write_xlsx(&sheets, &fields.path)
+                        } else {
+                            sheet::write_csv(&sheets, &fields.path, ';')
+                        };
+                        match result {
+                            Ok(()) => format!("Exported to {}", fields.path),
+                            Err(error) => error.to_string(),
+                        }
+                    }
+                    Err(error) => error.to_string(),
+                });
+            }
+            if let Some(message) = &fields.message {
+                ui.label(message);
+            }
+        });
+        if ui.button("Close").clicked() {
+            close_request = true;
+        }
+    });
+    if ctx.input(|i| i.viewport().close_requested()) || close_request {
+        app.statement = Statement::Common;
+    }
+}
@@ -1,16 +1,28 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 #![allow(rustdoc::missing_crate_level_docs)] // it's an example
 
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate, Timelike};
+use std::time::{Duration, Instant};
+use rust_decimal::Decimal;
+use strum::EnumIter;
 
 use uuid::Uuid;
 
-use crate::{
+use crate::app::cbox::*;
+use finance_core::{
     account::{self},
-    app::cbox::*,
+    audit::AuditFilter,
+    card::Card,
     database::*,
+    dedup::{Candidate, Resolution},
+    exchange::{ImportResult, csv::CsvProfile},
+    integrity::Problem,
     operation::*,
     receipt::{self},
+    search::Hit,
+    storage,
+    store::Store,
+    view::TableView,
 };
 
 use eframe::egui::{self};
@@ -18,6 +30,7 @@ use eframe::egui::{self};
 mod cbox;
 mod compare;
 mod context;
+mod settings;
 mod table;
 
 enum Selection {
@@ -30,6 +43,16 @@ enum Statement {
     EditAccount(Uuid),
     EditOperation(Uuid),
     EditReceipt(Uuid, Uuid, bool),
+    Check,
+    Import,
+    CsvImport,
+    ReviewImport,
+    Search,
+    DeleteAccount(Uuid),
+    DeleteOperation(Uuid),
+    Trash,
+    History,
+    Audit,
     ThripleDialog,
 }
 
@@ -38,6 +61,12 @@ struct AccountFields {
     account_type: account::AccountType,
     number: String,
     bik: String,
+    currency: receipt::Currency,
+    cards: Vec<Card>,
+    card_pan: String,
+    card_expiry: String,
+    card_holder: String,
+    card_error: Option<String>,
 }
 
 impl AccountFields {
@@ -47,6 +76,12 @@ impl AccountFields {
             account_type: account::AccountType::Cash,
             number: "".to_string(),
             bik: "100000000".to_string(),
+            currency: receipt::Currency::Rub,
+            cards: Vec::new(),
+            card_pan: "".to_string(),
+            card_expiry: "".to_string(),
+            card_holder: "".to_string(),
+            card_error: None,
         }
     }
 }
@@ -60,6 +95,10 @@ struct OperationFields {
     summary: String,
     direction: FinanseDirection,
     receipt: Option<Uuid>,
+    reference: String,
+    counterparty: String,
+    note: String,
+    category: String,
 }
 
 impl OperationFields {
@@ -73,6 +112,91 @@ impl OperationFields {
             summary: "0".to_string(),
             direction: FinanseDirection::Credit,
             receipt: None,
+            reference: "".to_string(),
+            counterparty: "".to_string(),
+            note: "".to_string(),
+            category: "".to_string(),
+        }
+    }
+}
+
+struct SubjectFields {
+    subject: receipt::Subject,
+    count: String,
+    price: String,
+    summary: String,
+}
+
+impl SubjectFields {
+    fn new(subject: receipt::Subject) -> Self {
+        Self {
+            count: subject.count.to_string(),
+            price: subject.price.to_string(),
+            summary: subject.summary.to_string(),
+            subject,
+        }
+    }
+}
+
+struct SlipFields {
+    terminal_id: String,
+    op_type: receipt::CashlessOpType,
+    date: NaiveDate,
+    hour: u32,
+    minute: u32,
+    summary: String,
+    currency: receipt::Currency,
+    comm_summary: String,
+    auth_code: String,
+    card: String,
+    address: String,
+    place: String,
+    payment_system: String,
+    doc_id: String,
+}
+
+impl SlipFields {
+    fn new() -> Self {
+        Self {
+            terminal_id: "".to_string(),
+            op_type: receipt::CashlessOpType::Payment,
+            date: chrono::Local::now().date_naive(),
+            hour: 0,
+            minute: 0,
+            summary: "".to_string(),
+            currency: receipt::Currency::Rub,
+            comm_summary: "".to_string(),
+            auth_code: "".to_string(),
+            card: "".to_string(),
+            address: "".to_string(),
+            place: "".to_string(),
+            payment_system: "".to_string(),
+            doc_id: "".to_string(),
+        }
+    }
+
+    fn from_slip(slip: &receipt::Slip) -> Self {
+        Self {
+            terminal_id: slip.id.to_string(),
+            op_type: slip.op_type,
+            date: slip.date_time.date(),
+            hour: slip.date_time.time().hour(),
+            minute: slip.date_time.time().minute(),
+            summary: slip.summary.to_string(),
+            currency: slip.currency,
+            comm_summary: slip
+                .comm_summary
+                .map(|value| value.to_string())
+                .unwrap_or_default(),
+            auth_code: slip.auth_code.clone(),
+            card: slip.card.clone(),
+            address: slip.address.clone().unwrap_or_default(),
+            place: slip.place.clone().unwrap_or_default(),
+            payment_system: slip.payment_system.clone().unwrap_or_default(),
+            doc_id: slip
+                .doc_id
+                .map(|value| value.to_string())
+                .unwrap_or_default(),
         }
     }
 }
@@ -84,7 +208,7 @@ struct ReceiptFields {
     calculation_type: receipt::CalculationType,
     address: String,
     place: String,
-    subjects: Vec<receipt::Subject>,
+    subjects: Vec<SubjectFields>,
     summary: String,
     cash: String,
     cashless: String,
@@ -93,6 +217,16 @@ struct ReceiptFields {
     in_kind: String,
     vat: String,
     url: String,
+    fn_number: String,
+    fd_number: String,
+    fiscal_sign: String,
+    shift_number: String,
+    seller_inn: String,
+    taxation_type: receipt::TaxationType,
+    cashier: String,
+    slip: Option<SlipFields>,
+    // Ошибка удаления чека
+    message: Option<String>,
 }
 
 impl ReceiptFields {
@@ -113,48 +247,378 @@ impl ReceiptFields {
             in_kind: "".to_string(),
             vat: "".to_string(),
             url: "".to_string(),
+            fn_number: "".to_string(),
+            fd_number: "".to_string(),
+            fiscal_sign: "".to_string(),
+            shift_number: "".to_string(),
+            seller_inn: "".to_string(),
+            taxation_type: receipt::TaxationType::Osn,
+            cashier: "".to_string(),
+            slip: None,
+            message: None,
+        }
+    }
+
+    fn from_receipt(element: &receipt::Receipt) -> Self {
+        let decimal = |value: Option<Decimal>| value.map(|v| v.to_string()).unwrap_or_default();
+        let number = |value: Option<u32>| value.map(|v| v.to_string()).unwrap_or_default();
+        Self {
+            date: element.date_time.date(),
+            hour: element.date_time.time().hour(),
+            minute: element.date_time.time().minute(),
+            calculation_type: element.calculation_type,
+            address: element.address.clone().unwrap_or_default(),
+            place: element.place.clone().unwrap_or_default(),
+            subjects: element
+                .subjects
+                .iter()
+                .map(|subject| SubjectFields::new(subject.clone()))
+                .collect(),
+            summary: element.summary.to_string(),
+            cash: decimal(element.cash),
+            cashless: decimal(element.cashless),
+            prepayment: decimal(element.prepayment),
+            postpayment: decimal(element.postpayment),
+            in_kind: decimal(element.in_kind),
+            vat: decimal(element.vat),
+            url: element.url.clone().unwrap_or_default(),
+            fn_number: element.fn_number.clone().unwrap_or_default(),
+            fd_number: number(element.fd_number),
+            fiscal_sign: number(element.fiscal_sign),
+            shift_number: number(element.shift_number),
+            seller_inn: element.seller_inn.clone().unwrap_or_default(),
+            taxation_type: element.taxation_type,
+            cashier: element.cashier.clone().unwrap_or_default(),
+            slip: element.slip.as_ref().map(SlipFields::from_slip),
+            message: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, EnumIter)]
+enum ImportFormat {
+    ClientBank,
+    Ofx,
+    Qif,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, EnumIter)]
+enum ExportFormat {
+    Ofx1,
+    Ofx2,
+    Qif,
+    Ledger,
+    Beancount,
+    Csv,
+    Xlsx,
+}
+
+// Таблицы для выгрузки в CSV и XLSX
+#[derive(Debug, PartialEq, Clone, Copy, EnumIter)]
+enum ExportTable {
+    All,
+    Operations,
+    Receipts,
+    Subjects,
+    Monthly,
+}
+
+struct ImportFields {
+    path: String,
+    format: ImportFormat,
+    export_path: String,
+    export_format: ExportFormat,
+    export_table: ExportTable,
+    report_year: i32,
+    // Сохранённый вид, из которого берутся операции; None - вся база
+    export_view: Option<Uuid>,
+    message: Option<String>,
+}
+
+impl ImportFields {
+    fn new() -> Self {
+        Self {
+            path: "".to_string(),
+            format: ImportFormat::ClientBank,
+            export_path: "".to_string(),
+            export_format: ExportFormat::Ofx2,
+            export_table: ExportTable::All,
+            report_year: chrono::Local::now().year(),
+            export_view: None,
+            message: None,
+        }
+    }
+}
+
+struct CsvImportFields {
+    path: String,
+    profile: CsvProfile,
+    account_id: Uuid,
+    rows: Vec<Vec<String>>,
+    message: Option<String>,
+}
+
+impl CsvImportFields {
+    fn new() -> Self {
+        Self {
+            path: "".to_string(),
+            profile: CsvProfile::new(),
+            account_id: Uuid::nil(),
+            rows: Vec::new(),
+            message: None,
+        }
+    }
+}
+
+struct SearchFields {
+    text: String,
+    hits: Vec<Hit>,
+    // Ревизия базы, по которой найдены записи
+    revision: Option<u64>,
+    error: Option<String>,
+}
+
+impl SearchFields {
+    fn new() -> Self {
+        Self {
+            text: "".to_string(),
+            hits: Vec::new(),
+            revision: None,
+            error: None,
+        }
+    }
+}
+
+struct DeleteFields {
+    // Счёт, на который переносятся операции удаляемого
+    target: Uuid,
+    message: Option<String>,
+}
+
+impl DeleteFields {
+    fn new() -> Self {
+        Self {
+            target: Uuid::nil(),
+            message: None,
+        }
+    }
+}
+
+struct CheckFields {
+    problems: Vec<Problem>,
+    // Ревизия базы, по которой найдены проблемы
+    revision: Option<u64>,
+    // Счёт для переноса операций с несуществующим счётом
+    target: Uuid,
+    // Тип и валюта счёта, создаваемого вместо несуществующего
+    account_type: account::AccountType,
+    currency: receipt::Currency,
+    message: Option<String>,
+}
+
+impl CheckFields {
+    fn new() -> Self {
+        Self {
+            problems: Vec::new(),
+            revision: None,
+            target: Uuid::nil(),
+            account_type: account::AccountType::Account,
+            currency: receipt::Currency::default(),
+            message: None,
+        }
+    }
+}
+
+struct AuditFields {
+    filter: AuditFilter,
+    selected: Option<Uuid>,
+    path: String,
+    message: Option<String>,
+}
+
+impl AuditFields {
+    fn new() -> Self {
+        Self {
+            filter: AuditFilter::default(),
+            selected: None,
+            path: "audit.csv".to_string(),
+            message: None,
+        }
+    }
+}
+
+struct ViewFields {
+    name: String,
+    error: Option<String>,
+}
+
+impl ViewFields {
+    fn new() -> Self {
+        Self {
+            name: "".to_string(),
+            error: None,
+        }
+    }
+}
+
+// Положение в таблице операций. Строки нумеруются в порядке показа.
+struct OperationsView {
+    // Операции после отбора и сортировки и то, по чему они посчитаны
+    rows: Vec<Uuid>,
+    source: Option<(u64, TableView, Option<Uuid>)>,
+    // Показываемый сохранённый вид
+    view: Option<Uuid>,
+    cursor: usize,
+    // Первая и последняя строки, показанные в прошлом кадре
+    visible: (usize, usize),
+    // Прокрутить таблицу к cursor в следующем кадре
+    scroll: bool,
+    jump_date: NaiveDate,
+}
+
+impl OperationsView {
+    fn new() -> Self {
+        Self {
+            rows: Vec::new(),
+            source: None,
+            view: None,
+            cursor: 0,
+            visible: (0, 0),
+            scroll: false,
+            jump_date: chrono::Local::now().date_naive(),
         }
     }
 }
 
+// Результат импорта, ожидающий решения по возможным дубликатам
+struct PendingImport {
+    result: ImportResult,
+    candidates: Vec<Candidate>,
+    resolutions: Vec<Resolution>,
+}
+
 pub struct App {
-    db: Database,
+    db: Store,
     file: String,
+    // Файл не прочитался: открыта пустая база, сохранение отключено
+    load_error: Option<String>,
+    // Результат последнего сохранения
+    save_message: Option<String>,
     selected: Option<Selection>,
     statement: Statement,
     account_fields: AccountFields,
     operation_fields: OperationFields,
     receipt_fields: ReceiptFields,
+    import_fields: ImportFields,
+    csv_import_fields: CsvImportFields,
+    pending_import: Option<PendingImport>,
+    operations_view: OperationsView,
+    search_fields: SearchFields,
+    view_fields: ViewFields,
+    delete_fields: DeleteFields,
+    audit_fields: AuditFields,
+    check_fields: CheckFields,
+    settings: settings::Settings,
+    settings_error: Option<String>,
+    // Время последнего несохранённого изменения настроек
+    settings_changed: Option<Instant>,
 }
 
+// Настройки записываются через секунду после последнего изменения, а не на
+// каждое нажатие и кадр перетаскивания столбца, и при выходе
+const SETTINGS_DELAY: Duration = Duration::from_secs(1);
+
 impl App {
     pub fn new(arg: Option<&String>) -> Self {
-        if let Some(arg) = arg {
+        let mut app = if let Some(arg) = arg {
+            let loaded = storage::open(arg).and_then(|storage| storage.load());
+            let (db, load_error) = match loaded {
+                Ok(db) => (db, None),
+                Err(error) => (Database::new(), Some(format!("{}: {}", arg, error))),
+            };
             Self {
-                db: Database::load(arg.clone()),
+                db: Store::new(db),
                 file: arg.clone(),
+                load_error,
+                save_message: None,
                 selected: None,
                 statement: Statement::Common,
                 account_fields: AccountFields::new(),
                 operation_fields: OperationFields::new(),
                 receipt_fields: ReceiptFields::new(),
+                import_fields: ImportFields::new(),
+                csv_import_fields: CsvImportFields::new(),
+                pending_import: None,
+                operations_view: OperationsView::new(),
+                search_fields: SearchFields::new(),
+                view_fields: ViewFields::new(),
+                delete_fields: DeleteFields::new(),
+                audit_fields: AuditFields::new(),
+                check_fields: CheckFields::new(),
+                settings: settings::Settings::load(),
+                settings_error: None,
+                settings_changed: None,
             }
         } else {
             Self {
-                db: Database::new(),
+                db: Store::new(Database::new()),
                 file: "file.json".to_string(),
+                load_error: None,
+                save_message: None,
                 selected: None,
                 statement: Statement::Common,
                 account_fields: AccountFields::new(),
                 operation_fields: OperationFields::new(),
                 receipt_fields: ReceiptFields::new(),
+                import_fields: ImportFields::new(),
+                csv_import_fields: CsvImportFields::new(),
+                pending_import: None,
+                operations_view: OperationsView::new(),
+                search_fields: SearchFields::new(),
+                view_fields: ViewFields::new(),
+                delete_fields: DeleteFields::new(),
+                audit_fields: AuditFields::new(),
+                check_fields: CheckFields::new(),
+                settings: settings::Settings::load(),
+                settings_error: None,
+                settings_changed: None,
             }
-        }
+        };
+        app.db.set_user(&app.settings.user_name());
+        app
+    }
+
+    fn settings_changed(&mut self) {
+        self.settings_changed = Some(Instant::now());
+    }
+
+    fn save_settings(&mut self) {
+        self.settings_changed = None;
+        self.settings_error = self
+            .settings
+            .save()
+            .err()
+            .map(|error| format!("Settings are not saved: {}", error));
     }
 }
 
 impl eframe::App for App {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if self.settings_changed.is_some() {
+            self.save_settings();
+        }
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if let Some(changed) = self.settings_changed {
+            match SETTINGS_DELAY.checked_sub(changed.elapsed()) {
+                Some(left) if !left.is_zero() => ctx.request_repaint_after(left),
+                _ => self.save_settings(),
+            }
+        }
+        if let Statement::Common = self.statement {
+            context::shortcuts(self, ctx);
+        }
         egui::CentralPanel::default().show(ctx, |ui| context::main_central_panel(self, ui));
         egui::SidePanel::right("right_panel")
             .resizable(true)
@@ -203,6 +667,108 @@ impl eframe::App for App {
                 );
             }
 
+            Statement::Check => {
+                ctx.show_viewport_immediate(
+                    egui::ViewportId::from_hash_of("check window"),
+                    egui::ViewportBuilder::default()
+                        .with_title("Check")
+                        .with_inner_size([600.0, 400.0]),
+                    |ctx, class| context::check(self, ctx, class),
+                );
+            }
+
+            Statement::Import => {
+                ctx.show_viewport_immediate(
+                    egui::ViewportId::from_hash_of("import window"),
+                    egui::ViewportBuilder::default()
+                        .with_title("Import")
+                        .with_inner_size([400.0, 200.0]),
+                    |ctx, class| context::import(self, ctx, class),
+                );
+            }
+
+            Statement::CsvImport => {
+                ctx.show_viewport_immediate(
+                    egui::ViewportId::from_hash_of("csv import window"),
+                    egui::ViewportBuilder::default()
+                        .with_title("CSV import")
+                        .with_inner_size([800.0, 600.0]),
+                    |ctx, class| context::csv_import(self, ctx, class),
+                );
+            }
+
+            Statement::ReviewImport => {
+                ctx.show_viewport_immediate(
+                    egui::ViewportId::from_hash_of("review import window"),
+                    egui::ViewportBuilder::default()
+                        .with_title("Possible duplicates")
+                        .with_inner_size([900.0, 400.0]),
+                    |ctx, class| context::review_import(self, ctx, class),
+                );
+            }
+
+            Statement::Search => {
+                ctx.show_viewport_immediate(
+                    egui::ViewportId::from_hash_of("search window"),
+                    egui::ViewportBuilder::default()
+                        .with_title("Search")
+                        .with_inner_size([800.0, 400.0]),
+                    |ctx, class| context::search(self, ctx, class),
+                );
+            }
+
+            Statement::DeleteAccount(uuid) => {
+                let acc_id = *uuid;
+                ctx.show_viewport_immediate(
+                    egui::ViewportId::from_hash_of("delete account window"),
+                    egui::ViewportBuilder::default()
+                        .with_title("Delete account")
+                        .with_inner_size([500.0, 200.0]),
+                    |ctx, class| context::delete_account(self, acc_id, ctx, class),
+                );
+            }
+
+            Statement::DeleteOperation(uuid) => {
+                let op_id = *uuid;
+                ctx.show_viewport_immediate(
+                    egui::ViewportId::from_hash_of("delete operation window"),
+                    egui::ViewportBuilder::default()
+                        .with_title("Delete operation")
+                        .with_inner_size([500.0, 200.0]),
+                    |ctx, class| context::delete_operation(self, op_id, ctx, class),
+                );
+            }
+
+            Statement::Trash => {
+                ctx.show_viewport_immediate(
+                    egui::ViewportId::from_hash_of("trash window"),
+                    egui::ViewportBuilder::default()
+                        .with_title("Trash")
+                        .with_inner_size([600.0, 400.0]),
+                    |ctx, class| context::trash(self, ctx, class),
+                );
+            }
+
+            Statement::History => {
+                ctx.show_viewport_immediate(
+                    egui::ViewportId::from_hash_of("history window"),
+                    egui::ViewportBuilder::default()
+                        .with_title("History")
+                        .with_inner_size([500.0, 400.0]),
+                    |ctx, class| context::history(self, ctx, class),
+                );
+            }
+
+            Statement::Audit => {
+                ctx.show_viewport_immediate(
+                    egui::ViewportId::from_hash_of("audit window"),
+                    egui::ViewportBuilder::default()
+                        .with_title("Audit log")
+                        .with_inner_size([900.0, 600.0]),
+                    |ctx, class| context::audit(self, ctx, class),
+                );
+            }
+
             Statement::ThripleDialog => {
                 todo!()
             }
@@ -0,0 +1,77 @@
+// Настройки интерфейса, общие для всех файлов базы. Хранятся в settings.json в
+// каталоге настроек платформы; отсутствующий или испорченный файл заменяется
+// настройками по умолчанию.
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io;
+use std::path::PathBuf;
+
+use finance_core::audit;
+use finance_core::view::TableView;
+
+const FILE: &str = "settings.json";
+
+#[derive(Serialize, Deserialize, Default, PartialEq, Clone)]
+pub struct Settings {
+    #[serde(default)]
+    pub operations_table: TableView,
+    // История отмены сохраняется в файл базы
+    #[serde(default)]
+    pub keep_history: bool,
+    // Имя в журнале изменений; пустое - имя пользователя системы
+    #[serde(default)]
+    pub user: String,
+}
+
+// Каталог настроек: %APPDATA% в Windows, ~/Library/Application Support в macOS,
+// $XDG_CONFIG_HOME или ~/.config в остальных системах
+fn path() -> Option<PathBuf> {
+    let home = || env::var_os("HOME").map(PathBuf::from);
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home().map(|home| home.join(".config")))
+    };
+    Some(base?.join("finance-counter").join(FILE))
+}
+
+impl Settings {
+    // Прежние версии держали настройки в рабочем каталоге; они читаются, пока
+    // в каталоге настроек файла нет
+    pub fn load() -> Self {
+        let mut settings: Settings = path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .or_else(|| std::fs::read_to_string(FILE).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        settings.operations_table.normalize();
+        settings
+    }
+
+    // Имя для журнала изменений: заданное в настройках или пользователь@компьютер
+    pub fn user_name(&self) -> String {
+        match self.user.trim() {
+            "" => audit::default_user(),
+            user => user.to_string(),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = path() else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "не найден каталог настроек",
+            ));
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
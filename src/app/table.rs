@@ -1,165 +1,301 @@
-use crate::app::{self, compare::response_compare};
+use crate::app::{self, compare::response_compare, context};
 use app::Selection;
-use eframe::egui::{self, Response, Ui};
-use egui_extras::{Column, Size, StripBuilder, TableBuilder}; //
+use chrono::NaiveDate;
+use eframe::egui::{self, Key, Response, Ui};
+use egui_extras::{Column, TableBuilder};
+use finance_core::search::Query;
+use finance_core::view::{Field, TableView};
+use uuid::Uuid;
 
-pub enum TableType {
-    Account,
-    Operation,
-}
+const ROW_HEIGHT: f32 = 30.0;
+const HEADER_HEIGHT: f32 = 30.0;
+const FILTER_HEADER_HEIGHT: f32 = 50.0;
 
-pub fn table(app: &mut app::App, table_type: TableType, ui: &mut Ui) -> Response {
-    match table_type {
-        TableType::Account => Some(ui.label(format!("Accounts"))),
-        TableType::Operation => Some(ui.label(format!("Operations"))),
-    };
+fn contents(ui: &mut Ui, text: String, response: &mut Option<Response>) {
+    response_compare(ui.label(text), response);
+}
 
-    StripBuilder::new(ui)
-        .size(Size::exact(250.0)) // for the table
-        .vertical(|mut strip| {
-            strip.cell(|ui| {
-                let mut table = TableBuilder::new(ui).resizable(true).striped(true);
-                match table_type {
-                    TableType::Account => table = table.id_salt("accounts_table"),
-                    TableType::Operation => table = table.id_salt("operations_table"),
-                }
-                table = table.cell_layout(egui::Layout::left_to_right(egui::Align::Center));
-                match table_type {
-                    TableType::Account => {
-                        table = table.column(Column::auto()).column(Column::auto());
-                    }
-                    TableType::Operation => {
-                        table = table
-                            .column(Column::auto())
-                            .column(Column::auto())
-                            .column(Column::auto());
-                    }
+// Строятся только видимые строки, поэтому размер базы не влияет на кадр
+pub fn accounts(app: &mut app::App, ui: &mut Ui, height: f32) {
+    ui.label("Accounts");
+    TableBuilder::new(ui)
+        .id_salt("accounts_table")
+        .resizable(true)
+        .striped(true)
+        .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+        .column(Column::auto())
+        .column(Column::remainder())
+        .min_scrolled_height(0.0)
+        .max_scroll_height(height)
+        .sense(egui::Sense::click())
+        .header(HEADER_HEIGHT, |mut header| {
+            header.col(|ui| {
+                ui.strong("ID");
+            });
+            header.col(|ui| {
+                ui.strong("Name");
+            });
+        })
+        .body(|body| {
+            body.rows(ROW_HEIGHT, app.db.accounts.len(), |mut row| {
+                let i = &app.db.accounts[row.index()];
+                row.set_selected(
+                    matches!(app.selected, Some(Selection::Account(id)) if id == i.id),
+                );
+                let mut inner_response: Option<Response> = None;
+                row.col(|ui| contents(ui, format!("ID '{}'", i.id), &mut inner_response));
+                row.col(|ui| contents(ui, format!("Name '{}'", i.name), &mut inner_response));
+                response_compare(row.response(), &mut inner_response);
+                if let Some(response) = inner_response
+                    && (response.double_clicked() || response.triple_clicked())
+                {
+                    app.selected = Some(Selection::Account(i.id))
                 }
-                table = table
-                    .min_scrolled_height(0.0)
-                    .max_scroll_height(500.0)
-                    .sense(egui::Sense::click());
+            });
+        });
+}
 
-                table
-                    .header(30.0, |mut header| {
-                        match table_type {
-                            TableType::Account => {
-                                header.col(|ui| {
-                                    ui.strong("ID");
-                                    // ui.push_id(id_salt, add_contents)
-                                });
-                                header.col(|ui| {
-                                    ui.strong("Name");
-                                });
-                            }
-                            TableType::Operation => {
-                                header.col(|ui| {
-                                    ui.strong("Account");
-                                });
-                                header.col(|ui| {
-                                    ui.strong("Date / Time");
-                                });
-                                header.col(|ui| {
-                                    ui.strong("Sum");
-                                });
-                            }
-                        }
-                    })
-                    .body(|mut body| {
-                        //let mut temp_response: Option<Response> = None;
-                        let contents =
-                            |ui: &mut eframe::egui::Ui,
-                             text: String,
-                             response: &mut Option<Response>| {
-                                response_compare(ui.label(text), response);
-                            };
-                        match table_type {
-                            TableType::Account => {
-                                for i in &app.db.accounts {
-                                    body.row(30.0, |mut row| {
-                                        let mut inner_response: Option<Response> = None;
-                                        row.col(|ui| {
-                                            contents(
-                                                ui,
-                                                format!("ID '{}'", i.id),
-                                                &mut inner_response,
-                                            )
-                                        });
+// Строки пересчитываются только после изменения базы или настройки таблицы
+fn refresh(app: &mut app::App) {
+    let table = &app.settings.operations_table;
+    let view = &mut app.operations_view;
+    let current = matches!(&view.source,
+        Some((revision, source, saved)) if *revision == app.db.revision()
+            && source == table && *saved == view.view);
+    if !current {
+        // Вид, удалённый или с испорченным запросом, ничего не отбирает
+        let query = view
+            .view
+            .and_then(|id| app.db.views.iter().find(|saved| saved.id == id))
+            .and_then(|saved| Query::parse(&saved.query).ok())
+            .unwrap_or_default();
+        view.rows = table.rows(&app.db, &query);
+        view.source = Some((app.db.revision(), table.clone(), view.view));
+        view.cursor = view.cursor.min(view.rows.len().saturating_sub(1));
+    }
+}
 
-                                        row.col(|ui| {
-                                            contents(
-                                                ui,
-                                                format!("Name '{}'", i.name),
-                                                &mut inner_response,
-                                            )
-                                        });
+// Первая строка не раньше даты; при сортировке не по дате - ближайшая к ней
+fn row_of_date(app: &app::App, date: NaiveDate) -> usize {
+    let date_time = date.and_hms_opt(0, 0, 0).unwrap();
+    let rows = &app.operations_view.rows;
+    let date_of = |id: &Uuid| app.db.operation(*id).unwrap().date_time;
+    let table = &app.settings.operations_table;
+    if table.sort == Field::Date {
+        if table.descending {
+            rows.partition_point(|id| date_of(id) >= date_time)
+                .saturating_sub(1)
+        } else {
+            rows.partition_point(|id| date_of(id) < date_time)
+        }
+    } else {
+        rows.iter()
+            .enumerate()
+            .min_by_key(|(_, id)| (date_of(id) - date_time).abs())
+            .map(|(index, _)| index)
+            .unwrap_or_default()
+    }
+}
 
-                                        let row_response = row.response();
+// Клавиши листания действуют, пока не редактируется текстовое поле
+fn paging(app: &mut app::App, ui: &Ui) {
+    if ui.ctx().memory(|memory| memory.focused().is_some()) {
+        return;
+    }
+    let count = app.operations_view.rows.len();
+    if count == 0 {
+        return;
+    }
+    let view = &mut app.operations_view;
+    let page = (view.visible.1 - view.visible.0).max(1);
+    let cursor = ui.input(|i| {
+        if i.key_pressed(Key::PageDown) {
+            Some(view.cursor.saturating_add(page))
+        } else if i.key_pressed(Key::PageUp) {
+            Some(view.cursor.saturating_sub(page))
+        } else if i.key_pressed(Key::ArrowDown) {
+            Some(view.cursor.saturating_add(1))
+        } else if i.key_pressed(Key::ArrowUp) {
+            Some(view.cursor.saturating_sub(1))
+        } else if i.key_pressed(Key::Home) {
+            Some(0)
+        } else if i.key_pressed(Key::End) {
+            Some(count - 1)
+        } else {
+            None
+        }
+    });
+    if let Some(cursor) = cursor {
+        view.cursor = cursor.min(count - 1);
+        view.scroll = true;
+        app.selected = Some(Selection::Operation(view.rows[view.cursor]));
+    }
+}
 
-                                        response_compare(row_response, &mut inner_response);
+// Видимость и порядок столбцов
+fn columns(table: &mut TableView, ui: &mut Ui) {
+    for index in 0..table.columns.len() {
+        ui.horizontal(|ui| {
+            if ui.small_button("◀").clicked() {
+                table.move_column(index, -1);
+            }
+            if ui.small_button("▶").clicked() {
+                table.move_column(index, 1);
+            }
+            let column = &mut table.columns[index];
+            ui.checkbox(&mut column.visible, column.field.to_string());
+        });
+    }
+    if ui.button("Reset").clicked() {
+        *table = TableView::default();
+    }
+}
 
-                                        if let Some(response) = inner_response {
-                                            if response.double_clicked() {
-                                                println!("Double!");
-                                                println!("{}", i.id);
-                                                app.selected = Some(Selection::Account(i.id))
-                                            }
-                                            if response.triple_clicked() {
-                                                println!("Triple!");
-                                                println!("{}", i.id);
-                                                app.selected = Some(Selection::Account(i.id))
-                                            }
-                                        }
-                                    });
-                                }
-                            }
-                            TableType::Operation => {
-                                for i in &app.db.operations {
-                                    body.row(30.0, |mut row| {
-                                        let mut inner_response: Option<Response> = None;
-                                        row.col(|ui| {
-                                            contents(
-                                                ui,
-                                                format!("ID '{}'", i.account_id),
-                                                &mut inner_response,
-                                            )
-                                        });
+fn toolbar(app: &mut app::App, ui: &mut Ui) {
+    let count = app.operations_view.rows.len();
+    ui.horizontal(|ui| {
+        ui.label("Operations");
+        if let Some(view) = app
+            .operations_view
+            .view
+            .and_then(|id| app.db.views.iter().find(|view| view.id == id))
+        {
+            ui.strong(&view.name);
+        }
+        ui.menu_button("Columns", |ui| {
+            columns(&mut app.settings.operations_table, ui)
+        });
+        if app.settings.operations_table.is_filtered() && ui.button("Clear filters").clicked() {
+            for column in app.settings.operations_table.columns.iter_mut() {
+                column.filter.clear();
+            }
+        }
+        ui.add(
+            egui_extras::DatePickerButton::new(&mut app.operations_view.jump_date)
+                .id_salt("jump_date"),
+        );
+        if ui.button("Go to date").clicked() && count > 0 {
+            app.operations_view.cursor = row_of_date(app, app.operations_view.jump_date);
+            app.operations_view.scroll = true;
+        }
+        let view = &app.operations_view;
+        if count > 0 {
+            ui.label(format!(
+                "{}-{} / {}",
+                view.visible.0 + 1,
+                view.visible.1 + 1,
+                count
+            ));
+        } else {
+            ui.label(format!("0 / {}", app.db.operation_count()));
+        }
+    });
+}
+
+// Заголовок столбца: сортировка по щелчку и строка отбора
+fn header_cell(table: &mut TableView, field: Field, ui: &mut Ui) {
+    ui.vertical(|ui| {
+        let mut title = field.to_string();
+        if table.sort == field {
+            title.push_str(if table.descending { " ⏷" } else { " ⏶" });
+        }
+        if ui
+            .add(egui::Button::new(egui::RichText::new(title).strong()).frame(false))
+            .clicked()
+        {
+            table.sort_by(field);
+        }
+        let column = table
+            .columns
+            .iter_mut()
+            .find(|column| column.field == field)
+            .unwrap();
+        ui.add(
+            egui::TextEdit::singleline(&mut column.filter)
+                .hint_text("filter")
+                .desired_width(f32::INFINITY),
+        );
+    });
+}
 
-                                        row.col(|ui| {
-                                            contents(
-                                                ui,
-                                                format!("Name '{}'", i.date_time),
-                                                &mut inner_response,
-                                            )
-                                        });
+// Операции после отбора и сортировки; занимают всё оставшееся место
+pub fn operations(app: &mut app::App, ui: &mut Ui) {
+    let before = app.settings.clone();
+    refresh(app);
+    toolbar(app, ui);
+    paging(app, ui);
 
-                                        row.col(|ui| {
-                                            contents(
-                                                ui,
-                                                format!("Name '{}'", i.summary),
-                                                &mut inner_response,
-                                            )
-                                        });
-                                        let row_response = row.response();
+    let fields: Vec<Field> = app.settings.operations_table.visible().collect();
+    let mut table = TableBuilder::new(ui)
+        .id_salt("operations_table")
+        .resizable(true)
+        .striped(true)
+        .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+        .columns(
+            Column::initial(110.0).at_least(40.0).clip(true),
+            fields.len().saturating_sub(1),
+        )
+        .column(Column::remainder().at_least(40.0).clip(true))
+        .min_scrolled_height(0.0)
+        .max_scroll_height(f32::INFINITY)
+        .auto_shrink(false)
+        .sense(egui::Sense::click());
+    if app.operations_view.scroll {
+        table = table.scroll_to_row(app.operations_view.cursor, Some(egui::Align::Center));
+        app.operations_view.scroll = false;
+    }
 
-                                        response_compare(row_response, &mut inner_response);
-                                        if let Some(response) = inner_response {
-                                            if response.double_clicked() {
-                                                println!("Double!");
-                                                println!("{}", i.id);
-                                                app.selected = Some(Selection::Operation(i.id))
-                                            }
-                                            if response.triple_clicked() {
-                                                println!("Triple!");
-                                                println!("{}", i.id);
-                                                app.selected = Some(Selection::Operation(i.id))
-                                            }
-                                        };
-                                    });
-                                }
+    let mut visible: Option<(usize, usize)> = None;
+    let mut open_receipt: Option<Uuid> = None;
+    table
+        .header(FILTER_HEADER_HEIGHT, |mut header| {
+            for field in fields.iter() {
+                header.col(|ui| header_cell(&mut app.settings.operations_table, *field, ui));
+            }
+        })
+        .body(|body| {
+            let view = &mut app.operations_view;
+            body.rows(ROW_HEIGHT, view.rows.len(), |mut row| {
+                let index = row.index();
+                visible = Some(match visible {
+                    Some((first, last)) => (first.min(index), last.max(index)),
+                    None => (index, index),
+                });
+                let i = app.db.operation(view.rows[index]).unwrap();
+                row.set_selected(
+                    matches!(app.selected, Some(Selection::Operation(id)) if id == i.id),
+                );
+                let mut inner_response: Option<Response> = None;
+                for field in fields.iter() {
+                    row.col(|ui| {
+                        let text = field.text(&app.db, i);
+                        if *field == Field::Receipt && i.receipt_id.is_some() {
+                            if ui.link(text).clicked() {
+                                open_receipt = Some(i.id);
                             }
+                        } else {
+                            contents(ui, text, &mut inner_response);
                         }
                     });
+                }
+                response_compare(row.response(), &mut inner_response);
+                if let Some(response) = inner_response {
+                    if response.clicked() {
+                        view.cursor = index;
+                    }
+                    if response.double_clicked() || response.triple_clicked() {
+                        app.selected = Some(Selection::Operation(i.id))
+                    }
+                }
             });
-        })
+        });
+    if let Some(visible) = visible {
+        app.operations_view.visible = visible;
+    }
+    if let Some(op_id) = open_receipt {
+        context::open_receipt(app, op_id);
+    }
+    if app.settings != before {
+        app.settings_changed();
+    }
 }
@@ -0,0 +1,815 @@
+// Командная строка без окна: finance-counter <файл> <команда> [параметры].
+// Команды работают с той же базой, импортом, проверками и отчётами, что и GUI.
+
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime};
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use uuid::Uuid;
+
+use finance_core::account::AccountType;
+use finance_core::audit;
+use finance_core::database::Database;
+use finance_core::dedup::{self, find_duplicates};
+use finance_core::exchange::{ImportResult, client_bank, csv, ofx, qif};
+use finance_core::integrity::{self, Fix, Severity};
+use finance_core::money::parse_decimal;
+use finance_core::operation::{FinanseDirection, Operation, OperationType};
+use finance_core::receipt::Currency;
+use finance_core::report::{self, Cell, Sheet};
+use finance_core::storage::{self, StorageError};
+use finance_core::store::Store;
+use finance_core::view::SavedView;
+
+const USAGE: &str = "Usage: finance-counter <file> <command> [options] [--format table|json]
+
+Commands:
+  accounts list
+  ops list [--account <name|number|id>] [--view <name>]
+  ops add --account <name|number|id> --amount <sum> [--date <YYYY-MM-DD[ HH:MM]>]
+          [--type <OperationType>] [--category <text>] [--counterparty <text>]
+          [--reference <text>] [--note <text>]
+  import csv <path> --profile <name> --account <name|number|id> [--dry-run]
+  import 1c|ofx|qif <path> [--dry-run]
+  report monthly [--year <year>] [--view <name>]
+  check [--repair [--account-type <AccountType>] [--currency <code>]
+        [--reassign <name|number|id>]]
+                          list integrity problems; --repair applies the safe fixes.
+                          Operations of a missing account get a recovered account
+                          of the given type and currency (Account, RUB by default)
+                          or move to the --reassign account
+  convert <target file>   copy the database to another file; .sqlite, .sqlite3
+                          and .db files use SQLite, others JSON
+
+A negative amount is a withdrawal, a positive one is a deposit.
+Likely duplicates found on import are skipped.";
+
+// Флаги без значения
+const FLAGS: [&str; 2] = ["dry-run", "repair"];
+
+#[derive(Debug)]
+pub struct CliError(String);
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<StorageError> for CliError {
+    fn from(error: StorageError) -> Self {
+        CliError(error.to_string())
+    }
+}
+
+fn error<T>(message: impl Into<String>) -> Result<T, CliError> {
+    Err(CliError(message.into()))
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Output {
+    Table,
+    Json,
+}
+
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Self, CliError> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if let Some(key) = arg.strip_prefix("--") {
+                if FLAGS.contains(&key) {
+                    options.insert(key.to_string(), String::new());
+                } else if let Some((key, value)) = key.split_once('=') {
+                    options.insert(key.to_string(), value.to_string());
+                } else {
+                    let Some(value) = iter.next() else {
+                        return error(format!("missing value for --{}", key));
+                    };
+                    options.insert(key.to_string(), value.clone());
+                }
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+        Ok(Self {
+            positional,
+            options,
+        })
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+
+    fn require(&self, key: &str) -> Result<&str, CliError> {
+        match self.get(key) {
+            Some(value) => Ok(value),
+            None => error(format!("--{} is required", key)),
+        }
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.options.contains_key(key)
+    }
+
+    fn text(&self, key: &str) -> Option<String> {
+        self.get(key)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    }
+}
+
+fn find_view<'a>(db: &'a Database, name: &str) -> Result<&'a SavedView, CliError> {
+    match db.views.iter().find(|view| view.name == name) {
+        Some(view) => Ok(view),
+        None => error(format!("view '{}' not found", name)),
+    }
+}
+
+// Счёт по идентификатору, номеру или названию (без учёта регистра)
+fn find_account(db: &Database, key: &str) -> Result<Uuid, CliError> {
+    if let Ok(id) = Uuid::parse_str(key)
+        && db.accounts.iter().any(|account| account.id == id)
+    {
+        return Ok(id);
+    }
+    let found: Vec<Uuid> = db
+        .accounts
+        .iter()
+        .filter(|account| {
+            account.number == key || account.name.to_lowercase() == key.to_lowercase()
+        })
+        .map(|account| account.id)
+        .collect();
+    match found.as_slice() {
+        [id] => Ok(*id),
+        [] => error(format!("account '{}' not found", key)),
+        _ => error(format!("account '{}' is ambiguous, use its id", key)),
+    }
+}
+
+fn parse_date_time(value: &str) -> Result<NaiveDateTime, CliError> {
+    let value = value.trim();
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d").map(NaiveDateTime::from))
+        .or_else(|_| error(format!("invalid date '{}'", value)))
+}
+
+fn operation_type(value: &str) -> Result<OperationType, CliError> {
+    use strum::IntoEnumIterator;
+    OperationType::iter()
+        .find(|element| format!("{:?}", element).eq_ignore_ascii_case(value))
+        .map_or_else(|| error(format!("unknown operation type '{}'", value)), Ok)
+}
+
+fn account_type(value: &str) -> Result<AccountType, CliError> {
+    use strum::IntoEnumIterator;
+    AccountType::iter()
+        .find(|element| format!("{:?}", element).eq_ignore_ascii_case(value))
+        .map_or_else(|| error(format!("unknown account type '{}'", value)), Ok)
+}
+
+fn currency(value: &str) -> Result<Currency, CliError> {
+    use strum::IntoEnumIterator;
+    Currency::iter()
+        .find(|element| element.code().eq_ignore_ascii_case(value))
+        .map_or_else(|| error(format!("unknown currency '{}'", value)), Ok)
+}
+
+// Исправление операций с несуществующим счётом по параметрам check --repair
+fn unknown_account_fix(db: &Database, args: &Args) -> Result<Fix, CliError> {
+    if let Some(key) = args.get("reassign") {
+        return Ok(Fix::Reassign(find_account(db, key)?));
+    }
+    let account_type = match args.get("account-type") {
+        Some(value) => account_type(value)?,
+        None => AccountType::Account,
+    };
+    let currency = match args.get("currency") {
+        Some(value) => currency(value)?,
+        None => Currency::default(),
+    };
+    Ok(Fix::CreateAccount(account_type, currency))
+}
+
+fn json_value(cell: &Cell) -> serde_json::Value {
+    match cell {
+        Cell::Empty => serde_json::Value::Null,
+        Cell::Text(text) => serde_json::Value::from(text.as_str()),
+        Cell::Number(number) => number
+            .to_f64()
+            .map(serde_json::Value::from)
+            .unwrap_or(serde_json::Value::Null),
+        Cell::Integer(number) => serde_json::Value::from(*number),
+        Cell::DateTime(date_time) => {
+            serde_json::Value::from(date_time.format("%Y-%m-%dT%H:%M:%S").to_string())
+        }
+    }
+}
+
+fn table_value(cell: &Cell) -> String {
+    match cell {
+        Cell::Empty => String::new(),
+        Cell::Text(text) => text.replace('\n', " "),
+        Cell::Number(number) => format!("{:.2}", number),
+        Cell::Integer(number) => number.to_string(),
+        Cell::DateTime(date_time) => date_time.format("%Y-%m-%d %H:%M").to_string(),
+    }
+}
+
+// Таблица в текстовом виде или JSON, без перевода строки в конце
+fn render(sheet: &Sheet, output: Output) -> String {
+    match output {
+        Output::Json => {
+            let rows: Vec<serde_json::Value> = sheet
+                .rows
+                .iter()
+                .map(|row| {
+                    let object = sheet
+                        .columns
+                        .iter()
+                        .zip(row.iter())
+                        .map(|(column, cell)| (column.clone(), json_value(cell)))
+                        .collect();
+                    serde_json::Value::Object(object)
+                })
+                .collect();
+            serde_json::to_string_pretty(&rows).unwrap()
+        }
+        Output::Table => {
+            let values: Vec<Vec<String>> = sheet
+                .rows
+                .iter()
+                .map(|row| row.iter().map(table_value).collect())
+                .collect();
+            let widths: Vec<usize> = sheet
+                .columns
+                .iter()
+                .enumerate()
+                .map(|(i, column)| {
+                    values
+                        .iter()
+                        .map(|row| row[i].chars().count())
+                        .chain([column.chars().count()])
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+            let line = |cells: Vec<(String, bool)>| -> String {
+                cells
+                    .iter()
+                    .zip(widths.iter())
+                    .map(|((value, right), width)| {
+                        if *right {
+                            format!("{:>width$}", value, width = width)
+                        } else {
+                            format!("{:<width$}", value, width = width)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("  ")
+                    .trim_end()
+                    .to_string()
+            };
+            let mut lines = vec![line(
+                sheet.columns.iter().map(|c| (c.clone(), false)).collect(),
+            )];
+            for (row, cells) in values.into_iter().zip(sheet.rows.iter()) {
+                let aligned = row
+                    .into_iter()
+                    .zip(cells.iter())
+                    .map(|(value, cell)| {
+                        let right = matches!(cell, Cell::Number(_) | Cell::Integer(_));
+                        (value, right)
+                    })
+                    .collect();
+                lines.push(line(aligned));
+            }
+            lines.join("\n")
+        }
+    }
+}
+
+fn message(text: String, output: Output) -> String {
+    match output {
+        Output::Json => serde_json::json!({ "message": text }).to_string(),
+        Output::Table => text,
+    }
+}
+
+fn add_operation(db: &mut Database, args: &Args) -> Result<Operation, CliError> {
+    let account_id = find_account(db, args.require("account")?)?;
+    let raw_amount = args.require("amount")?;
+    let Some(amount) = parse_decimal(raw_amount).filter(|amount| !amount.is_zero()) else {
+        return error(format!("invalid amount '{}'", raw_amount));
+    };
+    let direction = if amount.is_sign_negative() {
+        FinanseDirection::Credit
+    } else {
+        FinanseDirection::Debet
+    };
+    let operation_type = match args.get("type") {
+        Some(value) => operation_type(value)?,
+        None => match direction {
+            FinanseDirection::Debet => OperationType::DebetingAccounts,
+            FinanseDirection::Credit => OperationType::WithdrawalFromAccounts,
+        },
+    };
+    let date_time = match args.get("date") {
+        Some(value) => parse_date_time(value)?,
+        None => Local::now().naive_local(),
+    };
+    Ok(Operation {
+        id: Uuid::new_v4(),
+        date_time,
+        account_id,
+        operation_type,
+        summary: amount.abs(),
+        direction,
+        receipt_id: None,
+        reference: args.text("reference"),
+        counterparty: args.text("counterparty"),
+        note: args.text("note"),
+        category: args.text("category"),
+        transfer_id: None,
+    })
+}
+
+fn import(db: &mut Store, args: &Args, format: &str, path: &str) -> Result<String, CliError> {
+    let result: ImportResult = match format {
+        "csv" => {
+            let name = args.require("profile")?;
+            let Some(profile) = db
+                .csv_profiles
+                .iter()
+                .find(|profile| profile.name == name)
+                .cloned()
+            else {
+                return error(format!("CSV profile '{}' not found", name));
+            };
+            let account_id = find_account(db, args.require("account")?)?;
+            let rows = csv::read(path, &profile).map_err(|e| CliError(e.to_string()))?;
+            csv::import(db, &profile, &rows, account_id)
+        }
+        "1c" => client_bank::read(path).map(|statement| client_bank::import(db, &statement)),
+        "ofx" => ofx::read(db, path),
+        "qif" => qif::read(db, path),
+        _ => return error(format!("unknown import format '{}'", format)),
+    }
+    .map_err(|e| CliError(e.to_string()))?;
+
+    let candidates = find_duplicates(&db.operations, &result.operations);
+    let resolutions: Vec<_> = candidates
+        .iter()
+        .map(|candidate| candidate.default_resolution())
+        .collect();
+    let skipped = resolutions
+        .iter()
+        .filter(|resolution| **resolution != dedup::Resolution::Keep)
+        .count();
+    let mut text = format!(
+        "read: {}, new accounts: {}, duplicates skipped: {}, kept as possible duplicates: {}, without account or date: {}",
+        result.operations.len(),
+        result.accounts.len(),
+        skipped,
+        candidates.len() - skipped,
+        result.unmatched
+    );
+    if !result.unknown_accounts.is_empty() {
+        text += &format!(
+            ", accounts not in the database: {}",
+            result.unknown_accounts.join(", ")
+        );
+    }
+    if !args.flag("dry-run") {
+        dedup::commit(db, result, &candidates, &resolutions);
+    }
+    Ok(text)
+}
+
+// Изменение базы с записью в журнал изменений от имени пользователя системы
+fn audited<T>(db: Database, change: impl FnOnce(&mut Store) -> T) -> (Database, T) {
+    let mut store = Store::new(db);
+    store.set_user(&audit::default_user());
+    let result = change(&mut store);
+    store.commit();
+    (store.into_database(), result)
+}
+
+// Разбор и выполнение команды: код возврата и вывод. Код 0 - успех, 1 - проверка
+// нашла нарушения, 2 - ошибка
+fn execute(file: &str, args: &[String]) -> Result<(i32, String), CliError> {
+    let args = Args::parse(args)?;
+    let output = match args.get("format") {
+        None | Some("table") => Output::Table,
+        Some("json") => Output::Json,
+        Some(other) => return error(format!("unknown output format '{}'", other)),
+    };
+    if !Path::new(file).exists() {
+        return error(format!("file '{}' not found", file));
+    }
+    let mut storage = storage::open(file)?;
+    let mut db = storage.load()?;
+    let words: Vec<&str> = args.positional.iter().map(String::as_str).collect();
+    let text = match words.as_slice() {
+        ["accounts", "list"] => render(&report::accounts(&db), output),
+        ["ops", "list"] if args.get("view").is_some() => {
+            let view = find_view(&db, args.get("view").unwrap())?;
+            let store = Store::new(db.clone());
+            let rows = view.rows(&store).or_else(|e| error(e.to_string()))?;
+            let list: Vec<&Operation> = rows
+                .iter()
+                .map(|id| store.operation(*id).unwrap())
+                .collect();
+            render(&report::operations(&store, &list), output)
+        }
+        ["ops", "list"] => {
+            let account = args
+                .get("account")
+                .map(|key| find_account(&db, key))
+                .transpose()?;
+            let mut list: Vec<&Operation> = db
+                .operations
+                .iter()
+                .filter(|operation| account.is_none_or(|id| operation.account_id == id))
+                .collect();
+            list.sort_by_key(|operation| (operation.date_time, operation.id));
+            render(&report::operations(&db, &list), output)
+        }
+        ["ops", "add"] => {
+            let operation = add_operation(&mut db, &args)?;
+            let id = operation.id;
+            let (db, ()) = audited(db, |store| store.upsert_operation(operation));
+            storage.save(&db)?;
+            message(format!("added operation {}", id), output)
+        }
+        ["import", format, path] => {
+            let (db, text) = audited(db, |store| import(store, &args, format, path));
+            let text = text?;
+            if !args.flag("dry-run") {
+                storage.save(&db)?;
+            }
+            message(text, output)
+        }
+        ["report", "monthly"] => {
+            let year = match args.get("year") {
+                Some(value) => value
+                    .parse()
+                    .or_else(|_| error(format!("invalid year '{}'", value)))?,
+                None => Local::now().year(),
+            };
+            let source = match args.get("view") {
+                Some(name) => {
+                    let store = Store::new(db.clone());
+                    let view = find_view(&db, name)?;
+                    Some(view.database(&store).or_else(|e| error(e.to_string()))?)
+                }
+                None => None,
+            };
+            render(
+                &report::monthly(source.as_ref().unwrap_or(&db), year),
+                output,
+            )
+        }
+        ["convert", target] => {
+            let mut target_storage = storage::open(target)?;
+            storage::convert(storage.as_ref(), target_storage.as_mut())?;
+            message(format!("converted {} to {}", file, target), output)
+        }
+        ["check"] => {
+            let db = if args.flag("repair") {
+                let fix = unknown_account_fix(&db, &args)?;
+                let (db, count) = audited(db, |store| {
+                    let mut count = 0;
+                    store.update(|db| count = integrity::repair_all(db, &fix));
+                    count
+                });
+                storage.save(&db)?;
+                eprintln!("fixed: {}", count);
+                db
+            } else {
+                db
+            };
+            let problems = integrity::check(&db);
+            let mut sheet = Sheet::new("Check", &["Severity", "Record", "Problem"]);
+            for problem in problems.iter() {
+                sheet.rows.push(vec![
+                    Cell::Text(problem.severity().to_string()),
+                    Cell::Text(problem.record().to_string()),
+                    Cell::Text(problem.to_string()),
+                ]);
+            }
+            // Замечания не считаются нарушениями
+            let code = if problems
+                .iter()
+                .any(|problem| problem.severity() != Severity::Info)
+            {
+                1
+            } else {
+                0
+            };
+            return Ok((code, render(&sheet, output)));
+        }
+        _ => return error(USAGE),
+    };
+    Ok((0, text))
+}
+
+// Запуск команды, если она указана после имени файла; иначе None и открывается
+// окно. Справка и версия проверяются первыми; другой параметр вместо имени файла
+// - ошибка, а не файл для окна.
+pub fn run(args: &[String]) -> Option<i32> {
+    match args {
+        [first, ..] if ["--help", "-h", "help"].contains(&first.as_str()) => {
+            println!("{}", USAGE);
+            Some(0)
+        }
+        [first, ..] if first == "--version" || first == "-V" => {
+            println!("finance-counter {}", env!("CARGO_PKG_VERSION"));
+            Some(0)
+        }
+        [first, ..] if first.starts_with('-') => {
+            eprintln!("unknown option '{}'\n\n{}", first, USAGE);
+            Some(2)
+        }
+        [] | [_] => None,
+        [file, rest @ ..] => Some(match execute(file, rest) {
+            Ok((code, text)) => {
+                println!("{}", text);
+                code
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                2
+            }
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use finance_core::account::Account;
+    use finance_core::exchange::csv::{ColumnRole, CsvProfile};
+    use finance_core::view::TableView;
+    use rust_decimal::dec;
+
+    fn words(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn temp(extension: &str) -> String {
+        let path =
+            std::env::temp_dir().join(format!("finance-counter-{}.{}", Uuid::new_v4(), extension));
+        path.to_str().unwrap().to_string()
+    }
+
+    fn remove(path: &str) {
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(format!("{}.audit.jsonl", path));
+    }
+
+    // Файл базы со счётом Main и приходом 100 на него в марте 2026
+    fn file(change: impl FnOnce(&mut Database)) -> String {
+        let account = Account {
+            id: Uuid::new_v4(),
+            name: "Main".to_string(),
+            account_type: AccountType::Account,
+            number: "40817810000000000001".to_string(),
+            bik: 0,
+            sum: 0,
+            cards: Vec::new(),
+            currency: Currency::Rub,
+        };
+        let mut db = Database::new();
+        db.operations.push(Operation {
+            id: Uuid::new_v4(),
+            date_time: NaiveDate::from_ymd_opt(2026, 3, 1).unwrap().into(),
+            account_id: account.id,
+            operation_type: OperationType::DebetingAccounts,
+            summary: dec!(100),
+            direction: FinanseDirection::Debet,
+            receipt_id: None,
+            reference: None,
+            counterparty: None,
+            note: None,
+            category: Some("Зарплата".to_string()),
+            transfer_id: None,
+        });
+        db.accounts.push(account);
+        change(&mut db);
+        let path = temp("json");
+        storage::open(&path).unwrap().save(&db).unwrap();
+        path
+    }
+
+    fn load(path: &str) -> Database {
+        storage::open(path).unwrap().load().unwrap()
+    }
+
+    fn json(text: &str) -> serde_json::Value {
+        serde_json::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn parse_arguments() {
+        let args = Args::parse(&words(
+            "import csv a.csv --profile Bank --dry-run --account=Main --format json",
+        ))
+        .unwrap();
+        assert_eq!(args.positional, vec!["import", "csv", "a.csv"]);
+        assert_eq!(args.get("profile"), Some("Bank"));
+        assert_eq!(args.get("account"), Some("Main"));
+        assert_eq!(args.get("format"), Some("json"));
+        assert!(args.flag("dry-run"));
+        assert!(!args.flag("repair"));
+        assert!(args.require("note").is_err());
+        assert!(Args::parse(&words("ops add --amount")).is_err());
+    }
+
+    #[test]
+    fn help_version_and_window() {
+        assert_eq!(run(&words("--help")), Some(0));
+        assert_eq!(run(&words("-h")), Some(0));
+        assert_eq!(run(&words("--version")), Some(0));
+        assert_eq!(run(&words("--unknown")), Some(2));
+        // Без команды открывается окно
+        assert_eq!(run(&[]), None);
+        assert_eq!(run(&words("base.json")), None);
+    }
+
+    #[test]
+    fn missing_file_and_unknown_command() {
+        let path = temp("json");
+        assert!(execute(&path, &words("accounts list")).is_err());
+        let path = file(|_| {});
+        assert!(execute(&path, &words("accounts remove")).is_err());
+        assert!(execute(&path, &words("accounts list --format xml")).is_err());
+        remove(&path);
+    }
+
+    #[test]
+    fn accounts_list() {
+        let path = file(|_| {});
+        let (code, text) = execute(&path, &words("accounts list --format json")).unwrap();
+        assert_eq!(code, 0);
+        let rows = json(&text);
+        assert_eq!(rows[0]["Name"], "Main");
+        assert_eq!(rows[0]["Balance"], 100.0);
+        let (_, text) = execute(&path, &words("accounts list")).unwrap();
+        assert!(text.starts_with("Id"));
+        assert!(text.contains("Main"));
+        remove(&path);
+    }
+
+    #[test]
+    fn ops_list() {
+        let path = file(|db| {
+            db.views.push(SavedView {
+                id: Uuid::new_v4(),
+                name: "Food".to_string(),
+                query: "category:еда".to_string(),
+                table: TableView::default(),
+            })
+        });
+        let (_, text) = execute(&path, &words("ops list --account main --format json")).unwrap();
+        assert_eq!(json(&text).as_array().unwrap().len(), 1);
+        let (_, text) = execute(&path, &words("ops list --view Food --format json")).unwrap();
+        assert!(json(&text).as_array().unwrap().is_empty());
+        assert!(execute(&path, &words("ops list --account Other")).is_err());
+        remove(&path);
+    }
+
+    #[test]
+    fn ops_add() {
+        let path = file(|_| {});
+        let (code, text) = execute(
+            &path,
+            &words("ops add --account Main --amount -250,50 --date 2026-03-02 --category Еда"),
+        )
+        .unwrap();
+        assert_eq!(code, 0);
+        assert!(text.starts_with("added operation"));
+        let db = load(&path);
+        let added = &db.operations[1];
+        assert_eq!(added.summary, dec!(250.50));
+        assert_eq!(added.direction, FinanseDirection::Credit);
+        assert_eq!(added.operation_type, OperationType::WithdrawalFromAccounts);
+        assert_eq!(added.category.as_deref(), Some("Еда"));
+        assert!(execute(&path, &words("ops add --account Main --amount 0")).is_err());
+        remove(&path);
+    }
+
+    #[test]
+    fn import_csv() {
+        let path = file(|db| {
+            let mut profile = CsvProfile::new();
+            profile.name = "Bank".to_string();
+            profile.columns = vec![ColumnRole::Date, ColumnRole::Amount];
+            profile.date_format = "%Y-%m-%d".to_string();
+            profile.skip_rows = 0;
+            db.csv_profiles.push(profile);
+        });
+        let source = temp("csv");
+        std::fs::write(&source, "2026-03-05;-40\n2026-03-06;15\n").unwrap();
+        let line = format!("import csv {} --profile Bank --account Main", source);
+
+        let (_, text) = execute(&path, &words(&format!("{} --dry-run", line))).unwrap();
+        assert!(text.starts_with("read: 2"));
+        assert_eq!(load(&path).operations.len(), 1);
+        execute(&path, &words(&line)).unwrap();
+        assert_eq!(load(&path).operations.len(), 3);
+        // Повторный импорт пропускает те же операции
+        let (_, text) = execute(&path, &words(&line)).unwrap();
+        assert!(text.contains("duplicates skipped: 2"));
+        assert_eq!(load(&path).operations.len(), 3);
+
+        assert!(
+            execute(
+                &path,
+                &words(&format!(
+                    "import csv {} --profile Other --account Main",
+                    source
+                ))
+            )
+            .is_err()
+        );
+        remove(&source);
+        remove(&path);
+    }
+
+    #[test]
+    fn import_qif() {
+        let path = file(|_| {});
+        let source = temp("qif");
+        std::fs::write(&source, "!Type:Bank\nD03/25/2026\nT-100\nPМагазин\n^\n").unwrap();
+        let (code, text) = execute(&path, &words(&format!("import qif {}", source))).unwrap();
+        assert_eq!(code, 0);
+        assert!(text.starts_with("read: 1"));
+        assert_eq!(load(&path).operations.len(), 2);
+        assert!(execute(&path, &words(&format!("import xls {}", source))).is_err());
+        remove(&source);
+        remove(&path);
+    }
+
+    #[test]
+    fn report_monthly() {
+        let path = file(|_| {});
+        let (_, text) = execute(&path, &words("report monthly --year 2026 --format json")).unwrap();
+        let rows = json(&text);
+        assert_eq!(rows.as_array().unwrap().len(), 1);
+        assert_eq!(rows[0]["Income"], 100.0);
+        let (_, text) = execute(&path, &words("report monthly --year 2025 --format json")).unwrap();
+        assert!(json(&text).as_array().unwrap().is_empty());
+        assert!(execute(&path, &words("report monthly --year next")).is_err());
+        remove(&path);
+    }
+
+    #[test]
+    fn check_and_repair() {
+        let path = file(|_| {});
+        assert_eq!(execute(&path, &words("check")).unwrap().0, 0);
+        remove(&path);
+
+        let path = file(|db| {
+            let mut lost = db.operations[0].clone();
+            lost.id = Uuid::new_v4();
+            lost.account_id = Uuid::new_v4();
+            db.operations.push(lost);
+        });
+        assert_eq!(execute(&path, &words("check")).unwrap().0, 1);
+        let (code, _) = execute(&path, &words("check --repair --reassign Main")).unwrap();
+        assert_eq!(code, 0);
+        let db = load(&path);
+        assert_eq!(db.accounts.len(), 1);
+        assert!(
+            db.operations
+                .iter()
+                .all(|o| o.account_id == db.accounts[0].id)
+        );
+        remove(&path);
+    }
+
+    #[test]
+    fn convert_to_sqlite() {
+        let path = file(|_| {});
+        let target = temp("sqlite");
+        let (_, text) = execute(&path, &words(&format!("convert {}", target))).unwrap();
+        assert!(text.starts_with("converted"));
+        let db = load(&target);
+        assert_eq!(db.accounts.len(), 1);
+        assert_eq!(db.operations.len(), 1);
+        remove(&target);
+        remove(&path);
+    }
+}
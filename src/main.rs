@@ -6,14 +6,17 @@ use std::env;
 
 use crate::app::App;
 
-mod account;
 mod app;
-mod database;
-mod operation;
-mod receipt;
+mod cli;
 
 fn main() -> eframe::Result {
     let args: Vec<String> = env::args().collect();
+    if args.len() > 1 {
+        attach_console();
+    }
+    if let Some(code) = cli::run(&args[1..]) {
+        std::process::exit(code);
+    }
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([720.0, 480.0]),
@@ -31,3 +34,16 @@ fn main() -> eframe::Result {
         }),
     )
 }
+
+// В выпуске для Windows у программы нет своей консоли; команды пишут в консоль,
+// из которой их запустили
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{ATTACH_PARENT_PROCESS, AttachConsole};
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}